@@ -5,14 +5,22 @@ use std::{
     collections::BTreeMap,
     num::NonZeroUsize,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
-type BallotDrawKey = BlockHash;
+/// The block hash with the identity of the request, the block number or the batch id, since the
+/// batches proposed in the same L1 block share its hash.
+type BallotDrawKey = (BlockHash, u64);
 type BallotDrawResult = Option<ProofType>;
 /// max 8192 block hash cache for maximum 8192 blocks per day
 const CACHE_SIZE: usize = 8192;
 
 mod poisson;
+mod rules;
+mod simulate;
+
+pub use rules::{BallotRule, BlockCondition, BlockMetadata, RuleAction};
+pub use simulate::{simulate, BlockSample, DailyDraws, RateLimitedDraw, SimulationReport};
 
 /// Ballot is a proof type selection mechanism using the block hash.
 ///
@@ -28,8 +36,10 @@ pub struct Ballot {
     /// A PoissonDrawer to check if the proof type can be drawn
     /// based on the per-day limit and the last draw time
     poisson_drawer: PoissionDrawer,
-    /// A cache saves every block hash that has been drawn
+    /// A cache saves every request that has been drawn
     block_hash_cache: Arc<Mutex<LruCache<BallotDrawKey, BallotDrawResult>>>,
+    /// The rules on the block metadata, applied in order before the probabilities
+    rules: Vec<BallotRule>,
 }
 
 impl Default for Ballot {
@@ -56,15 +66,29 @@ impl Ballot {
             probabilities: probs,
             poisson_drawer: poisson_check,
             block_hash_cache,
+            rules: Vec::new(),
         };
         ballot.validate()?;
         Ok(ballot)
     }
 
+    /// Set the rules on the block metadata, keeping the draw state of the ballot.
+    pub fn with_rules(mut self, rules: Vec<BallotRule>) -> Result<Self, String> {
+        for rule in &rules {
+            rule.validate()?;
+        }
+        self.rules = rules;
+        Ok(self)
+    }
+
     pub fn probabilities(&self) -> &BTreeMap<ProofType, (f64, u64)> {
         &self.initial_config
     }
 
+    pub fn rules(&self) -> &[BallotRule] {
+        &self.rules
+    }
+
     pub fn validate(&self) -> Result<(), String> {
         // Validate each probability
         for (&proof_type, &prob) in self.probabilities.iter() {
@@ -87,8 +111,8 @@ impl Ballot {
         Ok(())
     }
 
-    /// Draw proof types based on the block hash.
-    fn draw(&self, block_hash: &BlockHash) -> BallotDrawResult {
+    /// Draw proof types based on the block hash, regardless of the per-day limits.
+    pub fn draw(&self, block_hash: &BlockHash) -> BallotDrawResult {
         let block_hash_bytes = block_hash.as_slice();
 
         // Take the last 16 bytes (least significant) and convert to u128
@@ -108,16 +132,67 @@ impl Ballot {
         res
     }
 
-    /// Draw proof types based on the block hash.
-    pub fn draw_with_poisson(&mut self, block_hash: &BlockHash) -> Option<ProofType> {
+    /// Draw proof types based on the block hash and the rules on the block metadata,
+    /// regardless of the per-day limits.
+    ///
+    /// The first `always` rule whose conditions hold picks the proof type. Otherwise the proof
+    /// type is drawn by the block hash, unless it has `only` rules and none of their conditions
+    /// hold, then nothing is drawn.
+    pub fn draw_for(&self, block_hash: &BlockHash, metadata: &BlockMetadata) -> BallotDrawResult {
+        if let Some(proof_type) = self.always_for(metadata) {
+            return Some(proof_type);
+        }
+
+        let proof_type = self.draw(block_hash)?;
+        let mut only_rules = self
+            .rules
+            .iter()
+            .filter(|rule| rule.action == RuleAction::Only && rule.proof_type == proof_type)
+            .peekable();
+        if only_rules.peek().is_some() && !only_rules.any(|rule| rule.when.holds(metadata)) {
+            return None;
+        }
+        Some(proof_type)
+    }
+
+    /// The proof type of the first `always` rule whose conditions hold on the block metadata.
+    ///
+    /// These proof types are requested whatever the per-day limits.
+    pub fn always_for(&self, metadata: &BlockMetadata) -> BallotDrawResult {
+        self.rules
+            .iter()
+            .find(|rule| rule.action == RuleAction::Always && rule.when.holds(metadata))
+            .map(|rule| rule.proof_type)
+    }
+
+    /// The minimal interval between two draws of the proof type, `None` when its draws are not
+    /// limited per day.
+    pub fn draw_interval(&self, proof_type: &ProofType) -> Option<Duration> {
+        self.poisson_drawer.interval(proof_type)
+    }
+
+    /// Draw proof types based on the block hash, once per request of the block identified by
+    /// `request_id`, its block number or batch id.
+    ///
+    /// The draws and the per-day limits are only kept in memory, so the results may differ
+    /// after a restart or between hosts. The proof types of `always` rules are not limited.
+    pub fn draw_with_poisson(
+        &mut self,
+        block_hash: &BlockHash,
+        request_id: u64,
+        metadata: &BlockMetadata,
+    ) -> Option<ProofType> {
+        let key = (*block_hash, request_id);
         let mut cache = self.block_hash_cache.lock().unwrap();
         // Check cache while holding the lock
-        if let Some(res) = cache.get(block_hash).cloned() {
+        if let Some(res) = cache.get(&key).cloned() {
             return res;
         }
 
-        let draw_result = self.draw(block_hash);
+        let draw_result = self.draw_for(block_hash, metadata);
         let res = match draw_result {
+            // The proof types of `always` rules are not limited per day
+            Some(ptype) if self.always_for(metadata).is_some() => Some(ptype),
             Some(ptype) => {
                 if self.poisson_drawer.poisson_freq_check(&ptype) {
                     Some(ptype)
@@ -128,7 +203,7 @@ impl Ballot {
             None => None,
         };
 
-        cache.put(*block_hash, res);
+        cache.put(key, res);
         res
     }
 }
@@ -228,7 +303,8 @@ mod tests {
         let mut proof_type_counts = BTreeMap::new();
         for u in 0..=u8::MAX {
             let block_hash = BlockHash::with_last_byte(u);
-            let proof_type = ballot.draw_with_poisson(&block_hash);
+            let proof_type =
+                ballot.draw_with_poisson(&block_hash, u as u64, &BlockMetadata::default());
             *proof_type_counts.entry(proof_type).or_insert(0) += 1;
         }
         assert_eq!(proof_type_counts.len(), 2);
@@ -242,7 +318,8 @@ mod tests {
         let mut proof_type_counts = BTreeMap::new();
         for u in 0..5 {
             let block_hash = BlockHash::with_last_byte(u);
-            let proof_type = ballot.draw_with_poisson(&block_hash);
+            let proof_type =
+                ballot.draw_with_poisson(&block_hash, u as u64, &BlockMetadata::default());
             *proof_type_counts.entry(proof_type).or_insert(0) += 1;
             sleep(std::time::Duration::from_secs(1));
         }
@@ -250,6 +327,75 @@ mod tests {
         assert_eq!(proof_type_counts[&Some(ProofType::Sp1)], 5);
     }
 
+    #[test]
+    fn test_draw_with_poisson_per_request() {
+        let mut ballot = Ballot::new(BTreeMap::from([(ProofType::Sp1, (1.0, 1))])).unwrap();
+        let block_hash = BlockHash::with_last_byte(1);
+        let metadata = BlockMetadata::default();
+
+        // The batches proposed in the same L1 block are drawn separately
+        assert_eq!(
+            ballot.draw_with_poisson(&block_hash, 1, &metadata),
+            Some(ProofType::Sp1)
+        );
+        assert_eq!(ballot.draw_with_poisson(&block_hash, 2, &metadata), None);
+        assert_eq!(
+            ballot.draw_with_poisson(&block_hash, 1, &metadata),
+            Some(ProofType::Sp1)
+        );
+    }
+
+    #[test]
+    fn test_draw_with_poisson_always_rules_are_not_limited() {
+        let mut ballot = Ballot::new(BTreeMap::from([(ProofType::Sp1, (1.0, 1))]))
+            .unwrap()
+            .with_rules(vec![BallotRule {
+                proof_type: ProofType::Sp1,
+                action: RuleAction::Always,
+                when: BlockCondition {
+                    min_batch_size: Some(10),
+                    ..Default::default()
+                },
+            }])
+            .unwrap();
+        let large_batch = BlockMetadata {
+            batch_size: Some(10),
+            ..Default::default()
+        };
+
+        for u in 0..5 {
+            let block_hash = BlockHash::with_last_byte(u);
+            assert_eq!(
+                ballot.draw_with_poisson(&block_hash, u as u64, &large_batch),
+                Some(ProofType::Sp1)
+            );
+        }
+        // The draws of the always rule do not count towards the per-day limit
+        assert_eq!(
+            ballot.draw_with_poisson(&BlockHash::with_last_byte(5), 5, &BlockMetadata::default()),
+            Some(ProofType::Sp1)
+        );
+        assert_eq!(
+            ballot.draw_with_poisson(&BlockHash::with_last_byte(6), 6, &BlockMetadata::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_draw_interval() {
+        let ballot = Ballot::new(BTreeMap::from([
+            (ProofType::Sp1, (0.5, 24)),
+            (ProofType::Risc0, (0.5, 0)),
+        ]))
+        .unwrap();
+        assert_eq!(
+            ballot.draw_interval(&ProofType::Sp1),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(ballot.draw_interval(&ProofType::Risc0), None);
+        assert_eq!(ballot.draw_interval(&ProofType::Native), None);
+    }
+
     #[test]
     fn test_draw_single_50_proof_types_with_0_poisson_check() {
         let ballot = Ballot::new(BTreeMap::from([(ProofType::Sp1, (0.5, 0))])).unwrap();
@@ -267,4 +413,72 @@ mod tests {
         );
         assert_eq!(proof_type_counts.get(&None), Some(&((u8::MAX / 2) + 1)));
     }
+
+    #[test]
+    fn test_draw_with_rules() {
+        let ballot = Ballot::new(BTreeMap::from([
+            (ProofType::Sp1, (0.5, 0)),
+            (ProofType::Risc0, (0.5, 0)),
+        ]))
+        .unwrap()
+        .with_rules(vec![
+            BallotRule {
+                proof_type: ProofType::Sp1,
+                action: RuleAction::Always,
+                when: BlockCondition {
+                    min_batch_size: Some(11),
+                    ..Default::default()
+                },
+            },
+            BallotRule {
+                proof_type: ProofType::Risc0,
+                action: RuleAction::Only,
+                when: BlockCondition {
+                    max_gas_used: Some(1_000_000),
+                    ..Default::default()
+                },
+            },
+        ])
+        .unwrap();
+        let sp1_hash = BlockHash::with_last_byte(0);
+        let risc0_hash = BlockHash::with_last_byte(u8::MAX);
+        assert_eq!(ballot.draw(&sp1_hash), Some(ProofType::Sp1));
+        assert_eq!(ballot.draw(&risc0_hash), Some(ProofType::Risc0));
+
+        let large_batch = BlockMetadata {
+            batch_size: Some(20),
+            gas_used: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(
+            ballot.draw_for(&risc0_hash, &large_batch),
+            Some(ProofType::Sp1)
+        );
+
+        let low_gas = BlockMetadata {
+            batch_size: Some(1),
+            gas_used: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(ballot.draw_for(&sp1_hash, &low_gas), Some(ProofType::Sp1));
+        assert_eq!(
+            ballot.draw_for(&risc0_hash, &low_gas),
+            Some(ProofType::Risc0)
+        );
+
+        // Risc0 is not drawn for high-gas or unknown-gas batches
+        let high_gas = BlockMetadata {
+            gas_used: Some(10_000_000),
+            ..low_gas
+        };
+        assert_eq!(ballot.draw_for(&risc0_hash, &high_gas), None);
+        assert_eq!(
+            ballot.draw_for(&risc0_hash, &BlockMetadata::default()),
+            None
+        );
+        assert_eq!(
+            ballot.draw_for(&sp1_hash, &BlockMetadata::default()),
+            Some(ProofType::Sp1)
+        );
+    }
 }
@@ -48,13 +48,27 @@ impl PoissionDrawer {
         self.per_day_limit.get(proof_type).unwrap_or(&0) > &0
     }
 
+    /// The interval between two draws of the proof type, if its draws are limited.
+    pub fn interval(&self, proof_type: &ProofType) -> Option<std::time::Duration> {
+        if !self.enabled(proof_type) {
+            return None;
+        }
+        let interval = *self.interval_secs.get(proof_type)?;
+        Some(std::time::Duration::from_secs(interval.max(0) as u64))
+    }
+
     /// Decide whether to trigger a proof for a given type based on last time and now
     pub fn poisson_freq_check(&mut self, proof_type: &ProofType) -> bool {
+        self.poisson_freq_check_at(proof_type, Utc::now())
+    }
+
+    /// Decide whether to trigger a proof for a given type based on last time and `now`, which
+    /// is the time of the replayed block when simulating a ballot.
+    pub fn poisson_freq_check_at(&mut self, proof_type: &ProofType, now: DateTime<Utc>) -> bool {
         if !self.enabled(proof_type) {
             return true;
         }
 
-        let now: DateTime<Utc> = Utc::now();
         let last_time: DateTime<Utc> = self
             .last_draw_time
             .get(proof_type)
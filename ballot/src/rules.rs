@@ -0,0 +1,181 @@
+use raiko_lib::proof_type::ProofType;
+use serde::{Deserialize, Serialize};
+
+/// What is known of the blocks of a request when drawing its proof type.
+///
+/// Unknown metadata is left unset, the conditions on it never hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlockMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_used: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_count: Option<u64>,
+    /// The number of L2 blocks to prove.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uses_blobs: Option<bool>,
+}
+
+/// Conditions on the block metadata, the rule applies when all the set ones hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockCondition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_gas_used: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gas_used: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_tx_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tx_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_batch_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_batch_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uses_blobs: Option<bool>,
+}
+
+impl BlockCondition {
+    pub fn holds(&self, metadata: &BlockMetadata) -> bool {
+        in_range(self.min_gas_used, self.max_gas_used, metadata.gas_used)
+            && in_range(self.min_tx_count, self.max_tx_count, metadata.tx_count)
+            && in_range(
+                self.min_batch_size,
+                self.max_batch_size,
+                metadata.batch_size,
+            )
+            && self
+                .uses_blobs
+                .is_none_or(|uses_blobs| metadata.uses_blobs == Some(uses_blobs))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, min, max) in [
+            ("gas_used", self.min_gas_used, self.max_gas_used),
+            ("tx_count", self.min_tx_count, self.max_tx_count),
+            ("batch_size", self.min_batch_size, self.max_batch_size),
+        ] {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    return Err(format!("Invalid range of {name}: min {min} > max {max}"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// A bound on unknown metadata never holds.
+fn in_range(min: Option<u64>, max: Option<u64>, value: Option<u64>) -> bool {
+    if min.is_none() && max.is_none() {
+        return true;
+    }
+    value.is_some_and(|value| {
+        min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Draw the proof type whenever the conditions hold, regardless of its probability.
+    Always,
+    /// Draw the proof type by its probability, only when the conditions hold.
+    Only,
+}
+
+/// A draw rule of the ballot depending on the block metadata, e.g. always Sp1 for batches
+/// above 10 blocks:
+///
+/// `{"proof_type": "Sp1", "action": "always", "when": {"min_batch_size": 11}}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BallotRule {
+    pub proof_type: ProofType,
+    pub action: RuleAction,
+    #[serde(default)]
+    pub when: BlockCondition,
+}
+
+impl BallotRule {
+    pub fn validate(&self) -> Result<(), String> {
+        self.when
+            .validate()
+            .map_err(|e| format!("Invalid rule for proof type {:?}: {e}", self.proof_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_condition_holds() {
+        let metadata = BlockMetadata {
+            gas_used: Some(1_000_000),
+            tx_count: Some(10),
+            batch_size: Some(4),
+            uses_blobs: Some(true),
+        };
+        assert!(BlockCondition::default().holds(&metadata));
+        assert!(BlockCondition::default().holds(&BlockMetadata::default()));
+
+        let condition = BlockCondition {
+            min_batch_size: Some(4),
+            max_gas_used: Some(1_000_000),
+            uses_blobs: Some(true),
+            ..Default::default()
+        };
+        assert!(condition.holds(&metadata));
+        assert!(!condition.holds(&BlockMetadata {
+            batch_size: Some(3),
+            ..metadata.clone()
+        }));
+        assert!(!condition.holds(&BlockMetadata {
+            uses_blobs: Some(false),
+            ..metadata.clone()
+        }));
+        // A condition on unknown metadata does not hold
+        assert!(!condition.holds(&BlockMetadata {
+            gas_used: None,
+            ..metadata
+        }));
+    }
+
+    #[test]
+    fn test_rule_deserialize() {
+        let serialized =
+            r#"{"proof_type": "Risc0", "action": "only", "when": {"max_gas_used": 1000}}"#;
+        let rule: BallotRule = serde_json::from_str(serialized).unwrap();
+        assert_eq!(
+            rule,
+            BallotRule {
+                proof_type: ProofType::Risc0,
+                action: RuleAction::Only,
+                when: BlockCondition {
+                    max_gas_used: Some(1000),
+                    ..Default::default()
+                },
+            }
+        );
+
+        let typo = r#"{"proof_type": "Risc0", "action": "only", "when": {"max_gas": 1000}}"#;
+        assert!(serde_json::from_str::<BallotRule>(typo).is_err());
+    }
+
+    #[test]
+    fn test_rule_validate() {
+        let rule = BallotRule {
+            proof_type: ProofType::Sp1,
+            action: RuleAction::Always,
+            when: BlockCondition {
+                min_tx_count: Some(10),
+                max_tx_count: Some(1),
+                ..Default::default()
+            },
+        };
+        assert!(rule.validate().is_err());
+    }
+}
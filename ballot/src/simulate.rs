@@ -0,0 +1,229 @@
+use crate::{poisson::PoissionDrawer, Ballot, BallotRule, BlockMetadata};
+use chrono::{DateTime, NaiveDate, Utc};
+use raiko_lib::{primitives::BlockHash, proof_type::ProofType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// A historical block the ballot draws a proof type for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSample {
+    pub block_hash: BlockHash,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub metadata: BlockMetadata,
+}
+
+/// The draws of a day of the simulation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyDraws {
+    pub date: NaiveDate,
+    pub blocks: u64,
+    /// The proofs which would have been requested, per proof type.
+    pub requested: BTreeMap<ProofType, u64>,
+    /// The draws dropped by the per-day limits, per proof type.
+    pub rate_limited: BTreeMap<ProofType, u64>,
+}
+
+/// A draw dropped by the per-day limit of its proof type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitedDraw {
+    pub block_hash: BlockHash,
+    pub timestamp: DateTime<Utc>,
+    pub proof_type: ProofType,
+}
+
+/// The outcome of replaying a ballot config over historical blocks.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub blocks: u64,
+    /// The proofs which would have been requested over all the days, per proof type.
+    pub requested: BTreeMap<ProofType, u64>,
+    /// The draws dropped by the per-day limits over all the days, per proof type.
+    pub rate_limited: BTreeMap<ProofType, u64>,
+    /// The days having blocks, in order.
+    pub days: Vec<DailyDraws>,
+    pub rate_limited_draws: Vec<RateLimitedDraw>,
+}
+
+/// Replay the ballot config and rules over the blocks in time order, as if they had been drawn
+/// at their timestamps. Like the ballot, a block hash drawn again is only counted once.
+pub fn simulate(
+    config: BTreeMap<ProofType, (f64, u64)>,
+    rules: Vec<BallotRule>,
+    blocks: impl IntoIterator<Item = BlockSample>,
+) -> Result<SimulationReport, String> {
+    let ballot = Ballot::new(config.clone())?.with_rules(rules)?;
+    let mut drawer = PoissionDrawer::new(config);
+    // Nothing was drawn before the first block
+    drawer.last_draw_time.clear();
+
+    let mut blocks = blocks.into_iter().collect::<Vec<_>>();
+    blocks.sort_by_key(|block| block.timestamp);
+
+    let mut drawn = HashSet::new();
+    let mut report = SimulationReport::default();
+    for block in blocks {
+        if !drawn.insert(block.block_hash) {
+            continue;
+        }
+        let date = block.timestamp.date_naive();
+        if report.days.last().map(|day| day.date) != Some(date) {
+            report.days.push(DailyDraws {
+                date,
+                blocks: 0,
+                requested: BTreeMap::new(),
+                rate_limited: BTreeMap::new(),
+            });
+        }
+        let day = report.days.last_mut().expect("the day is pushed above");
+        day.blocks += 1;
+        report.blocks += 1;
+
+        let Some(proof_type) = ballot.draw_for(&block.block_hash, &block.metadata) else {
+            continue;
+        };
+        // As when drawing, the proof types of `always` rules are not limited per day
+        if ballot.always_for(&block.metadata).is_some()
+            || drawer.poisson_freq_check_at(&proof_type, block.timestamp)
+        {
+            *day.requested.entry(proof_type).or_default() += 1;
+            *report.requested.entry(proof_type).or_default() += 1;
+        } else {
+            *day.rate_limited.entry(proof_type).or_default() += 1;
+            *report.rate_limited.entry(proof_type).or_default() += 1;
+            report.rate_limited_draws.push(RateLimitedDraw {
+                block_hash: block.block_hash,
+                timestamp: block.timestamp,
+                proof_type,
+            });
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockCondition, RuleAction};
+    use chrono::Duration;
+
+    fn sample(block_hash: BlockHash, timestamp: DateTime<Utc>) -> BlockSample {
+        BlockSample {
+            block_hash,
+            timestamp,
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_simulate_per_day_limit() {
+        let start = DateTime::from_timestamp(1_700_006_400, 0).unwrap();
+        assert_eq!(start.timestamp() % (24 * 3600), 0);
+        // A block every 12 seconds during two days
+        let blocks = (0..2 * 7200u64).map(|i| {
+            let mut hash = [0u8; 32];
+            hash[..8].copy_from_slice(&i.to_le_bytes());
+            sample(
+                BlockHash::from(hash),
+                start + Duration::seconds(12 * i as i64),
+            )
+        });
+
+        let report = simulate(
+            BTreeMap::from([(ProofType::Sp1, (1.0, 24))]),
+            vec![],
+            blocks,
+        )
+        .unwrap();
+        assert_eq!(report.blocks, 2 * 7200);
+        assert_eq!(report.days.len(), 2);
+        for day in &report.days {
+            assert_eq!(day.blocks, 7200);
+            assert_eq!(day.requested, BTreeMap::from([(ProofType::Sp1, 24)]));
+            assert_eq!(
+                day.rate_limited,
+                BTreeMap::from([(ProofType::Sp1, 7200 - 24)])
+            );
+        }
+        assert_eq!(report.requested, BTreeMap::from([(ProofType::Sp1, 48)]));
+        assert_eq!(report.rate_limited_draws.len(), 2 * (7200 - 24));
+        assert_eq!(
+            report.rate_limited_draws[0].timestamp,
+            start + Duration::seconds(12)
+        );
+    }
+
+    #[test]
+    fn test_simulate_without_limit() {
+        let start = DateTime::from_timestamp(1_700_006_400, 0).unwrap();
+        let blocks = (0..=u8::MAX).map(|u| {
+            sample(
+                BlockHash::with_last_byte(u),
+                start + Duration::seconds(u as i64),
+            )
+        });
+
+        let report =
+            simulate(BTreeMap::from([(ProofType::Sp1, (0.5, 0))]), vec![], blocks).unwrap();
+        assert_eq!(report.blocks, 256);
+        assert_eq!(report.requested, BTreeMap::from([(ProofType::Sp1, 128)]));
+        assert!(report.rate_limited.is_empty());
+        assert!(report.rate_limited_draws.is_empty());
+    }
+
+    #[test]
+    fn test_simulate_replays_blocks_in_order_once() {
+        let start = DateTime::from_timestamp(1_700_006_400, 0).unwrap();
+        let (first, second) = (BlockHash::with_last_byte(1), BlockHash::with_last_byte(2));
+        let blocks = vec![
+            sample(second, start + Duration::seconds(60)),
+            sample(first, start),
+            sample(first, start + Duration::seconds(120)),
+        ];
+
+        let report =
+            simulate(BTreeMap::from([(ProofType::Sp1, (1.0, 1))]), vec![], blocks).unwrap();
+        assert_eq!(report.blocks, 2);
+        assert_eq!(report.requested, BTreeMap::from([(ProofType::Sp1, 1)]));
+        assert_eq!(
+            report.rate_limited_draws,
+            vec![RateLimitedDraw {
+                block_hash: second,
+                timestamp: start + Duration::seconds(60),
+                proof_type: ProofType::Sp1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_simulate_invalid_config() {
+        assert!(simulate(BTreeMap::from([(ProofType::Sp1, (1.5, 1))]), vec![], vec![]).is_err());
+    }
+
+    #[test]
+    fn test_simulate_rules() {
+        let start = DateTime::from_timestamp(1_700_006_400, 0).unwrap();
+        let blocks = (0..10u8).map(|u| BlockSample {
+            block_hash: BlockHash::with_last_byte(u),
+            timestamp: start + Duration::seconds(u as i64),
+            metadata: BlockMetadata {
+                batch_size: Some(u as u64),
+                ..Default::default()
+            },
+        });
+        let rules = vec![BallotRule {
+            proof_type: ProofType::Risc0,
+            action: RuleAction::Always,
+            when: BlockCondition {
+                min_batch_size: Some(5),
+                ..Default::default()
+            },
+        }];
+
+        let report = simulate(BTreeMap::from([(ProofType::Sp1, (1.0, 0))]), rules, blocks).unwrap();
+        assert_eq!(
+            report.requested,
+            BTreeMap::from([(ProofType::Sp1, 5), (ProofType::Risc0, 5)])
+        );
+    }
+}
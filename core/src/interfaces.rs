@@ -1,4 +1,4 @@
-use crate::{merge, prover::NativeProver};
+use crate::{merge, prover::NativeProver, HeaderMismatch};
 use alloy_primitives::{Address, B256};
 use clap::Args;
 use raiko_lib::{
@@ -51,6 +51,11 @@ pub enum RaikoError {
     #[schema(value_type = Value)]
     Guest(#[from] ProverError),
 
+    /// For blocks whose re-executed header differs from the expected one.
+    #[error("Header mismatch for {0}")]
+    #[schema(value_type = Value)]
+    HeaderMismatch(HeaderMismatch),
+
     /// For db errors.
     #[error("There was an error with the db: {0}")]
     #[schema(value_type = Value)]
@@ -656,6 +661,9 @@ pub struct AggregationOnlyRequest {
     pub proofs: Vec<Proof>,
     /// The proof type.
     pub proof_type: Option<String>,
+    /// Unix timestamp in seconds after which the proof is worthless and its work is cancelled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u64>,
     #[serde(flatten)]
     /// Any additional prover params in JSON format.
     pub prover_args: ProverSpecificOpts,
@@ -676,6 +684,7 @@ impl From<(AggregationRequest, Vec<Proof>)> for AggregationOnlyRequest {
             proofs,
             aggregation_ids: request.block_numbers.iter().map(|(id, _)| *id).collect(),
             proof_type: request.proof_type,
+            deadline: None,
             prover_args: request.prover_args,
         }
     }
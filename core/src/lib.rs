@@ -1,24 +1,28 @@
-use std::{collections::HashMap, hint::black_box};
+use std::{collections::HashMap, fmt::Display};
 
 use alloy_primitives::Address;
 use alloy_rpc_types::EIP1186AccountProofResponse;
 use interfaces::{cancel_proof, run_batch_prover, run_prover};
 use raiko_lib::{
-    builder::{create_mem_db, RethBlockBuilder},
+    builder::{create_mem_db, for_each_batch_block, share_batch_state, RethBlockBuilder},
     consts::ChainSpec,
     input::{GuestBatchInput, GuestBatchOutput, GuestInput, GuestOutput, TaikoProverData},
+    mem_db::MemDb,
     protocol_instance::ProtocolInstance,
     prover::{IdStore, IdWrite, Proof, ProofKey},
-    utils::{generate_transactions, generate_transactions_for_batch_blocks},
+    utils::generate_transactions,
+    Measurement,
 };
-use reth_primitives::{Block, Header};
+use reth_primitives::{Block, Header, Receipt, TransactionSigned};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tracing::{debug, error, info, warn};
+use utoipa::ToSchema;
 
 use crate::{
     interfaces::{ProofRequest, RaikoError, RaikoResult},
     preflight::{batch_preflight, preflight, BatchPreflightData, PreflightData},
-    provider::BlockDataProvider,
+    provider::{BlockDataProvider, ReceiptSummary},
 };
 
 pub mod interfaces;
@@ -95,49 +99,27 @@ impl Raiko {
         //TODO: read fork from config
         let preflight_data = self.get_batch_preflight_data();
         info!("Generating batch input for batch {}", self.request.batch_id);
-        batch_preflight(provider, preflight_data)
-            .await
-            .map_err(Into::<RaikoError>::into)
+        let mut batch_input = batch_preflight(provider, preflight_data).await?;
+        // Carry the state needed by the blocks after the first one only once
+        let measurement = Measurement::start("Sharing batch state...", true);
+        share_batch_state(&mut batch_input);
+        measurement.stop();
+        Ok(batch_input)
     }
 
     pub fn get_output(&self, input: &GuestInput) -> RaikoResult<GuestOutput> {
-        let db = create_mem_db(&mut input.clone()).unwrap();
-        let mut builder = RethBlockBuilder::new(input, db);
-        let pool_tx = generate_transactions(
-            &input.chain_spec,
-            &input.taiko.block_proposed,
-            &input.taiko.tx_data,
-            &input.taiko.anchor_tx,
+        let header = build_header(input)?;
+        info!("Verifying final state using provider data ...");
+        info!(
+            "Final block hash derived successfully. {}",
+            header.hash_slow()
         );
-        builder
-            .execute_transactions(pool_tx, false)
-            .expect("execute");
-        let result = builder.finalize();
-
-        match result {
-            Ok(header) => {
-                info!("Verifying final state using provider data ...");
-                info!(
-                    "Final block hash derived successfully. {}",
-                    header.hash_slow()
-                );
-                debug!("Final block header derived successfully. {header:?}");
-                // Check if the header is the expected one
-                check_header(&input.block.header, &header)?;
-
-                Ok(GuestOutput {
-                    header: header.clone(),
-                    hash: ProtocolInstance::new(input, &header, self.request.proof_type)?
-                        .instance_hash(),
-                })
-            }
-            Err(e) => {
-                warn!("Proving bad block construction!");
-                Err(RaikoError::Guest(
-                    raiko_lib::prover::ProverError::GuestError(e.to_string()),
-                ))
-            }
-        }
+        debug!("Final block header derived successfully. {header:?}");
+
+        Ok(GuestOutput {
+            header: header.clone(),
+            hash: ProtocolInstance::new(input, &header, self.request.proof_type)?.instance_hash(),
+        })
     }
 
     pub fn get_batch_output(&self, batch_input: &GuestBatchInput) -> RaikoResult<GuestBatchOutput> {
@@ -145,16 +127,15 @@ impl Raiko {
             "Generating {} output for batch id: {}",
             self.request.proof_type, batch_input.taiko.batch_id
         );
-        let pool_txs_list = generate_transactions_for_batch_blocks(&batch_input.taiko);
-        let blocks = batch_input.inputs.iter().zip(pool_txs_list).try_fold(
-            Vec::new(),
-            |mut acc, input_and_txs| -> RaikoResult<Vec<Block>> {
-                let (input, pool_txs) = input_and_txs;
-                let output = self.single_output_for_batch(pool_txs, input)?;
-                acc.push(output);
-                Ok(acc)
-            },
-        )?;
+        let blocks = build_batch(batch_input)?;
+        for block in &blocks {
+            info!(
+                "Final block {} hash derived successfully. {}",
+                block.header.number,
+                block.header.hash_slow()
+            );
+            debug!("Final block derived successfully. {block:?}");
+        }
 
         blocks.windows(2).try_for_each(|window| {
             let parent = &window[0];
@@ -174,49 +155,6 @@ impl Raiko {
         })
     }
 
-    fn single_output_for_batch(
-        &self,
-        origin_pool_txs: Vec<reth_primitives::TransactionSigned>,
-        input: &GuestInput,
-    ) -> RaikoResult<Block> {
-        let db = create_mem_db(&mut input.clone()).unwrap();
-        let mut builder = RethBlockBuilder::new(input, db);
-
-        let mut pool_txs = vec![input.taiko.anchor_tx.clone().unwrap()];
-        pool_txs.extend_from_slice(&origin_pool_txs);
-
-        builder
-            .execute_transactions(pool_txs, false)
-            .expect("execute");
-        let result = builder.finalize_block();
-
-        match result {
-            Ok(block) => {
-                let header = block.header.clone();
-                info!(
-                    "Verifying final block {} state using provider data ...",
-                    header.number
-                );
-                info!(
-                    "Final block {} hash derived successfully. {}",
-                    header.number,
-                    header.hash_slow()
-                );
-                debug!("Final block derived successfully. {block:?}");
-                // Check if the header is the expected one
-                check_header(&input.block.header, &header)?;
-
-                Ok(block.clone())
-            }
-            Err(e) => {
-                warn!("Proving bad block construction!");
-                Err(RaikoError::Guest(
-                    raiko_lib::prover::ProverError::GuestError(e.to_string()),
-                ))
-            }
-        }
-    }
-
     pub async fn prove(
         &self,
         input: GuestInput,
@@ -246,79 +184,273 @@ impl Raiko {
     }
 }
 
-fn check_header(exp: &Header, header: &Header) -> Result<(), RaikoError> {
-    // Check against the expected value of all fields for easy debugability
-    check_eq(&exp.parent_hash, &header.parent_hash, "parent_hash");
-    check_eq(&exp.ommers_hash, &header.ommers_hash, "ommers_hash");
-    check_eq(&exp.beneficiary, &header.beneficiary, "beneficiary");
-    check_eq(&exp.state_root, &header.state_root, "state_root");
+/// Execute the transactions of a block input and build the resulting header.
+///
+/// Fails with [`RaikoError::HeaderMismatch`] when the header differs from the expected header
+/// of the input.
+pub fn build_header(input: &GuestInput) -> RaikoResult<Header> {
+    let (header, _, executed) = derive_block(input)?;
+    // Report the diverging fields first, they tell more than the failed execution checks
+    check_header(&input.block.header, &header)?;
+    executed.map_err(bad_block)?;
+    Ok(header)
+}
+
+/// Find the first transaction of the block whose receipt differs from the one of the node, to
+/// narrow down a header mismatch on the receipts.
+pub async fn locate_diverging_transaction<BDP: BlockDataProvider>(
+    provider: &BDP,
+    input: &GuestInput,
+) -> RaikoResult<Option<u64>> {
+    let (_, receipts, _) = derive_block(input)?;
+    let expected = provider.get_receipts(input.block.number).await?;
+    Ok(first_diverging_receipt(&expected, &receipts))
+}
+
+fn first_diverging_receipt(expected: &[ReceiptSummary], receipts: &[Receipt]) -> Option<u64> {
+    expected
+        .iter()
+        .zip(receipts)
+        .position(|(expected, receipt)| !expected.matches(receipt))
+        .or((expected.len() != receipts.len()).then(|| expected.len().min(receipts.len())))
+        .map(|index| index as u64)
+}
+
+/// Execute the transactions of a block input, keeping the derived header and receipts even when
+/// the checks after the execution fail.
+fn derive_block(input: &GuestInput) -> RaikoResult<(Header, Vec<Receipt>, anyhow::Result<()>)> {
+    let db = create_mem_db(&mut input.clone()).map_err(bad_block)?;
+    let mut builder = RethBlockBuilder::new(input, db);
+    let pool_tx = generate_transactions(
+        &input.chain_spec,
+        &input.taiko.block_proposed,
+        &input.taiko.tx_data,
+        &input.taiko.anchor_tx,
+    );
+    let (header, executed) = derive_header(&mut builder, pool_tx).map_err(bad_block)?;
+    Ok((header, builder.receipts, executed))
+}
+
+fn derive_header(
+    builder: &mut RethBlockBuilder<MemDb>,
+    pool_tx: Vec<TransactionSigned>,
+) -> anyhow::Result<(Header, anyhow::Result<()>)> {
+    let executed = builder.execute_transactions_unchecked(pool_tx)?;
+    Ok((builder.derived_header()?, executed))
+}
+
+/// A block of a batch executed by [`derive_batch_blocks`].
+#[derive(Debug)]
+pub struct DerivedBlock {
+    /// The expected block of the input.
+    pub block: Block,
+    /// The header resulting from the execution.
+    pub header: Header,
+    /// The result of the checks after the execution.
+    pub executed: anyhow::Result<()>,
+}
+
+/// Execute the blocks of a batch input, keeping the derived headers even when the checks after
+/// the execution of a block fail.
+pub fn derive_batch_blocks(batch_input: &GuestBatchInput) -> RaikoResult<Vec<DerivedBlock>> {
+    let mut derived = Vec::with_capacity(batch_input.inputs.len());
+    for_each_batch_block(batch_input, |builder, pool_tx| {
+        let (header, executed) = derive_header(builder, pool_tx)?;
+        derived.push(DerivedBlock {
+            block: builder.input.block.clone(),
+            header,
+            executed,
+        });
+        Ok(())
+    })
+    .map_err(bad_block)?;
+    Ok(derived)
+}
+
+/// Execute the blocks of a batch input and build the resulting blocks.
+///
+/// Fails with [`RaikoError::HeaderMismatch`] on the first block whose header differs from the
+/// expected header of its input.
+pub fn build_batch(batch_input: &GuestBatchInput) -> RaikoResult<Vec<Block>> {
+    derive_batch_blocks(batch_input)?
+        .into_iter()
+        .map(|derived| {
+            check_header(&derived.block.header, &derived.header)?;
+            derived.executed.map_err(bad_block)?;
+            Ok(derived.block)
+        })
+        .collect()
+}
+
+fn bad_block(e: anyhow::Error) -> RaikoError {
+    warn!("Proving bad block construction!");
+    RaikoError::Guest(raiko_lib::prover::ProverError::GuestError(e.to_string()))
+}
+
+/// A header field whose derived value differs from the expected one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub struct HeaderFieldMismatch {
+    pub field: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// The header derived by executing a block differs from the expected one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+pub struct HeaderMismatch {
+    pub block_number: u64,
+    pub fields: Vec<HeaderFieldMismatch>,
+    /// Index of the first transaction whose receipt diverges, when it could be located.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_index: Option<u64>,
+}
+
+impl HeaderMismatch {
+    /// Whether the receipts diverge, in which case the offending transaction can be located.
+    pub fn receipts_diverge(&self) -> bool {
+        self.fields.iter().any(|mismatch| {
+            matches!(
+                mismatch.field.as_str(),
+                "receipts_root" | "gas_used" | "logs_bloom"
+            )
+        })
+    }
+}
+
+impl Display for HeaderMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "block {}:", self.block_number)?;
+        for mismatch in &self.fields {
+            write!(
+                f,
+                " {} expected {}, found {};",
+                mismatch.field, mismatch.expected, mismatch.actual
+            )?;
+        }
+        if let Some(tx_index) = self.tx_index {
+            write!(f, " first diverging transaction {tx_index}")?;
+        }
+        Ok(())
+    }
+}
+
+/// All the fields of `header` that differ from the `exp`ected header.
+pub fn header_diff(exp: &Header, header: &Header) -> Vec<HeaderFieldMismatch> {
+    let mut diff = Vec::new();
+    check_eq(
+        &mut diff,
+        &exp.parent_hash,
+        &header.parent_hash,
+        "parent_hash",
+    );
+    check_eq(
+        &mut diff,
+        &exp.ommers_hash,
+        &header.ommers_hash,
+        "ommers_hash",
+    );
     check_eq(
+        &mut diff,
+        &exp.beneficiary,
+        &header.beneficiary,
+        "beneficiary",
+    );
+    check_eq(&mut diff, &exp.state_root, &header.state_root, "state_root");
+    check_eq(
+        &mut diff,
         &exp.transactions_root,
         &header.transactions_root,
         "transactions_root",
     );
-    check_eq(&exp.receipts_root, &header.receipts_root, "receipts_root");
     check_eq(
+        &mut diff,
+        &exp.receipts_root,
+        &header.receipts_root,
+        "receipts_root",
+    );
+    check_eq(
+        &mut diff,
         &exp.withdrawals_root,
         &header.withdrawals_root,
         "withdrawals_root",
     );
-    check_eq(&exp.logs_bloom, &header.logs_bloom, "logs_bloom");
-    check_eq(&exp.difficulty, &header.difficulty, "difficulty");
-    check_eq(&exp.number, &header.number, "number");
-    check_eq(&exp.gas_limit, &header.gas_limit, "gas_limit");
-    check_eq(&exp.gas_used, &header.gas_used, "gas_used");
-    check_eq(&exp.timestamp, &header.timestamp, "timestamp");
-    check_eq(&exp.mix_hash, &header.mix_hash, "mix_hash");
-    check_eq(&exp.nonce, &header.nonce, "nonce");
+    check_eq(&mut diff, &exp.logs_bloom, &header.logs_bloom, "logs_bloom");
+    check_eq(&mut diff, &exp.difficulty, &header.difficulty, "difficulty");
+    check_eq(&mut diff, &exp.number, &header.number, "number");
+    check_eq(&mut diff, &exp.gas_limit, &header.gas_limit, "gas_limit");
+    check_eq(&mut diff, &exp.gas_used, &header.gas_used, "gas_used");
+    check_eq(&mut diff, &exp.timestamp, &header.timestamp, "timestamp");
+    check_eq(&mut diff, &exp.mix_hash, &header.mix_hash, "mix_hash");
+    check_eq(&mut diff, &exp.nonce, &header.nonce, "nonce");
     check_eq(
+        &mut diff,
         &exp.base_fee_per_gas,
         &header.base_fee_per_gas,
         "base_fee_per_gas",
     );
-    check_eq(&exp.blob_gas_used, &header.blob_gas_used, "blob_gas_used");
     check_eq(
+        &mut diff,
+        &exp.blob_gas_used,
+        &header.blob_gas_used,
+        "blob_gas_used",
+    );
+    check_eq(
+        &mut diff,
         &exp.excess_blob_gas,
         &header.excess_blob_gas,
         "excess_blob_gas",
     );
     check_eq(
+        &mut diff,
         &exp.parent_beacon_block_root,
         &header.parent_beacon_block_root,
         "parent_beacon_block_root",
     );
-    check_eq(&exp.extra_data, &header.extra_data, "extra_data");
-
-    // Make sure the blockhash from the node matches the one from the builder
-    require_eq(
-        &exp.hash_slow(),
-        &header.hash_slow(),
-        &format!("block hash unexpected for block {}", exp.number),
-    )
+    check_eq(&mut diff, &exp.extra_data, &header.extra_data, "extra_data");
+    diff
 }
 
-fn check_eq<T: std::cmp::PartialEq + std::fmt::Debug>(expected: &T, actual: &T, message: &str) {
-    // printing out error, if any, but ignoring the result
-    // making sure it's not optimized out
-    let _ = black_box(require_eq(expected, actual, message));
-}
-
-fn require(expression: bool, message: &str) -> RaikoResult<()> {
-    if !expression {
-        let msg = format!("Assertion failed: {message}");
-        error!("{msg}");
-        return Err(anyhow::Error::msg(msg).into());
+fn check_eq<T: std::cmp::PartialEq + std::fmt::Debug>(
+    diff: &mut Vec<HeaderFieldMismatch>,
+    expected: &T,
+    actual: &T,
+    field: &str,
+) {
+    if expected != actual {
+        diff.push(HeaderFieldMismatch {
+            field: field.to_owned(),
+            expected: format!("{expected:?}"),
+            actual: format!("{actual:?}"),
+        });
     }
-    Ok(())
 }
 
-fn require_eq<T: std::cmp::PartialEq + std::fmt::Debug>(
-    expected: &T,
-    actual: &T,
-    message: &str,
-) -> RaikoResult<()> {
-    let msg = format!("{message} - Expected: {expected:?}, Found: {actual:?}");
-    require(expected == actual, &msg)
+fn check_header(exp: &Header, header: &Header) -> RaikoResult<()> {
+    // Check against the expected value of all fields for easy debugability
+    let mut fields = header_diff(exp, header);
+    // Make sure the blockhash from the node matches the one from the builder
+    let (exp_hash, hash) = (exp.hash_slow(), header.hash_slow());
+    if fields.is_empty() && exp_hash != hash {
+        fields.push(HeaderFieldMismatch {
+            field: "hash".to_owned(),
+            expected: format!("{exp_hash:?}"),
+            actual: format!("{hash:?}"),
+        });
+    }
+    if fields.is_empty() {
+        return Ok(());
+    }
+    for mismatch in &fields {
+        error!(
+            "Assertion failed: {} - Expected: {}, Found: {}",
+            mismatch.field, mismatch.expected, mismatch.actual
+        );
+    }
+    Err(RaikoError::HeaderMismatch(HeaderMismatch {
+        block_number: exp.number,
+        fields,
+        tx_index: None,
+    }))
 }
 
 /// Merges two json's together, overwriting `a` with the values of `b`
@@ -338,18 +470,24 @@ pub fn merge(a: &mut Value, b: &Value) {
 #[cfg(test)]
 mod tests {
     use crate::interfaces::aggregate_proofs;
-    use crate::preflight::parse_l1_batch_proposal_tx_for_pacaya_fork;
-    use crate::{interfaces::ProofRequest, provider::rpc::RpcBlockDataProvider, ChainSpec, Raiko};
-    use alloy_primitives::Address;
-    use alloy_provider::Provider;
+    use crate::preflight::{batch_preflight, parse_l1_batch_proposal_tx_for_pacaya_fork};
+    use crate::{
+        check_header, first_diverging_receipt, header_diff,
+        interfaces::{ProofRequest, RaikoError},
+        provider::{rpc::RpcBlockDataProvider, ReceiptSummary},
+        ChainSpec, HeaderFieldMismatch, Raiko,
+    };
+    use alloy_primitives::{Address, Bloom, U64};
     use env_logger;
     use raiko_lib::{
+        builder::{build_batch_blocks, share_batch_state},
         consts::{Network, SupportedChainSpecs},
-        input::{AggregationGuestInput, AggregationGuestOutput, BlobProofType},
+        input::{AggregationGuestInput, AggregationGuestOutput, BlobProofType, GuestBatchInput},
         primitives::B256,
         proof_type::ProofType,
         prover::Proof,
     };
+    use reth_primitives::{Header, Receipt};
     use serde_json::{json, Value};
     use std::{collections::HashMap, env, str::FromStr};
     use tracing::{debug, trace};
@@ -478,6 +616,64 @@ mod tests {
             .expect("proof generation failed")
     }
 
+    #[test]
+    fn test_header_diff() {
+        let expected = Header {
+            number: 1,
+            gas_used: 21000,
+            ..Default::default()
+        };
+        assert!(header_diff(&expected, &expected).is_empty());
+
+        let actual = Header {
+            gas_used: 42000,
+            ..expected.clone()
+        };
+        assert_eq!(
+            header_diff(&expected, &actual),
+            vec![HeaderFieldMismatch {
+                field: "gas_used".to_owned(),
+                expected: "21000".to_owned(),
+                actual: "42000".to_owned(),
+            }]
+        );
+
+        match check_header(&expected, &actual) {
+            Err(RaikoError::HeaderMismatch(mismatch)) => {
+                assert_eq!(mismatch.block_number, 1);
+                assert_eq!(mismatch.fields, header_diff(&expected, &actual));
+                assert!(mismatch.receipts_diverge());
+            }
+            result => panic!("unexpected header check result: {result:?}"),
+        }
+        assert!(check_header(&expected, &expected).is_ok());
+    }
+
+    #[test]
+    fn test_first_diverging_receipt() {
+        let receipts: Vec<Receipt> = [21000, 42000, 63000]
+            .into_iter()
+            .map(|cumulative_gas_used| Receipt {
+                success: true,
+                cumulative_gas_used,
+                ..Default::default()
+            })
+            .collect();
+        let mut expected: Vec<ReceiptSummary> = receipts
+            .iter()
+            .map(|receipt| ReceiptSummary {
+                status: U64::from(1),
+                cumulative_gas_used: U64::from(receipt.cumulative_gas_used),
+                logs_bloom: Bloom::ZERO,
+            })
+            .collect();
+        assert_eq!(first_diverging_receipt(&expected, &receipts), None);
+        assert_eq!(first_diverging_receipt(&expected, &receipts[..2]), Some(2));
+
+        expected[1].status = U64::from(0);
+        assert_eq!(first_diverging_receipt(&expected, &receipts), Some(1));
+    }
+
     #[ignore]
     #[tokio::test(flavor = "multi_thread")]
     async fn test_prove_batch_block_taiko_dev() {
@@ -610,7 +806,7 @@ mod tests {
 
     async fn get_recent_block_num(chain_spec: &ChainSpec) -> u64 {
         let provider = RpcBlockDataProvider::new(&chain_spec.rpc, 0).await.unwrap();
-        let height = provider.provider.get_block_number().await.unwrap();
+        let height = provider.get_block_number().await.unwrap();
         height - 100
     }
 
@@ -685,6 +881,96 @@ mod tests {
         }
     }
 
+    /// A batch of Taiko mainnet with its unshared input.
+    async fn pacaya_batch_input_taiko_mainnet() -> (Raiko, GuestBatchInput) {
+        let network = Network::TaikoMainnet.to_string();
+        let l1_network = Network::Ethereum.to_string();
+        let taiko_chain_spec = SupportedChainSpecs::default()
+            .get_chain_spec(&network)
+            .unwrap();
+        let l1_chain_spec = SupportedChainSpecs::default()
+            .get_chain_spec(&l1_network)
+            .unwrap();
+        let mut proof_request = ProofRequest {
+            block_number: 0,
+            batch_id: 1167616,
+            l1_inclusion_block_number: 22536616,
+            l2_block_numbers: Vec::new(),
+            network,
+            graffiti: B256::ZERO,
+            prover: Address::ZERO,
+            l1_network,
+            proof_type: ProofType::Native,
+            blob_proof_type: BlobProofType::ProofOfEquivalence,
+            prover_args: test_proof_params(false),
+        };
+        let block_numbers = parse_l1_batch_proposal_tx_for_pacaya_fork(
+            &l1_chain_spec,
+            &taiko_chain_spec,
+            proof_request.l1_inclusion_block_number,
+            proof_request.batch_id,
+        )
+        .await
+        .expect("Could not parse L1 batch proposal tx");
+        let provider = RpcBlockDataProvider::new_batch(
+            &taiko_chain_spec.rpc,
+            (block_numbers[0] - 1..=*block_numbers.last().unwrap()).collect(),
+        )
+        .await
+        .expect("Could not create RpcBlockDataProvider");
+        proof_request.l2_block_numbers = block_numbers;
+        let raiko = Raiko::new(l1_chain_spec, taiko_chain_spec, proof_request);
+
+        let input = batch_preflight(provider, raiko.get_batch_preflight_data())
+            .await
+            .expect("input generation failed");
+        (raiko, input)
+    }
+
+    #[ignore]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_shared_batch_state_taiko_mainnet() {
+        let (_, input) = pacaya_batch_input_taiko_mainnet().await;
+        let mut shared_input = input.clone();
+        share_batch_state(&mut shared_input);
+        assert!(!shared_input.shared_state.is_empty());
+        // The guest gets the input through bincode
+        let shared_input: GuestBatchInput =
+            bincode::deserialize(&bincode::serialize(&shared_input).unwrap()).unwrap();
+
+        let blocks = build_batch_blocks(&input).unwrap();
+        let shared_blocks = build_batch_blocks(&shared_input).unwrap();
+        assert_eq!(blocks.len(), shared_blocks.len());
+        for (block, shared_block) in blocks.iter().zip(shared_blocks.iter()) {
+            assert_eq!(block.header.state_root, shared_block.header.state_root);
+            assert_eq!(block.header.hash_slow(), shared_block.header.hash_slow());
+        }
+    }
+
+    #[ignore]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_batch_header_mismatch_taiko_mainnet() {
+        let (raiko, mut input) = pacaya_batch_input_taiko_mainnet().await;
+        let last = input.inputs.len() - 1;
+        input.inputs[last].block.header.gas_used += 1;
+        share_batch_state(&mut input);
+
+        match raiko.get_batch_output(&input) {
+            Err(RaikoError::HeaderMismatch(mismatch)) => {
+                assert_eq!(mismatch.block_number, input.inputs[last].block.number);
+                assert_eq!(
+                    mismatch
+                        .fields
+                        .iter()
+                        .map(|field| field.field.as_str())
+                        .collect::<Vec<_>>(),
+                    vec!["gas_used"]
+                );
+            }
+            other => panic!("expected a header mismatch, got {other:?}"),
+        }
+    }
+
     #[ignore = "holesky down"]
     #[tokio::test(flavor = "multi_thread")]
     async fn test_prove_block_taiko_a7_aggregated() {
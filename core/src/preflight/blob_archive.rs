@@ -0,0 +1,160 @@
+use alloy_primitives::B256;
+use raiko_lib::{
+    consts::ChainSpec,
+    primitives::eip4844::{calc_kzg_proof_commitment, commitment_to_version_hash},
+};
+use std::{
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+use super::util::{block_time_to_block_slot, get_blobs};
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    provider::{rpc::RpcBlockDataProvider, BlockDataProvider},
+};
+
+/// Environment variable enabling the blob archive, set to the directory to store blobs in.
+pub const BLOB_ARCHIVE_DIR_ENV: &str = "BLOB_ARCHIVE_DIR";
+
+/// Blobs stored on disk by versioned hash, so that they can still be proven after the beacon
+/// nodes pruned their sidecars.
+#[derive(Debug, Clone)]
+pub struct BlobArchive {
+    dir: PathBuf,
+}
+
+impl BlobArchive {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// The archive configured with `BLOB_ARCHIVE_DIR`, if any.
+    pub fn from_env() -> Option<Self> {
+        std::env::var(BLOB_ARCHIVE_DIR_ENV)
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(Self::new)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn blob_path(&self, blob_hash: &B256) -> PathBuf {
+        self.dir.join(format!("{blob_hash}.blob"))
+    }
+
+    /// Load the blob with the given versioned hash.
+    ///
+    /// A blob that does not match its versioned hash is removed from the archive and
+    /// treated as missing.
+    pub fn load(&self, blob_hash: &B256) -> Option<Vec<u8>> {
+        let path = self.blob_path(blob_hash);
+        let blob = fs::read(&path).ok()?;
+        match calc_kzg_proof_commitment(&blob) {
+            Ok(commitment) if commitment_to_version_hash(&commitment) == *blob_hash => Some(blob),
+            _ => {
+                warn!("Archived blob {blob_hash} is corrupted, removing it");
+                let _ = fs::remove_file(path);
+                None
+            }
+        }
+    }
+
+    /// Load all the given blobs, or `None` if any of them is missing.
+    pub fn load_all(&self, blob_hashes: &[B256]) -> Option<Vec<Vec<u8>>> {
+        blob_hashes.iter().map(|hash| self.load(hash)).collect()
+    }
+
+    pub fn store(&self, blob_hash: &B256, blob: &[u8]) -> RaikoResult<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first so a crash never leaves a truncated blob behind.
+        let path = self.blob_path(blob_hash);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, blob)?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Fetch and archive the blobs of all the blob transactions in the given range of L1 blocks.
+///
+/// Returns the number of blobs archived, including the ones that were already there.
+pub async fn prefetch_blobs(
+    archive: &BlobArchive,
+    l1_chain_spec: &ChainSpec,
+    blocks: RangeInclusive<u64>,
+) -> RaikoResult<usize> {
+    let provider =
+        RpcBlockDataProvider::new_with_fallbacks(&l1_chain_spec.rpc_endpoints(), 0).await?;
+    let mut archived = 0;
+    for block_number in blocks {
+        let block = provider
+            .get_blocks(&[(block_number, true)])
+            .await?
+            .pop()
+            .ok_or_else(|| RaikoError::RPC(format!("No block {block_number}")))?;
+        let blob_hashes: Vec<B256> = block
+            .transactions
+            .as_transactions()
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|tx| tx.blob_versioned_hashes.clone().unwrap_or_default())
+            .collect();
+        if blob_hashes.is_empty() {
+            continue;
+        }
+
+        let missing: Vec<B256> = blob_hashes
+            .iter()
+            .filter(|hash| !archive.blob_path(hash).exists())
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            let slot_id = block_time_to_block_slot(
+                block.header.timestamp,
+                l1_chain_spec.genesis_time,
+                l1_chain_spec.seconds_per_slot,
+            )?;
+            get_blobs(l1_chain_spec, slot_id, &missing, Some(archive)).await?;
+        }
+        info!(
+            "Archived {} blobs of block {block_number} ({} new)",
+            blob_hashes.len(),
+            missing.len()
+        );
+        archived += blob_hashes.len();
+    }
+    Ok(archived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_archive_verifies_on_load() {
+        let dir = std::env::temp_dir().join(format!("raiko-blob-archive-{}", std::process::id()));
+        let archive = BlobArchive::new(&dir);
+
+        let blob = vec![0u8; 131072];
+        let blob_hash = commitment_to_version_hash(&calc_kzg_proof_commitment(&blob).unwrap());
+        archive.store(&blob_hash, &blob).unwrap();
+        assert_eq!(archive.load(&blob_hash), Some(blob.clone()));
+        assert_eq!(archive.load_all(&[blob_hash]), Some(vec![blob.clone()]));
+
+        // A blob stored under the wrong hash is dropped
+        let wrong_hash = B256::with_last_byte(1);
+        archive.store(&wrong_hash, &blob).unwrap();
+        assert_eq!(archive.load(&wrong_hash), None);
+        assert!(!archive.blob_path(&wrong_hash).exists());
+        assert_eq!(archive.load_all(&[blob_hash, wrong_hash]), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,486 @@
+use alloy_primitives::{hex, FixedBytes, B256};
+use anyhow::{anyhow, ensure, Result};
+use kzg_traits::{
+    eip_4844::{blob_to_kzg_commitment_rust, Blob},
+    G1,
+};
+use raiko_lib::primitives::eip4844::{self, commitment_to_version_hash, KZG_SETTINGS};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::interfaces::RaikoError;
+
+/// A place to get EIP-4844 blobs from.
+#[async_trait::async_trait]
+pub trait BlobSource: Send + Sync {
+    /// The endpoint of this source.
+    fn url(&self) -> &str;
+
+    /// Get the blobs with the versioned hashes `blob_hashes` that were posted in the beacon
+    /// slot `slot_id`, in the same order.
+    async fn get_blobs(&self, slot_id: u64, blob_hashes: &[B256]) -> Result<Vec<Vec<u8>>>;
+}
+
+/// Get the blob source serving the given url.
+///
+/// Urls of blobscan.com, or any other blobscan instance when prefixed with `blobscan+`
+/// (e.g. `blobscan+https://blobscan.example.org/api`), are served by the blobscan API,
+/// everything else is treated as a beacon node.
+pub fn blob_source(url: &str) -> Box<dyn BlobSource> {
+    if let Some(url) = url.strip_prefix("blobscan+") {
+        Box::new(BlobscanBlobSource::new(url))
+    } else if url.contains("blobscan.com") {
+        Box::new(BlobscanBlobSource::new(url))
+    } else {
+        Box::new(BeaconBlobSource::new(url))
+    }
+}
+
+/// The error of a request answered with a failure `status`, an RPC error when the source may
+/// serve it once it recovered.
+fn status_error(url: &str, status: StatusCode) -> anyhow::Error {
+    warn!("Request {url} failed with status code: {status}");
+    let message = format!("Request failed with status code: {status}");
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        RaikoError::RPC(message).into()
+    } else {
+        anyhow!(message)
+    }
+}
+
+pub fn blob_to_bytes(blob_str: &str) -> Vec<u8> {
+    hex::decode(blob_str.to_lowercase().trim_start_matches("0x")).unwrap_or_default()
+}
+
+fn calc_blob_versioned_hash(blob: &[u8]) -> Result<B256> {
+    let blob = Blob::from_bytes(blob).map_err(|e| anyhow!("Could not create blob: {e:?}"))?;
+    let commitment = blob_to_kzg_commitment_rust(
+        &eip4844::deserialize_blob_rust(&blob)
+            .map_err(|e| anyhow!("Could not deserialize blob: {e:?}"))?,
+        &KZG_SETTINGS.clone(),
+    )
+    .map_err(|e| anyhow!("Could not create kzg commitment from blob: {e:?}"))?;
+    Ok(commitment_to_version_hash(&commitment.to_bytes()))
+}
+
+/// Environment variable enabling the check of the `kzg_commitment_inclusion_proof` of the
+/// sidecars served by beacon nodes.
+pub const VERIFY_BLOB_INCLUSION_PROOF_ENV: &str = "VERIFY_BLOB_INCLUSION_PROOF";
+
+/// Depth of the merkle branch proving a commitment in `blob_kzg_commitments` of the beacon
+/// block body.
+const KZG_COMMITMENT_INCLUSION_PROOF_DEPTH: usize = 17;
+/// Index of the first commitment of `blob_kzg_commitments` at that depth: the list is field
+/// 11 of the body (16 leaves), its data tree is the left child of its root, and it holds up to
+/// 4096 commitments.
+const KZG_COMMITMENT_SUBTREE_INDEX: u64 = ((16 + 11) * 2 - 32) * 4096;
+
+// Blob data from the beacon chain
+// type Sidecar struct {
+// Index                    string                   `json:"index"`
+// Blob                     string                   `json:"blob"`
+// SignedBeaconBlockHeader  *SignedBeaconBlockHeader `json:"signed_block_header"`
+// KzgCommitment            string                   `json:"kzg_commitment"`
+// KzgProof                 string                   `json:"kzg_proof"`
+// CommitmentInclusionProof []string
+// `json:"kzg_commitment_inclusion_proof"` }
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct GetBlobData {
+    pub index: String,
+    pub blob: String,
+    #[serde(default)]
+    pub signed_block_header: Option<SignedBeaconBlockHeader>,
+    pub kzg_commitment: FixedBytes<48>,
+    pub kzg_proof: FixedBytes<48>,
+    #[serde(default)]
+    pub kzg_commitment_inclusion_proof: Vec<B256>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct SignedBeaconBlockHeader {
+    pub message: BeaconBlockHeader,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct BeaconBlockHeader {
+    pub slot: String,
+    pub body_root: B256,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct GetBlobsResponse {
+    pub data: Vec<GetBlobData>,
+}
+
+impl GetBlobData {
+    fn versioned_hash(&self) -> B256 {
+        commitment_to_version_hash(&self.kzg_commitment.0)
+    }
+
+    /// Check that the blob matches the commitment, using the proof sent along with it.
+    fn verify_blob(&self, blob: &[u8]) -> Result<()> {
+        let valid = eip4844::verify_blob_kzg_proof(blob, &self.kzg_commitment.0, &self.kzg_proof.0)
+            .map_err(|e| anyhow!("Could not verify blob kzg proof: {e}"))?;
+        ensure!(valid, "invalid kzg proof for blob {}", self.index);
+        Ok(())
+    }
+
+    /// Check that the commitment is part of the body of the block at `slot_id`.
+    fn verify_inclusion_proof(&self, slot_id: u64) -> Result<()> {
+        let header = &self
+            .signed_block_header
+            .as_ref()
+            .ok_or_else(|| anyhow!("No signed block header for blob {}", self.index))?
+            .message;
+        ensure!(
+            header.slot == slot_id.to_string(),
+            "blob {} belongs to slot {}, expected {slot_id}",
+            self.index,
+            header.slot
+        );
+        let index: u64 = self.index.parse()?;
+        ensure!(
+            is_valid_merkle_branch(
+                commitment_hash_tree_root(&self.kzg_commitment.0),
+                &self.kzg_commitment_inclusion_proof,
+                KZG_COMMITMENT_INCLUSION_PROOF_DEPTH,
+                KZG_COMMITMENT_SUBTREE_INDEX + index,
+                header.body_root,
+            ),
+            "invalid kzg commitment inclusion proof for blob {}",
+            self.index
+        );
+        Ok(())
+    }
+}
+
+/// SSZ hash tree root of a 48 bytes commitment, packed into two chunks.
+fn commitment_hash_tree_root(commitment: &[u8; 48]) -> B256 {
+    let mut chunks = [0u8; 64];
+    chunks[..48].copy_from_slice(commitment);
+    B256::from_slice(&Sha256::digest(chunks))
+}
+
+/// `is_valid_merkle_branch` of the consensus specs.
+fn is_valid_merkle_branch(
+    leaf: B256,
+    branch: &[B256],
+    depth: usize,
+    index: u64,
+    root: B256,
+) -> bool {
+    if branch.len() != depth {
+        return false;
+    }
+    let value = branch.iter().enumerate().fold(leaf, |value, (i, node)| {
+        let mut hasher = Sha256::new();
+        if (index >> i) & 1 == 1 {
+            hasher.update(node);
+            hasher.update(value);
+        } else {
+            hasher.update(value);
+            hasher.update(node);
+        }
+        B256::from_slice(&hasher.finalize())
+    });
+    value == root
+}
+
+/// Blobs from the `blob_sidecars` endpoint of a beacon node, only available until the node
+/// prunes them after the retention window.
+pub struct BeaconBlobSource {
+    url: String,
+    verify_inclusion_proof: bool,
+}
+
+impl BeaconBlobSource {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_owned(),
+            verify_inclusion_proof: std::env::var(VERIFY_BLOB_INCLUSION_PROOF_ENV)
+                .is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true")),
+        }
+    }
+
+    /// Also check that every returned commitment is included in the body of its block.
+    pub fn with_inclusion_proof(mut self, verify_inclusion_proof: bool) -> Self {
+        self.verify_inclusion_proof = verify_inclusion_proof;
+        self
+    }
+
+    async fn get_blob_sidecars(&self, slot_id: u64) -> Result<GetBlobsResponse> {
+        let url = format!("{}/eth/v1/beacon/blob_sidecars/{slot_id}", self.url);
+        info!("Retrieve blob from {url}.");
+        let response = reqwest::get(url.clone()).await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(&url, response.status()));
+        }
+
+        let blobs = response.json::<GetBlobsResponse>().await?;
+        ensure!(!blobs.data.is_empty(), "blob data not available anymore");
+        Ok(blobs)
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobSource for BeaconBlobSource {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn get_blobs(&self, slot_id: u64, blob_hashes: &[B256]) -> Result<Vec<Vec<u8>>> {
+        info!("Retrieve blobs for slot {slot_id} and expect {blob_hashes:?}.");
+        let sidecars = self.get_blob_sidecars(slot_id).await?;
+        blob_hashes
+            .iter()
+            .map(|blob_hash| {
+                // Match on the commitments the node sent, and only check the selected blobs
+                let sidecar = sidecars
+                    .data
+                    .iter()
+                    .find(|sidecar| sidecar.versioned_hash() == *blob_hash)
+                    .ok_or_else(|| anyhow!("couldn't find blob data matching blob hash"))?;
+                if self.verify_inclusion_proof {
+                    sidecar.verify_inclusion_proof(slot_id)?;
+                }
+                let blob = blob_to_bytes(&sidecar.blob);
+                sidecar.verify_blob(&blob)?;
+                Ok(blob)
+            })
+            .collect()
+    }
+}
+
+// https://api.blobscan.com/#/
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct BlobScanData {
+    pub commitment: String,
+    pub data: String,
+}
+
+/// Blobs from a blobscan indexer, which keeps them after beacon nodes pruned them.
+pub struct BlobscanBlobSource {
+    url: String,
+}
+
+impl BlobscanBlobSource {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    async fn get_blob(&self, blob_hash: &B256) -> Result<Vec<u8>> {
+        let url = format!("{}/blobs/{blob_hash}", self.url);
+        info!("Retrieve blob from {url}.");
+        let response = reqwest::get(url.clone()).await?;
+
+        if !response.status().is_success() {
+            return Err(status_error(&url, response.status()));
+        }
+
+        let blob = blob_to_bytes(&response.json::<BlobScanData>().await?.data);
+        // Blobscan is a third party indexer, don't take its word for the content
+        ensure!(
+            calc_blob_versioned_hash(&blob)? == *blob_hash,
+            "blobscan returned a blob not matching blob hash {blob_hash}"
+        );
+        Ok(blob)
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobSource for BlobscanBlobSource {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn get_blobs(&self, _slot_id: u64, blob_hashes: &[B256]) -> Result<Vec<Vec<u8>>> {
+        let mut blobs = Vec::with_capacity(blob_hashes.len());
+        for blob_hash in blob_hashes {
+            blobs.push(self.get_blob(blob_hash).await?);
+        }
+        Ok(blobs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    fn hash_pair(left: B256, right: B256) -> B256 {
+        B256::from_slice(&Sha256::digest([left.0, right.0].concat()))
+    }
+
+    #[test]
+    fn test_kzg_commitment_inclusion_proof() {
+        let commitment = [0xc0; 48];
+        let branch: Vec<B256> = (0..KZG_COMMITMENT_INCLUSION_PROOF_DEPTH as u8)
+            .map(B256::repeat_byte)
+            .collect();
+        let blob_index = 3u64;
+        let index = KZG_COMMITMENT_SUBTREE_INDEX + blob_index;
+        let root = branch.iter().enumerate().fold(
+            commitment_hash_tree_root(&commitment),
+            |value, (i, node)| {
+                if (index >> i) & 1 == 1 {
+                    hash_pair(*node, value)
+                } else {
+                    hash_pair(value, *node)
+                }
+            },
+        );
+
+        let mut sidecar = GetBlobData {
+            index: blob_index.to_string(),
+            blob: String::new(),
+            signed_block_header: Some(SignedBeaconBlockHeader {
+                message: BeaconBlockHeader {
+                    slot: "100".to_owned(),
+                    body_root: root,
+                },
+            }),
+            kzg_commitment: FixedBytes(commitment),
+            kzg_proof: FixedBytes([0; 48]),
+            kzg_commitment_inclusion_proof: branch,
+        };
+        assert!(sidecar.verify_inclusion_proof(100).is_ok());
+        assert!(sidecar.verify_inclusion_proof(101).is_err());
+
+        // The same branch does not prove the commitment at another index
+        sidecar.index = "4".to_owned();
+        assert!(sidecar.verify_inclusion_proof(100).is_err());
+    }
+
+    /// Serve `route(path)` as `(status, json body)` on a local port, recording the requested
+    /// paths.
+    async fn serve_stub(
+        route: impl Fn(&str) -> (u16, String) + Send + Sync + 'static,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let paths = requested.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                paths.lock().unwrap().push(path.to_owned());
+                let (status, body) = route(path);
+                let response = format!(
+                    "HTTP/1.1 {status} Stub\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requested)
+    }
+
+    fn zero_blob() -> Vec<u8> {
+        vec![0; 131072]
+    }
+
+    fn blobscan_body(blob: &[u8]) -> String {
+        serde_json::to_string(&BlobScanData {
+            commitment: String::new(),
+            data: format!("0x{}", hex::encode(blob)),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_blobscan_get_blobs() {
+        let blob_hash = calc_blob_versioned_hash(&zero_blob()).unwrap();
+        let (url, requested) = serve_stub(|_| (200, blobscan_body(&zero_blob()))).await;
+
+        let source = blob_source(&format!("blobscan+{url}/api/"));
+        assert_eq!(source.url(), format!("{url}/api"));
+        let blobs = source.get_blobs(7, &[blob_hash, blob_hash]).await.unwrap();
+        assert_eq!(blobs, vec![zero_blob(), zero_blob()]);
+        assert_eq!(
+            *requested.lock().unwrap(),
+            vec![format!("/api/blobs/{blob_hash}"); 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blobscan_rejects_wrong_blob() {
+        let (url, _) = serve_stub(|_| (200, blobscan_body(&zero_blob()))).await;
+        let error = blob_source(&format!("blobscan+{url}"))
+            .get_blobs(7, &[B256::repeat_byte(0x01)])
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("not matching blob hash"));
+    }
+
+    #[tokio::test]
+    async fn test_blobscan_malformed_response() {
+        let (url, _) = serve_stub(|_| (200, r#"{"commitment":"0x00"}"#.to_owned())).await;
+        let error = blob_source(&format!("blobscan+{url}"))
+            .get_blobs(7, &[B256::ZERO])
+            .await
+            .unwrap_err();
+        assert!(error.chain().any(|cause| cause.is::<reqwest::Error>()));
+    }
+
+    #[tokio::test]
+    async fn test_status_errors() {
+        let (url, _) = serve_stub(|path| {
+            if path.contains("pruned") {
+                (404, r#"{"message":"Blob not found"}"#.to_owned())
+            } else {
+                (503, String::new())
+            }
+        })
+        .await;
+
+        // A missing blob won't show up on retry, an overloaded node may serve it later
+        let error = blob_source(&format!("blobscan+{url}/pruned"))
+            .get_blobs(7, &[B256::ZERO])
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<RaikoError>().is_none());
+        let error = blob_source(&format!("blobscan+{url}/overloaded"))
+            .get_blobs(7, &[B256::ZERO])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<RaikoError>(),
+            Some(RaikoError::RPC(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_beacon_sidecars_not_available() {
+        let (url, requested) = serve_stub(|_| (200, r#"{"data":[]}"#.to_owned())).await;
+        let error = blob_source(&url)
+            .get_blobs(42, &[B256::ZERO])
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("not available"));
+        assert_eq!(
+            *requested.lock().unwrap(),
+            vec!["/eth/v1/beacon/blob_sidecars/42".to_owned()]
+        );
+    }
+}
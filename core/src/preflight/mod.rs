@@ -18,17 +18,23 @@ use reth_primitives::TransactionSigned;
 use tracing::{debug, info};
 
 use util::{
-    execute_txs, get_batch_blocks_and_parent_data, get_block_and_parent_data,
+    execute_txs, get_batch_blocks_and_parent_data, get_block_and_parent_data, get_witness_state,
     prepare_taiko_chain_batch_input, prepare_taiko_chain_input,
 };
 
-pub use util::parse_l1_batch_proposal_tx_for_pacaya_fork;
+pub use util::{
+    parse_l1_batch_proposal_for_pacaya_fork, parse_l1_batch_proposal_tx_for_pacaya_fork,
+};
 
 #[cfg(feature = "statedb_lru")]
-use lru::{load_state_db, save_state_db};
+use state_cache::{load_state_db, save_state_db};
+#[cfg(feature = "statedb_lru")]
+pub use state_cache::{state_cache_stats, StateCacheStats};
 #[cfg(feature = "statedb_lru")]
-mod lru;
+mod state_cache;
 
+pub mod blob_archive;
+pub mod blob_source;
 mod util;
 
 pub struct PreflightData {
@@ -125,7 +131,7 @@ pub async fn preflight<BDP: BlockDataProvider>(
     let initial_db_with_headers = None;
 
     // Create the block builder, run the transactions and extract the DB
-    let provider_db = ProviderDb::new(
+    let mut provider_db = ProviderDb::new(
         &provider,
         taiko_chain_spec,
         parent_block_number,
@@ -133,6 +139,23 @@ pub async fn preflight<BDP: BlockDataProvider>(
     )
     .await?;
 
+    // Build the state straight from an execution witness when the node provides one
+    if let Some(witness_state) =
+        get_witness_state(&provider, block_number, input.parent_header.state_root).await
+    {
+        let measurement = Measurement::start("Fetching historical block headers...", true);
+        let ancestor_headers = provider_db.get_ancestor_headers().await?;
+        measurement.stop();
+
+        return Ok(GuestInput {
+            parent_state_trie: witness_state.parent_state_trie,
+            parent_storage: witness_state.parent_storage,
+            contracts: witness_state.contracts,
+            ancestor_headers,
+            ..input
+        });
+    }
+
     // Now re-execute the transactions in the block to collect all required data
     let mut builder = RethBlockBuilder::new(&input, provider_db);
 
@@ -151,7 +174,7 @@ pub async fn preflight<BDP: BlockDataProvider>(
         #[cfg(feature = "statedb_lru")]
         save_state_db(
             (parent_block_number + 1, block.hash_slow()),
-            (db.current_db.clone(), {
+            (db.post_state(block.hash_slow()), {
                 let mut current_headers = db.initial_headers.clone();
                 current_headers.insert(block_number, block.header.clone());
                 current_headers
@@ -314,13 +337,15 @@ pub async fn batch_preflight<BDP: BlockDataProvider>(
                 };
 
                 let provider_target_blocks = vec![parent_block_number, parent_block_number + 1];
-                let provider =
-                    RpcBlockDataProvider::new_batch(&taiko_chain_spec.rpc, provider_target_blocks)
-                        .await
-                        .expect("Could not create RpcBlockDataProvider");
+                let provider = RpcBlockDataProvider::new_batch_with_fallbacks(
+                    &taiko_chain_spec.rpc_endpoints(),
+                    provider_target_blocks,
+                )
+                .await
+                .expect("Could not create RpcBlockDataProvider");
 
                 // Create the block builder, run the transactions and extract the DB
-                let provider_db = ProviderDb::new(
+                let mut provider_db = ProviderDb::new(
                     &provider,
                     taiko_chain_spec.clone(),
                     parent_block_number,
@@ -328,6 +353,25 @@ pub async fn batch_preflight<BDP: BlockDataProvider>(
                 )
                 .await?;
 
+                // Build the state straight from an execution witness when the node provides one
+                if let Some(witness_state) = get_witness_state(
+                    &provider,
+                    prove_block.header.number,
+                    input.parent_header.state_root,
+                )
+                .await
+                {
+                    let ancestor_headers = provider_db.get_ancestor_headers().await?;
+                    chunk_guest_input.push(GuestInput {
+                        parent_state_trie: witness_state.parent_state_trie,
+                        parent_storage: witness_state.parent_storage,
+                        contracts: witness_state.contracts,
+                        ancestor_headers,
+                        ..input
+                    });
+                    continue;
+                }
+
                 // Now re-execute the transactions in the block to collect all required data
                 let mut builder = RethBlockBuilder::new(&input, provider_db);
 
@@ -341,7 +385,7 @@ pub async fn batch_preflight<BDP: BlockDataProvider>(
                     #[cfg(feature = "statedb_lru")]
                     save_state_db(
                         (prove_block.header.number, prove_block.hash_slow()),
-                        (db.current_db.clone(), {
+                        (db.post_state(prove_block.hash_slow()), {
                             let mut current_headers = db.initial_headers.clone();
                             current_headers
                                 .insert(prove_block.header.number, prove_block.header.clone());
@@ -425,6 +469,7 @@ pub async fn batch_preflight<BDP: BlockDataProvider>(
     Ok(GuestBatchInput {
         inputs: final_result,
         taiko: taiko_guest_batch_input,
+        ..Default::default()
     })
 }
 
@@ -436,7 +481,7 @@ mod test {
         utils::decode_transactions,
     };
 
-    use crate::preflight::util::{blob_to_bytes, block_time_to_block_slot};
+    use crate::preflight::{blob_source::blob_to_bytes, util::block_time_to_block_slot};
 
     #[test]
     fn test_new_blob_decode() {
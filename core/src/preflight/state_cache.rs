@@ -0,0 +1,338 @@
+#![cfg(feature = "statedb_lru")]
+use alloy_primitives::{Address, U256};
+use lazy_static::lazy_static;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem::size_of,
+    sync::Mutex,
+};
+
+use raiko_lib::mem_db::{DbAccount, MemDb};
+use reth_primitives::{Header, B256};
+use tracing::debug;
+
+use lru::LruCache;
+
+type ChainBlockCacheKey = (u64, B256);
+type ChainBlockCacheEntry = (MemDb, HashMap<u64, Header>);
+
+/// Default memory budget of the state cache, overridable with `STATE_CACHE_MAX_BYTES`.
+const DEFAULT_MAX_BYTES: usize = 2 << 30;
+
+lazy_static! {
+    static ref HISTORY_STATE_DB: Mutex<StateCache> = Mutex::new(StateCache::new(
+        std::env::var("STATE_CACHE_MAX_BYTES")
+            .ok()
+            .and_then(|max_bytes| max_bytes.parse().ok())
+            .unwrap_or(DEFAULT_MAX_BYTES)
+    ));
+}
+
+/// Counters of the process-wide state cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to stay within the memory budget.
+    pub evictions: u64,
+    /// Entries dropped because their block is no longer canonical.
+    pub invalidations: u64,
+    pub entries: usize,
+    /// Estimated memory used by all the entries.
+    pub bytes: usize,
+    pub max_bytes: usize,
+}
+
+struct CachedState {
+    state: ChainBlockCacheEntry,
+    parent_hash: Option<B256>,
+    size: usize,
+}
+
+/// The state after each recently executed block, so that proving the next block only has to
+/// fetch what the previous blocks didn't touch.
+///
+/// Entries are evicted least recently used first once their estimated size exceeds the memory
+/// budget. When a block hash at some height changes, the stale entry and everything built on
+/// top of it is dropped.
+struct StateCache {
+    entries: LruCache<ChainBlockCacheKey, CachedState>,
+    /// The hashes of the cached blocks by block number, to find the reorged entries without
+    /// scanning the whole cache.
+    hashes_by_number: BTreeMap<u64, HashSet<B256>>,
+    stats: StateCacheStats,
+}
+
+impl StateCache {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            entries: LruCache::unbounded(),
+            hashes_by_number: BTreeMap::new(),
+            stats: StateCacheStats {
+                max_bytes,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.hashes_by_number.clear();
+        self.stats.entries = 0;
+        self.stats.bytes = 0;
+    }
+
+    fn get(&mut self, key: ChainBlockCacheKey) -> Option<ChainBlockCacheEntry> {
+        self.invalidate_reorged(key);
+        match self.entries.get(&key) {
+            Some(cached) => {
+                self.stats.hits += 1;
+                Some(cached.state.clone())
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn put(&mut self, key: ChainBlockCacheKey, state: ChainBlockCacheEntry) {
+        self.invalidate_reorged(key);
+        let size = estimate_size(&state);
+        if size > self.stats.max_bytes {
+            debug!("state of block {} too large to cache: {size} bytes", key.0);
+            return;
+        }
+        let parent_hash = state.1.get(&key.0).map(|header| header.parent_hash);
+        if let Some(replaced) = self.entries.put(
+            key,
+            CachedState {
+                state,
+                parent_hash,
+                size,
+            },
+        ) {
+            self.stats.bytes -= replaced.size;
+        }
+        self.hashes_by_number
+            .entry(key.0)
+            .or_default()
+            .insert(key.1);
+        self.stats.bytes += size;
+
+        while self.stats.bytes > self.stats.max_bytes {
+            let Some((evicted, cached)) = self.entries.pop_lru() else {
+                break;
+            };
+            debug!("evict state db key: {evicted:?}");
+            self.unindex(evicted);
+            self.stats.bytes -= cached.size;
+            self.stats.evictions += 1;
+        }
+        self.stats.entries = self.entries.len();
+    }
+
+    fn unindex(&mut self, (number, hash): ChainBlockCacheKey) {
+        if let Some(hashes) = self.hashes_by_number.get_mut(&number) {
+            hashes.remove(&hash);
+            if hashes.is_empty() {
+                self.hashes_by_number.remove(&number);
+            }
+        }
+    }
+
+    /// `key` is canonical, so any other block cached at the same height was reorged out,
+    /// and so were all the cached blocks descending from it.
+    fn invalidate_reorged(&mut self, (number, hash): ChainBlockCacheKey) {
+        let reorged_here = self
+            .hashes_by_number
+            .get(&number)
+            .is_some_and(|hashes| hashes.iter().any(|cached| *cached != hash));
+        if !reorged_here {
+            return;
+        }
+
+        let stale: Vec<ChainBlockCacheKey> = self
+            .hashes_by_number
+            .range(number..)
+            .flat_map(|(number, hashes)| hashes.iter().map(|hash| (*number, *hash)))
+            .collect();
+
+        let mut stale_hashes = HashSet::new();
+        for key in stale {
+            let reorged = if key.0 == number {
+                key.1 != hash
+            } else {
+                self.entries
+                    .peek(&key)
+                    .and_then(|cached| cached.parent_hash)
+                    .is_some_and(|parent_hash| stale_hashes.contains(&parent_hash))
+            };
+            if reorged {
+                if let Some(cached) = self.entries.pop(&key) {
+                    debug!("invalidate reorged state db key: {key:?}");
+                    self.unindex(key);
+                    self.stats.bytes -= cached.size;
+                    self.stats.invalidations += 1;
+                }
+                stale_hashes.insert(key.1);
+            }
+        }
+        self.stats.entries = self.entries.len();
+    }
+}
+
+/// Rough estimate of the memory used by a cache entry.
+fn estimate_size((db, headers): &ChainBlockCacheEntry) -> usize {
+    let accounts: usize = db
+        .accounts
+        .values()
+        .map(|account| {
+            size_of::<(Address, DbAccount)>()
+                + account.info.code.as_ref().map_or(0, |code| code.len())
+                + account.storage.len() * size_of::<(U256, U256)>()
+        })
+        .sum();
+    let headers: usize = headers
+        .values()
+        .map(|header| size_of::<(u64, Header)>() + header.extra_data.len())
+        .sum();
+    size_of::<CachedState>() + accounts + headers + db.block_hashes.len() * size_of::<(u64, B256)>()
+}
+
+fn lock_state_db() -> std::sync::MutexGuard<'static, StateCache> {
+    HISTORY_STATE_DB.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+fn clear_state_db() {
+    debug!("clear state db");
+    lock_state_db().clear();
+}
+
+/// Cache the state after executing block `key`, to be used as the initial state of its child.
+pub(crate) fn save_state_db(key: ChainBlockCacheKey, value: ChainBlockCacheEntry) {
+    debug!("save state db key: {:?}", key);
+    tracing::trace!("save state db account: {:?}", value.0.accounts);
+    tracing::trace!("save state history headers: {:?}", value.1);
+    lock_state_db().put(key, value);
+}
+
+pub(crate) fn load_state_db(key: ChainBlockCacheKey) -> Option<ChainBlockCacheEntry> {
+    debug!("query state db key: {:?}", key);
+    lock_state_db().get(key)
+}
+
+/// Get the counters of the state cache shared by all the preflights of this process.
+pub fn state_cache_stats() -> StateCacheStats {
+    lock_state_db().stats
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use raiko_lib::mem_db::MemDb;
+    use reth_primitives::B256;
+    use serial_test::serial;
+
+    fn entry(number: u64, parent_hash: B256) -> ChainBlockCacheEntry {
+        let header = Header {
+            number,
+            parent_hash,
+            ..Default::default()
+        };
+        (MemDb::default(), HashMap::from([(number, header)]))
+    }
+
+    #[test]
+    #[serial]
+    fn test_lru_cache_save_load() {
+        let key = (1, B256::ZERO);
+        let value = (MemDb::default(), HashMap::new());
+        save_state_db(key, value.clone());
+        let result = load_state_db(key);
+        assert!(result.is_some());
+        assert_eq!(result.clone().unwrap().0.block_hashes, value.0.block_hashes);
+        assert_eq!(
+            result
+                .clone()
+                .unwrap()
+                .0
+                .accounts
+                .keys()
+                .collect::<Vec<_>>(),
+            value.0.accounts.keys().collect::<Vec<_>>()
+        );
+
+        let hits = state_cache_stats().hits;
+        let key = (2, B256::random());
+        assert!(load_state_db(key).is_none());
+        assert_eq!(state_cache_stats().hits, hits);
+        clear_state_db();
+        assert_eq!(state_cache_stats().entries, 0);
+    }
+
+    #[test]
+    fn test_lru_cache_replace() {
+        let size = estimate_size(&entry(0, B256::ZERO));
+        let mut cache = StateCache::new(256 * size);
+        for i in 0..256 + 4 {
+            cache.put((i, B256::with_last_byte(i as u8)), entry(i, B256::ZERO));
+        }
+        assert_eq!(cache.stats.entries, 256);
+        assert_eq!(cache.hashes_by_number.len(), 256);
+        assert_eq!(cache.stats.evictions, 4);
+        assert!(cache.stats.bytes <= cache.stats.max_bytes);
+
+        // 0 is out
+        assert!(cache.get((0, B256::with_last_byte(0))).is_none());
+        // 1 is out
+        assert!(cache.get((1, B256::with_last_byte(1))).is_none());
+        // 4 is still in
+        assert!(cache.get((4, B256::with_last_byte(4))).is_some());
+    }
+
+    #[test]
+    fn test_lru_cache_memory_bound() {
+        let mut cache = StateCache::new(1024);
+        let mut db = MemDb::default();
+        let address = Address::with_last_byte(1);
+        db.insert_account_info(address, Default::default());
+        for slot in 0..1024u64 {
+            db.insert_account_storage(&address, U256::from(slot), U256::from(slot));
+        }
+        cache.put((1, B256::ZERO), (db, HashMap::new()));
+        assert!(cache.get((1, B256::ZERO)).is_none());
+        assert_eq!(cache.stats.bytes, 0);
+    }
+
+    #[test]
+    fn test_lru_cache_reorg() {
+        let mut cache = StateCache::new(usize::MAX);
+        let (a1, a2, a3) = (
+            B256::repeat_byte(0xa1),
+            B256::repeat_byte(0xa2),
+            B256::repeat_byte(0xa3),
+        );
+        cache.put((1, a1), entry(1, B256::ZERO));
+        cache.put((2, a2), entry(2, a1));
+        cache.put((3, a3), entry(3, a2));
+        // Unrelated to the reorged branch
+        let other = B256::repeat_byte(0xff);
+        cache.put((3, other), entry(3, B256::repeat_byte(0xee)));
+
+        // Block 2 was replaced, so the cached block 3 built on the old one is gone as well
+        let b2 = B256::repeat_byte(0xb2);
+        assert!(cache.get((2, b2)).is_none());
+        assert!(!cache.entries.contains(&(2, a2)));
+        assert!(!cache.entries.contains(&(3, a3)));
+        assert!(cache.entries.contains(&(3, other)));
+        assert_eq!(cache.stats.invalidations, 2);
+        assert_eq!(cache.stats.entries, 2);
+        assert!(cache.get((1, a1)).is_some());
+        assert_eq!(
+            cache.hashes_by_number,
+            BTreeMap::from([(1, HashSet::from([a1])), (3, HashSet::from([other]))])
+        );
+    }
+}
@@ -1,13 +1,11 @@
-use alloy_primitives::{hex, Log as LogStruct, B256};
-use alloy_provider::{Provider, ReqwestProvider};
+use alloy_primitives::{Log as LogStruct, B256};
+use alloy_provider::Provider;
 use alloy_rpc_types::{Filter, Header, Log, Transaction as AlloyRpcTransaction};
 use alloy_sol_types::{SolCall, SolEvent};
 use anyhow::{anyhow, bail, ensure, Result};
 use kzg::kzg_types::ZFr;
-use kzg_traits::{
-    eip_4844::{blob_to_kzg_commitment_rust, Blob},
-    Fr, G1,
-};
+use kzg_traits::{Fr, G1};
+use lazy_static::lazy_static;
 use raiko_lib::{
     builder::{OptimisticDatabase, RethBlockBuilder},
     clear_line,
@@ -19,18 +17,24 @@ use raiko_lib::{
         proposeBlockCall, BlobProofType, BlockProposed, BlockProposedFork, TaikoGuestBatchInput,
         TaikoGuestInput, TaikoProverData,
     },
-    primitives::eip4844::{self, commitment_to_version_hash, KZG_SETTINGS},
+    primitives::eip4844::{self, commitment_to_version_hash},
 };
 use reth_evm_ethereum::taiko::{decode_anchor, decode_anchor_ontake, decode_anchor_pacaya};
 use reth_primitives::{Block as RethBlock, TransactionSigned};
 use reth_revm::primitives::SpecId;
-use serde::{Deserialize, Serialize};
 use std::iter;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 
+use super::{blob_archive::BlobArchive, blob_source::blob_source};
 use crate::{
     interfaces::{RaikoError, RaikoResult},
-    provider::{db::ProviderDb, rpc::RpcBlockDataProvider, BlockDataProvider},
+    provider::{
+        db::ProviderDb,
+        failover::{with_failover, NotServed},
+        rpc::RpcBlockDataProvider,
+        witness::WitnessState,
+        BlockDataProvider,
+    },
     require,
 };
 
@@ -70,6 +74,44 @@ where
     Ok(())
 }
 
+lazy_static! {
+    static ref EXECUTION_WITNESS_ENABLED: bool = std::env::var("PREFLIGHT_EXECUTION_WITNESS")
+        .is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+}
+
+/// Fetch the parent state of `block_number` with a single execution witness call instead of
+/// discovering it through optimistic execution. Only used when `PREFLIGHT_EXECUTION_WITNESS`
+/// is set, and returns `None` whenever the optimistic execution should be used instead.
+pub async fn get_witness_state<BDP>(
+    provider: &BDP,
+    block_number: u64,
+    parent_state_root: B256,
+) -> Option<WitnessState>
+where
+    BDP: BlockDataProvider,
+{
+    if !*EXECUTION_WITNESS_ENABLED {
+        return None;
+    }
+
+    let witness_state = async {
+        provider
+            .get_execution_witness(block_number)
+            .await?
+            .into_state(parent_state_root)
+    };
+    match witness_state.await {
+        Ok(state) => {
+            info!("State of block {block_number} built from execution witness");
+            Some(state)
+        }
+        Err(e) => {
+            warn!("Execution witness unusable for block {block_number}, executing optimistically: {e}");
+            None
+        }
+    }
+}
+
 /// Prepare the input for a Taiko chain
 pub async fn prepare_taiko_chain_input(
     l1_chain_spec: &ChainSpec,
@@ -108,7 +150,8 @@ pub async fn prepare_taiko_chain_input(
 
     // // Get the L1 block in which the L2 block was included so we can fetch the DA data.
     // // Also get the L1 state block header so that we can prove the L1 state root.
-    let provider_l1 = RpcBlockDataProvider::new(&l1_chain_spec.rpc, 0).await?;
+    let provider_l1 =
+        RpcBlockDataProvider::new_with_fallbacks(&l1_chain_spec.rpc_endpoints(), 0).await?;
 
     info!("current taiko chain fork: {fork:?}");
 
@@ -116,7 +159,7 @@ pub async fn prepare_taiko_chain_input(
         if let Some(l1_block_number) = l1_inclusion_block_number {
             // Get the block proposal data
             get_block_proposed_event_by_height(
-                provider_l1.provider(),
+                &provider_l1,
                 taiko_chain_spec.clone(),
                 l1_block_number,
                 block_number,
@@ -126,7 +169,7 @@ pub async fn prepare_taiko_chain_input(
         } else {
             // traversal next 64 blocks to get proposal data
             get_block_proposed_event_by_traversal(
-                provider_l1.provider(),
+                &provider_l1,
                 taiko_chain_spec.clone(),
                 anchor_block_height,
                 block_number,
@@ -184,7 +227,7 @@ pub async fn prepare_taiko_chain_input(
             SpecId::ONTAKE => {
                 // Get the tx list data directly from the propose block CalldataTxList event
                 let (_, CalldataTxList { txList, .. }) = get_calldata_txlist_event(
-                    provider_l1.provider(),
+                    &provider_l1,
                     taiko_chain_spec.clone(),
                     l1_inclusion_block_hash,
                     block_number,
@@ -247,9 +290,27 @@ pub async fn parse_l1_batch_proposal_tx_for_pacaya_fork(
     l1_inclusion_block_number: u64,
     batch_id: u64,
 ) -> RaikoResult<Vec<u64>> {
-    let provider_l1 = RpcBlockDataProvider::new(&l1_chain_spec.rpc, 0).await?;
+    parse_l1_batch_proposal_for_pacaya_fork(
+        l1_chain_spec,
+        taiko_chain_spec,
+        l1_inclusion_block_number,
+        batch_id,
+    )
+    .await
+    .map(|(block_numbers, _)| block_numbers)
+}
+
+/// return the block numbers of the batch, and whether its txlist is in blobs
+pub async fn parse_l1_batch_proposal_for_pacaya_fork(
+    l1_chain_spec: &ChainSpec,
+    taiko_chain_spec: &ChainSpec,
+    l1_inclusion_block_number: u64,
+    batch_id: u64,
+) -> RaikoResult<(Vec<u64>, bool)> {
+    let provider_l1 =
+        RpcBlockDataProvider::new_with_fallbacks(&l1_chain_spec.rpc_endpoints(), 0).await?;
     let (l1_inclusion_height, _tx, batch_proposed_fork) = get_block_proposed_event_by_height(
-        provider_l1.provider(),
+        &provider_l1,
         taiko_chain_spec.clone(),
         l1_inclusion_block_number,
         batch_id,
@@ -261,13 +322,15 @@ pub async fn parse_l1_batch_proposal_tx_for_pacaya_fork(
         l1_inclusion_block_number == l1_inclusion_height,
         "proposal tx inclusive block != proof_request block"
     );
+    let blob_used = batch_proposed_fork.blob_used();
     if let BlockProposedFork::Pacaya(batch_proposed) = batch_proposed_fork {
         let batch_info = &batch_proposed.info;
-        Ok(
+        Ok((
             ((batch_info.lastBlockId - (batch_info.blocks.len() as u64 - 1))
                 ..=batch_info.lastBlockId)
                 .collect(),
-        )
+            blob_used,
+        ))
     } else {
         Err(RaikoError::Preflight(
             "BatchProposedFork is not Pacaya".to_owned(),
@@ -307,10 +370,11 @@ pub async fn prepare_taiko_chain_batch_input(
 
     let (anchor_block_height, anchor_state_root) = batch_anchor_tx_info[0];
     let fork = taiko_chain_spec.active_fork(batch_blocks[0].number, batch_blocks[0].timestamp)?;
-    let provider_l1 = RpcBlockDataProvider::new(&l1_chain_spec.rpc, 0).await?;
+    let provider_l1 =
+        RpcBlockDataProvider::new_with_fallbacks(&l1_chain_spec.rpc_endpoints(), 0).await?;
     // todo: duplicate code with parse_l1_batch_proposal_tx_for_pacaya_fork(), better to make these values fn parameters
     let (l1_inclusion_height, _, batch_proposed_fork) = get_block_proposed_event_by_height(
-        provider_l1.provider(),
+        &provider_l1,
         taiko_chain_spec.clone(),
         l1_inclusion_block_number,
         batch_id,
@@ -389,6 +453,16 @@ pub async fn prepare_taiko_chain_batch_input(
     }
 }
 
+fn beacon_rpc_endpoints(chain_spec: &ChainSpec) -> RaikoResult<Vec<String>> {
+    let beacon_rpc_urls = chain_spec.beacon_rpc_endpoints();
+    if beacon_rpc_urls.is_empty() {
+        return Err(RaikoError::Preflight(
+            "Beacon RPC URL is required for Taiko chains".to_owned(),
+        ));
+    }
+    Ok(beacon_rpc_urls)
+}
+
 pub async fn get_tx_blob(
     blob_hash: B256,
     timestamp: u64,
@@ -402,41 +476,63 @@ pub async fn get_tx_blob(
         chain_spec.genesis_time,
         chain_spec.seconds_per_slot,
     )?;
-    let beacon_rpc_url: String = chain_spec.beacon_rpc.clone().ok_or_else(|| {
-        RaikoError::Preflight("Beacon RPC URL is required for Taiko chains".to_owned())
-    })?;
-    let blob = get_and_filter_blob_data(&beacon_rpc_url, slot_id, blob_hash).await?;
-    let commitment = eip4844::calc_kzg_proof_commitment(&blob).map_err(|e| anyhow!(e))?;
-    let blob_proof = match blob_proof_type {
-        BlobProofType::KzgVersionedHash => None,
-        BlobProofType::ProofOfEquivalence => {
-            let (x, y) =
-                eip4844::proof_of_equivalence(&blob, &commitment_to_version_hash(&commitment))
-                    .map_err(|e| anyhow!(e))?;
+    let blob = get_blobs(
+        chain_spec,
+        slot_id,
+        &[blob_hash],
+        BlobArchive::from_env().as_ref(),
+    )
+    .await?
+    .pop()
+    .ok_or_else(|| RaikoError::Preflight(format!("No blob data for {blob_hash}")))?;
+    blob_with_proof(blob, blob_proof_type)
+}
 
-            debug!("x {x:?} y {y:?}");
-            let point = eip4844::calc_kzg_proof_with_point(&blob, ZFr::from_bytes(&x).unwrap());
-            debug!("calc_kzg_proof_with_point {point:?}");
+/// Get the blobs with the given versioned hashes posted in `slot_id`, failing over across all
+/// the blob sources configured for the chain.
+///
+/// With an `archive`, blobs are looked up there first and every fetched blob is stored in it.
+pub async fn get_blobs(
+    chain_spec: &ChainSpec,
+    slot_id: u64,
+    blob_hashes: &[B256],
+    archive: Option<&BlobArchive>,
+) -> RaikoResult<Vec<Vec<u8>>> {
+    if let Some(blobs) = archive.and_then(|archive| archive.load_all(blob_hashes)) {
+        debug!("Loaded blobs {blob_hashes:?} from the archive");
+        return Ok(blobs);
+    }
 
-            Some(
-                point
-                    .map(|g1| g1.to_bytes().to_vec())
-                    .map_err(|e| anyhow!(e))?,
-            )
+    let beacon_rpc_urls = beacon_rpc_endpoints(chain_spec)?;
+    let blob_sources: Vec<_> = beacon_rpc_urls.iter().map(|url| blob_source(url)).collect();
+    let blobs = with_failover(&beacon_rpc_urls, |i| {
+        let blob_source = &blob_sources[i];
+        async move {
+            // A source missing the blobs or serving wrong ones may still be backed up by the
+            // next one, only the unreachable sources are worth retrying
+            blob_source
+                .get_blobs(slot_id, blob_hashes)
+                .await
+                .map_err(NotServed::unless_transient)
         }
-    };
+    })
+    .await?;
 
-    Ok((blob, Some(commitment.to_vec()), blob_proof))
+    if let Some(archive) = archive {
+        for (blob_hash, blob) in blob_hashes.iter().zip(blobs.iter()) {
+            if let Err(e) = archive.store(blob_hash, blob) {
+                warn!("Failed to archive blob {blob_hash}: {e}");
+            }
+        }
+    }
+    Ok(blobs)
 }
 
-pub async fn filter_tx_blob_beacon_with_proof(
-    blob_hash: B256,
-    blobs: Vec<String>,
+/// Compute the commitment of a blob, and its proof for `blob_proof_type`.
+fn blob_with_proof(
+    blob: Vec<u8>,
     blob_proof_type: &BlobProofType,
 ) -> RaikoResult<(Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>)> {
-    info!("get tx from hash blob: {blob_hash:?}");
-    // Get the blob data for this block
-    let blob = filter_blob_data_beacon(blobs, blob_hash).await?;
     let commitment = eip4844::calc_kzg_proof_commitment(&blob).map_err(|e| anyhow!(e))?;
     let blob_proof = match blob_proof_type {
         BlobProofType::KzgVersionedHash => None,
@@ -468,37 +564,64 @@ pub async fn get_batch_tx_data_with_proofs(
     chain_spec: &ChainSpec,
     blob_proof_type: &BlobProofType,
 ) -> RaikoResult<Vec<(Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>)>> {
-    let mut tx_data = Vec::new();
-    let beacon_rpc_url: String = chain_spec.beacon_rpc.clone().ok_or_else(|| {
-        RaikoError::Preflight("Beacon RPC URL is required for Taiko chains".to_owned())
-    })?;
     let slot_id = block_time_to_block_slot(
         timestamp,
         chain_spec.genesis_time,
         chain_spec.seconds_per_slot,
     )?;
     // get blob data once
-    let blob_data = get_blob_data(&beacon_rpc_url, slot_id).await?;
-    let blobs: Vec<String> = blob_data.data.iter().map(|b| b.blob.clone()).collect();
-    for hash in blob_hashes {
-        let data = filter_tx_blob_beacon_with_proof(hash, blobs.clone(), blob_proof_type).await?;
-        tx_data.push(data);
-    }
-    Ok(tx_data)
+    let blobs = get_blobs(
+        chain_spec,
+        slot_id,
+        &blob_hashes,
+        BlobArchive::from_env().as_ref(),
+    )
+    .await?;
+    blobs
+        .into_iter()
+        .map(|blob| blob_with_proof(blob, blob_proof_type))
+        .collect()
 }
 
 pub async fn filter_blockchain_event(
-    provider: &ReqwestProvider,
+    provider: &RpcBlockDataProvider,
     gen_block_event_filter: impl Fn() -> Filter,
 ) -> Result<Vec<Log>> {
     // Setup the filter to get the relevant events
     let filter = gen_block_event_filter();
     // Now fetch the events
-    Ok(provider.get_logs(&filter).await?)
+    let logs = provider
+        .with_provider(|provider| {
+            let filter = filter.clone();
+            async move {
+                provider
+                    .get_logs(&filter)
+                    .await
+                    .map_err(|e| RaikoError::RPC(format!("Error fetching logs: {e}")))
+            }
+        })
+        .await?;
+    Ok(logs)
+}
+
+/// Get a transaction through all the endpoints of `provider`.
+async fn get_transaction_by_hash(
+    provider: &RpcBlockDataProvider,
+    tx_hash: B256,
+) -> Result<AlloyRpcTransaction> {
+    let tx = provider
+        .with_provider(|provider| async move {
+            provider
+                .get_transaction_by_hash(tx_hash)
+                .await
+                .map_err(|e| RaikoError::RPC(format!("Error fetching tx {tx_hash}: {e}")))
+        })
+        .await?;
+    tx.ok_or_else(|| anyhow!("Could not find the propose tx {tx_hash}"))
 }
 
 pub async fn get_calldata_txlist_event(
-    provider: &ReqwestProvider,
+    provider: &RpcBlockDataProvider,
     chain_spec: ChainSpec,
     block_hash: B256,
     l2_block_number: u64,
@@ -532,11 +655,7 @@ pub async fn get_calldata_txlist_event(
             let Some(log_tx_hash) = log.transaction_hash else {
                 bail!("No transaction hash in the log")
             };
-            let tx = provider
-                .get_transaction_by_hash(log_tx_hash)
-                .await
-                .expect("couldn't query the propose tx")
-                .expect("Could not find the propose tx");
+            let tx = get_transaction_by_hash(provider, log_tx_hash).await?;
             return Ok((tx, event.data));
         }
     }
@@ -551,7 +670,7 @@ pub enum EventFilterConditioin {
 }
 
 pub async fn filter_block_proposed_event(
-    provider: &ReqwestProvider,
+    provider: &RpcBlockDataProvider,
     chain_spec: ChainSpec,
     filter_condition: EventFilterConditioin,
     block_num_or_batch_id: u64,
@@ -622,11 +741,7 @@ pub async fn filter_block_proposed_event(
             let Some(log_tx_hash) = log.transaction_hash else {
                 bail!("No transaction hash in the log")
             };
-            let tx = provider
-                .get_transaction_by_hash(log_tx_hash)
-                .await
-                .expect("couldn't query the propose tx")
-                .expect("Could not find the propose tx");
+            let tx = get_transaction_by_hash(provider, log_tx_hash).await?;
             return Ok((log.block_number.unwrap(), tx, block_propose_event));
         }
     }
@@ -637,7 +752,7 @@ pub async fn filter_block_proposed_event(
 }
 
 pub async fn _get_block_proposed_event_by_hash(
-    provider: &ReqwestProvider,
+    provider: &RpcBlockDataProvider,
     chain_spec: ChainSpec,
     l1_inclusion_block_hash: B256,
     l2_block_number: u64,
@@ -654,7 +769,7 @@ pub async fn _get_block_proposed_event_by_hash(
 }
 
 pub async fn get_block_proposed_event_by_height(
-    provider: &ReqwestProvider,
+    provider: &RpcBlockDataProvider,
     chain_spec: ChainSpec,
     l1_inclusion_block_number: u64,
     block_num_or_batch_id: u64,
@@ -673,7 +788,7 @@ pub async fn get_block_proposed_event_by_height(
 const MAX_ANCHOR_BLOCK_RANGE: u64 = 96;
 
 pub async fn get_block_proposed_event_by_traversal(
-    provider: &ReqwestProvider,
+    provider: &RpcBlockDataProvider,
     chain_spec: ChainSpec,
     l1_anchor_block_number: u64,
     l2_block_number: u64,
@@ -815,158 +930,3 @@ pub fn block_time_to_block_slot(
         Ok((block_time - genesis_time) / block_per_slot)
     }
 }
-
-pub fn blob_to_bytes(blob_str: &str) -> Vec<u8> {
-    hex::decode(blob_str.to_lowercase().trim_start_matches("0x")).unwrap_or_default()
-}
-
-fn calc_blob_versioned_hash(blob_str: &str) -> [u8; 32] {
-    let blob_bytes = hex::decode(blob_str.to_lowercase().trim_start_matches("0x"))
-        .expect("Could not decode blob");
-    let blob = Blob::from_bytes(&blob_bytes).expect("Could not create blob");
-    let commitment = blob_to_kzg_commitment_rust(
-        &eip4844::deserialize_blob_rust(&blob).expect("Could not deserialize blob"),
-        &KZG_SETTINGS.clone(),
-    )
-    .expect("Could not create kzg commitment from blob");
-    commitment_to_version_hash(&commitment.to_bytes()).0
-}
-
-async fn get_and_filter_blob_data(
-    beacon_rpc_url: &str,
-    block_id: u64,
-    blob_hash: B256,
-) -> Result<Vec<u8>> {
-    if beacon_rpc_url.contains("blobscan.com") {
-        get_and_filter_blob_data_by_blobscan(beacon_rpc_url, block_id, blob_hash).await
-    } else {
-        get_and_filter_blob_data_beacon(beacon_rpc_url, block_id, blob_hash).await
-    }
-}
-
-async fn get_blob_data(beacon_rpc_url: &str, block_id: u64) -> Result<GetBlobsResponse> {
-    if beacon_rpc_url.contains("blobscan.com") {
-        unimplemented!("blobscan.com is not supported yet")
-    } else {
-        get_blob_data_beacon(beacon_rpc_url, block_id).await
-    }
-}
-
-// Blob data from the beacon chain
-// type Sidecar struct {
-// Index                    string                   `json:"index"`
-// Blob                     string                   `json:"blob"`
-// SignedBeaconBlockHeader  *SignedBeaconBlockHeader `json:"signed_block_header"`
-// KzgCommitment            string                   `json:"kzg_commitment"`
-// KzgProof                 string                   `json:"kzg_proof"`
-// CommitmentInclusionProof []string
-// `json:"kzg_commitment_inclusion_proof"` }
-#[derive(Clone, Debug, Deserialize, Serialize)]
-struct GetBlobData {
-    pub index: String,
-    pub blob: String,
-    // pub signed_block_header: SignedBeaconBlockHeader, // ignore for now
-    pub kzg_commitment: String,
-    pub kzg_proof: String,
-    //pub kzg_commitment_inclusion_proof: Vec<String>,
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
-struct GetBlobsResponse {
-    pub data: Vec<GetBlobData>,
-}
-
-async fn get_blob_data_beacon(beacon_rpc_url: &str, block_id: u64) -> Result<GetBlobsResponse> {
-    let url = format!(
-        "{}/eth/v1/beacon/blob_sidecars/{block_id}",
-        beacon_rpc_url.trim_end_matches('/'),
-    );
-    info!("Retrieve blob from {url}.");
-    let response = reqwest::get(url.clone()).await?;
-
-    if !response.status().is_success() {
-        warn!(
-            "Request {url} failed with status code: {}",
-            response.status()
-        );
-        return Err(anyhow::anyhow!(
-            "Request failed with status code: {}",
-            response.status()
-        ));
-    }
-
-    let blobs = response.json::<GetBlobsResponse>().await?;
-    ensure!(!blobs.data.is_empty(), "blob data not available anymore");
-    Ok(blobs)
-}
-
-async fn get_and_filter_blob_data_beacon(
-    beacon_rpc_url: &str,
-    block_id: u64,
-    blob_hash: B256,
-) -> Result<Vec<u8>> {
-    info!("Retrieve blob for {block_id} and expect {blob_hash}.");
-    let blobs = get_blob_data_beacon(beacon_rpc_url, block_id).await?;
-    // Get the blob data for the blob storing the tx list
-    let tx_blob = blobs
-        .data
-        .iter()
-        .find(|blob| {
-            // calculate from plain blob
-            blob_hash == calc_blob_versioned_hash(&blob.blob)
-        })
-        .cloned();
-
-    if let Some(tx_blob) = &tx_blob {
-        Ok(blob_to_bytes(&tx_blob.blob))
-    } else {
-        Err(anyhow!("couldn't find blob data matching blob hash"))
-    }
-}
-
-async fn filter_blob_data_beacon(blobs: Vec<String>, blob_hash: B256) -> Result<Vec<u8>> {
-    // Get the blob data for the blob storing the tx list
-    let tx_blob = blobs
-        .iter()
-        .find(|blob| {
-            // calculate from plain blob
-            blob_hash == calc_blob_versioned_hash(blob)
-        })
-        .cloned();
-
-    if let Some(tx_blob) = &tx_blob {
-        Ok(blob_to_bytes(tx_blob))
-    } else {
-        Err(anyhow!("couldn't find blob data matching blob hash"))
-    }
-}
-
-// https://api.blobscan.com/#/
-#[derive(Clone, Debug, Deserialize, Serialize)]
-struct BlobScanData {
-    pub commitment: String,
-    pub data: String,
-}
-
-async fn get_and_filter_blob_data_by_blobscan(
-    beacon_rpc_url: &str,
-    _block_id: u64,
-    blob_hash: B256,
-) -> Result<Vec<u8>> {
-    let url = format!("{}/blobs/{blob_hash}", beacon_rpc_url.trim_end_matches('/'),);
-    let response = reqwest::get(url.clone()).await?;
-
-    if !response.status().is_success() {
-        error!(
-            "Request {url} failed with status code: {}",
-            response.status()
-        );
-        return Err(anyhow::anyhow!(
-            "Request failed with status code: {}",
-            response.status()
-        ));
-    }
-
-    let blob = response.json::<BlobScanData>().await?;
-    Ok(blob_to_bytes(&blob.data))
-}
@@ -1,5 +1,9 @@
 use alloy_primitives::{Address, Bytes, U256};
-use raiko_lib::{builder::OptimisticDatabase, consts::ChainSpec, mem_db::MemDb};
+use raiko_lib::{
+    builder::OptimisticDatabase,
+    consts::ChainSpec,
+    mem_db::{AccountState, DbAccount, MemDb},
+};
 use reth_primitives::{Header, B256};
 use reth_provider::ProviderError;
 use reth_revm::{
@@ -36,16 +40,22 @@ impl<'a, BDP: BlockDataProvider> ProviderDb<'a, BDP> {
         provider: &'a BDP,
         chain_spec: ChainSpec,
         block_number: u64,
-        initial_db_with_headers: Option<(MemDb, HashMap<u64, Header>)>,
+        cached_state: Option<(MemDb, HashMap<u64, Header>)>,
     ) -> RaikoResult<Self> {
-        let (initial_db, initial_headers) = initial_db_with_headers.unwrap_or_default();
+        let (cached_db, initial_headers) = cached_state.unwrap_or_default();
+        // Cached accounts and slots only prefill the staging db, so that only the ones
+        // actually read by this block end up in the initial db and need to be proven.
+        let initial_db = MemDb {
+            block_hashes: cached_db.block_hashes.clone(),
+            ..Default::default()
+        };
         let mut provider_db = ProviderDb {
             provider,
             block_number,
             async_executor: Handle::current(),
             // defaults
             optimistic: false,
-            staging_db: initial_db.clone(),
+            staging_db: cached_db,
             initial_db: initial_db,
             initial_headers: initial_headers,
             current_db: Default::default(),
@@ -165,6 +175,35 @@ impl<'a, BDP: BlockDataProvider> ProviderDb<'a, BDP> {
         Ok(headers)
     }
 
+    /// The state after executing the block, as far as it is known: every account and slot
+    /// loaded for the parent block, overwritten by the changes of the block itself.
+    ///
+    /// Used as the cached state of the next block, together with the hash of the executed
+    /// block at `block_number + 1`.
+    pub fn post_state(&self, block_hash: B256) -> MemDb {
+        let mut state = self.staging_db.clone();
+        for (address, account) in &self.initial_db.accounts {
+            let cached = state.accounts.entry(*address).or_default();
+            cached.info = account.info.clone();
+            cached.storage.extend(&account.storage);
+        }
+        for (address, account) in &self.current_db.accounts {
+            let cached = state.accounts.entry(*address).or_default();
+            match account.state {
+                // Deleted accounts read back as empty accounts without storage
+                AccountState::Deleted => *cached = DbAccount::default(),
+                // All the slots not written since the storage was cleared read back as zero,
+                // leave them out so they are fetched again instead of served stale.
+                AccountState::StorageCleared => *cached = DbAccount::new(account.info.clone()),
+                _ => cached.info = account.info.clone(),
+            }
+            cached.storage.extend(&account.storage);
+        }
+        state.block_hashes.clone_from(&self.initial_db.block_hashes);
+        state.block_hashes.insert(self.block_number + 1, block_hash);
+        state
+    }
+
     pub fn is_valid_run(&self) -> bool {
         self.pending_accounts.is_empty()
             && self.pending_slots.is_empty()
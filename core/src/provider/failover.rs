@@ -0,0 +1,358 @@
+use lazy_static::lazy_static;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{debug, warn};
+
+use crate::interfaces::{RaikoError, RaikoResult};
+
+/// Number of consecutive failures after which an endpoint is considered unhealthy.
+const UNHEALTHY_THRESHOLD: u32 = 3;
+/// How long an unhealthy endpoint is moved behind the healthy ones.
+const UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    /// Health of every endpoint used by this process, keyed by url, so that all the providers
+    /// talking to the same node share what they learned about it.
+    static ref ENDPOINT_HEALTH: Mutex<HashMap<String, EndpointHealth>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EndpointHealth {
+    pub consecutive_failures: u32,
+    pub last_failure: Option<Instant>,
+    pub last_error: Option<String>,
+}
+
+impl EndpointHealth {
+    pub fn is_healthy(&self) -> bool {
+        match self.last_failure {
+            Some(last_failure) if self.consecutive_failures >= UNHEALTHY_THRESHOLD => {
+                last_failure.elapsed() >= UNHEALTHY_COOLDOWN
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Get the health of an endpoint.
+pub fn endpoint_health(url: &str) -> EndpointHealth {
+    ENDPOINT_HEALTH
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(url)
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn record_success(url: &str) {
+    ENDPOINT_HEALTH
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(url);
+}
+
+fn record_failure(url: &str, error: &RaikoError) {
+    let mut health = ENDPOINT_HEALTH.lock().unwrap_or_else(|e| e.into_inner());
+    let entry = health.entry(url.to_owned()).or_default();
+    entry.consecutive_failures += 1;
+    entry.last_failure = Some(Instant::now());
+    entry.last_error = Some(error.to_string());
+}
+
+/// Indices of `urls` in the order they should be tried: healthy endpoints first, each group
+/// keeping the configured priority.
+fn ordered_endpoints(urls: &[String]) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..urls.len()).collect();
+    indices.sort_by_key(|&i| !endpoint_health(&urls[i]).is_healthy());
+    indices
+}
+
+/// The failure of an endpoint which answered, but cannot serve the call, e.g. a blob it pruned
+/// or a method it does not support. The other endpoints are tried, but not this one again.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct NotServed(pub String);
+
+impl NotServed {
+    pub fn error(message: impl Into<String>) -> RaikoError {
+        RaikoError::Anyhow(anyhow::Error::new(NotServed(message.into())))
+    }
+
+    /// The failure `error` of an endpoint, as not served unless the endpoint could not be
+    /// reached.
+    pub fn unless_transient(error: anyhow::Error) -> RaikoError {
+        if error.chain().any(is_transport_error) {
+            RaikoError::Anyhow(error)
+        } else {
+            Self::error(format!("{error:#}"))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FailureKind {
+    /// The endpoint could not be reached or failed to answer, worth retrying after a backoff.
+    Transient,
+    /// See [`NotServed`].
+    NotServed,
+    /// Every endpoint would fail the same way.
+    Permanent,
+}
+
+fn failure_kind(error: &RaikoError) -> FailureKind {
+    match error {
+        RaikoError::RPC(_) | RaikoError::Io(_) => FailureKind::Transient,
+        RaikoError::Anyhow(e) if e.chain().any(|cause| cause.is::<NotServed>()) => {
+            FailureKind::NotServed
+        }
+        // The lookups returning anyhow errors keep their transport errors as causes
+        RaikoError::Anyhow(e) if e.chain().any(is_transport_error) => FailureKind::Transient,
+        _ => FailureKind::Permanent,
+    }
+}
+
+fn is_transport_error(cause: &(dyn std::error::Error + 'static)) -> bool {
+    cause.is::<reqwest::Error>()
+        || cause.is::<std::io::Error>()
+        || matches!(
+            cause.downcast_ref::<RaikoError>(),
+            Some(RaikoError::RPC(_) | RaikoError::Io(_))
+        )
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// How many times every endpoint is tried.
+    pub rounds: u32,
+    /// Delay after the first round failed on all the endpoints, doubled after each round.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            rounds: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+/// Run `op` against the endpoints in `urls` with the default [`RetryPolicy`].
+///
+/// `op` receives the index in `urls` of the endpoint to use.
+pub async fn with_failover<T, F, Fut>(urls: &[String], op: F) -> RaikoResult<T>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = RaikoResult<T>>,
+{
+    with_failover_policy(&RetryPolicy::default(), urls, op).await
+}
+
+/// Run `op` against the endpoints in `urls`, failing over to the next endpoint when one cannot
+/// be reached or cannot serve the call, and backing off before retrying the unreachable ones.
+///
+/// Transport and RPC errors are retried, [`NotServed`] failures only fail over, and any other
+/// error is returned right away.
+pub async fn with_failover_policy<T, F, Fut>(
+    policy: &RetryPolicy,
+    urls: &[String],
+    mut op: F,
+) -> RaikoResult<T>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = RaikoResult<T>>,
+{
+    if urls.is_empty() {
+        return Err(RaikoError::RPC("No endpoint configured".to_owned()));
+    }
+
+    let mut backoff = policy.initial_backoff;
+    let mut not_served = HashSet::new();
+    let mut last_error = None;
+    for round in 0..policy.rounds.max(1) {
+        if round > 0 {
+            debug!("All endpoints failed, retrying in {backoff:?}");
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(policy.max_backoff);
+        }
+
+        let mut unreachable = false;
+        for i in ordered_endpoints(urls) {
+            if not_served.contains(&i) {
+                continue;
+            }
+            match op(i).await {
+                Ok(value) => {
+                    record_success(&urls[i]);
+                    return Ok(value);
+                }
+                Err(error) => match failure_kind(&error) {
+                    FailureKind::Transient => {
+                        warn!("Endpoint {} failed: {error}", urls[i]);
+                        record_failure(&urls[i], &error);
+                        unreachable = true;
+                        last_error = Some(error);
+                    }
+                    FailureKind::NotServed => {
+                        debug!("Endpoint {} cannot serve the call: {error}", urls[i]);
+                        not_served.insert(i);
+                        last_error = Some(error);
+                    }
+                    FailureKind::Permanent => return Err(error),
+                },
+            }
+        }
+        // Waiting only helps the endpoints which could not be reached
+        if !unreachable {
+            break;
+        }
+    }
+
+    Err(last_error.expect("at least one endpoint was tried"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn test_policy() -> RetryPolicy {
+        RetryPolicy {
+            rounds: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover_to_next_endpoint() {
+        let urls = vec![
+            "http://failover-test-down".to_owned(),
+            "http://failover-test-up".to_owned(),
+        ];
+        let result = with_failover_policy(&test_policy(), &urls, |i| {
+            let url = urls[i].clone();
+            async move {
+                if url.ends_with("down") {
+                    Err(RaikoError::RPC("connection refused".to_owned()))
+                } else {
+                    Ok(url)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), urls[1]);
+        assert_eq!(endpoint_health(&urls[0]).consecutive_failures, 1);
+        assert_eq!(endpoint_health(&urls[1]).consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_on_permanent_error() {
+        let urls = vec![
+            "http://failover-test-a".to_owned(),
+            "http://failover-test-b".to_owned(),
+        ];
+        let calls = AtomicUsize::new(0);
+        let result: RaikoResult<()> = with_failover_policy(&test_policy(), &urls, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(RaikoError::Conversion("bad value".to_owned())) }
+        })
+        .await;
+        assert!(matches!(result, Err(RaikoError::Conversion(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_on_permanent_anyhow_error() {
+        let urls = vec!["http://failover-test-permanent".to_owned()];
+        let calls = AtomicUsize::new(0);
+        let result: RaikoResult<()> = with_failover_policy(&test_policy(), &urls, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(RaikoError::Anyhow(anyhow::anyhow!("blob not available"))) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(endpoint_health(&urls[0]).consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_wrapped_rpc_error() {
+        let urls = vec!["http://failover-test-wrapped".to_owned()];
+        let calls = AtomicUsize::new(0);
+        let result: RaikoResult<()> = with_failover_policy(&test_policy(), &urls, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async {
+                Err(RaikoError::Anyhow(
+                    anyhow::Error::new(RaikoError::RPC("connection reset".to_owned()))
+                        .context("looking up the proposal"),
+                ))
+            }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_not_served_fails_over_without_retry() {
+        let urls = vec![
+            "http://failover-test-pruned".to_owned(),
+            "http://failover-test-archive".to_owned(),
+        ];
+        let calls = AtomicUsize::new(0);
+        let result = with_failover_policy(&test_policy(), &urls, |i| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            let url = urls[i].clone();
+            async move {
+                if url.ends_with("pruned") {
+                    Err(NotServed::error("blob not available anymore"))
+                } else {
+                    Ok(url)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), urls[1]);
+        assert_eq!(endpoint_health(&urls[0]).consecutive_failures, 0);
+
+        // Without any endpoint serving the call, no round is retried
+        calls.store(0, Ordering::SeqCst);
+        let result: RaikoResult<()> = with_failover_policy(&test_policy(), &urls, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(NotServed::error("method not found")) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_not_served_unless_transient() {
+        let pruned = NotServed::unless_transient(anyhow::anyhow!("blob data not available"));
+        assert_eq!(failure_kind(&pruned), FailureKind::NotServed);
+        let overloaded = NotServed::unless_transient(
+            RaikoError::RPC("Request failed with status code: 503".to_owned()).into(),
+        );
+        assert_eq!(failure_kind(&overloaded), FailureKind::Transient);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_endpoint_is_tried_last() {
+        let urls = vec![
+            "http://failover-test-flaky".to_owned(),
+            "http://failover-test-stable".to_owned(),
+        ];
+        for _ in 0..UNHEALTHY_THRESHOLD {
+            record_failure(&urls[0], &RaikoError::RPC("timeout".to_owned()));
+        }
+        assert_eq!(ordered_endpoints(&urls), vec![1, 0]);
+    }
+}
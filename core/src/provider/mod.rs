@@ -1,18 +1,22 @@
-use alloy_primitives::{Address, B256, U256};
+use alloy_primitives::{Address, Bloom, B256, U256, U64};
 use alloy_rpc_types::Block;
 use raiko_lib::consts::SupportedChainSpecs;
-use reth_primitives::revm_primitives::AccountInfo;
+use reth_primitives::{revm_primitives::AccountInfo, Receipt};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::{
     interfaces::{RaikoError, RaikoResult},
     preflight::parse_l1_batch_proposal_tx_for_pacaya_fork,
-    provider::rpc::RpcBlockDataProvider,
+    provider::{rpc::RpcBlockDataProvider, witness::ExecutionWitness},
     MerkleProof,
 };
 
 pub mod db;
+pub mod failover;
+pub mod recorder;
 pub mod rpc;
+pub mod witness;
 
 #[allow(async_fn_in_trait)]
 pub trait BlockDataProvider {
@@ -37,6 +41,39 @@ pub trait BlockDataProvider {
         offset: usize,
         num_storage_proofs: usize,
     ) -> RaikoResult<MerkleProof>;
+
+    /// Get all the state needed to execute `block_number` on top of its parent in a single
+    /// call. Only some nodes support this, so the default implementation fails.
+    async fn get_execution_witness(&self, block_number: u64) -> RaikoResult<ExecutionWitness> {
+        Err(RaikoError::RPC(format!(
+            "Execution witness for block {block_number} is not supported by this provider"
+        )))
+    }
+
+    /// Get the receipts of all the transactions of `block_number`, as executed by the node.
+    async fn get_receipts(&self, block_number: u64) -> RaikoResult<Vec<ReceiptSummary>> {
+        Err(RaikoError::RPC(format!(
+            "Receipts of block {block_number} are not supported by this provider"
+        )))
+    }
+}
+
+/// The fields of a node's transaction receipt that can be checked against a receipt derived by
+/// executing the block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptSummary {
+    pub status: U64,
+    pub cumulative_gas_used: U64,
+    pub logs_bloom: Bloom,
+}
+
+impl ReceiptSummary {
+    pub fn matches(&self, receipt: &Receipt) -> bool {
+        self.status == U64::from(receipt.success as u64)
+            && self.cumulative_gas_used == U64::from(receipt.cumulative_gas_used)
+            && self.logs_bloom == receipt.bloom_slow()
+    }
 }
 
 pub async fn get_task_data(
@@ -47,8 +84,11 @@ pub async fn get_task_data(
     let taiko_chain_spec = chain_specs
         .get_chain_spec(network)
         .ok_or_else(|| RaikoError::InvalidRequestConfig("Unsupported raiko network".to_string()))?;
-    let provider =
-        RpcBlockDataProvider::new(&taiko_chain_spec.rpc.clone(), block_number - 1).await?;
+    let provider = RpcBlockDataProvider::new_with_fallbacks(
+        &taiko_chain_spec.rpc_endpoints(),
+        block_number - 1,
+    )
+    .await?;
     let blocks = provider.get_blocks(&[(block_number, true)]).await?;
     let block = blocks
         .first()
@@ -82,9 +122,11 @@ pub async fn get_batch_task_data(
     .await?;
 
     let batch_block_number_start = all_prove_blocks.first().expect("No block numbers provided");
-    let provider =
-        RpcBlockDataProvider::new(&taiko_chain_spec.rpc.clone(), batch_block_number_start - 1)
-            .await?;
+    let provider = RpcBlockDataProvider::new_with_fallbacks(
+        &taiko_chain_spec.rpc_endpoints(),
+        batch_block_number_start - 1,
+    )
+    .await?;
     let blocks = provider
         .get_blocks(&[(*batch_block_number_start, false)])
         .await?;
@@ -0,0 +1,445 @@
+use alloy_primitives::{Address, U256};
+use alloy_rpc_types::{Block, EIP1186AccountProofResponse};
+use reth_primitives::revm_primitives::AccountInfo;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::{debug, error};
+
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    provider::{
+        rpc::RpcBlockDataProvider, witness::ExecutionWitness, BlockDataProvider, ReceiptSummary,
+    },
+    MerkleProof,
+};
+
+/// All the responses recorded by a [`RecordingBlockDataProvider`].
+///
+/// Responses are stored per item rather than per call, so that a replay works even when
+/// the caller splits its requests differently than during the recording.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RecordStore {
+    /// Blocks fetched with transaction hashes only.
+    pub blocks: BTreeMap<u64, Block>,
+    /// Blocks fetched with full transactions.
+    pub full_blocks: BTreeMap<u64, Block>,
+    pub accounts: BTreeMap<u64, HashMap<Address, AccountInfo>>,
+    pub storage_values: BTreeMap<u64, HashMap<Address, HashMap<U256, U256>>>,
+    /// Account proofs, with the storage proofs of all recorded slots merged together.
+    pub merkle_proofs: BTreeMap<u64, HashMap<Address, EIP1186AccountProofResponse>>,
+    #[serde(default)]
+    pub execution_witnesses: BTreeMap<u64, ExecutionWitness>,
+    #[serde(default)]
+    pub receipts: BTreeMap<u64, Vec<ReceiptSummary>>,
+}
+
+impl RecordStore {
+    pub fn load(path: &Path) -> RaikoResult<Self> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: &Path) -> RaikoResult<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first so an interrupted run never leaves a truncated store.
+        let tmp_path = path.with_extension("tmp");
+        let file = File::create(&tmp_path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        std::fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    fn record_merkle_proofs(&mut self, block_number: u64, proofs: &MerkleProof) {
+        let recorded = self.merkle_proofs.entry(block_number).or_default();
+        for (address, proof) in proofs {
+            match recorded.get_mut(address) {
+                Some(recorded_proof) => {
+                    for storage_proof in &proof.storage_proof {
+                        if !recorded_proof
+                            .storage_proof
+                            .iter()
+                            .any(|p| p.key.0 == storage_proof.key.0)
+                        {
+                            recorded_proof.storage_proof.push(storage_proof.clone());
+                        }
+                    }
+                }
+                None => {
+                    recorded.insert(*address, proof.clone());
+                }
+            }
+        }
+    }
+}
+
+/// A [`BlockDataProvider`] that records every response of an [`RpcBlockDataProvider`] to a
+/// local JSON file, or replays a previously recorded file without any network access.
+///
+/// Recording mode appends to an existing store, so several runs can share one fixture. The
+/// responses are kept in memory and written to the file by [`Self::flush`], or when the
+/// provider is dropped.
+pub struct RecordingBlockDataProvider {
+    /// The upstream provider, `None` when replaying.
+    inner: Option<RpcBlockDataProvider>,
+    store: Mutex<RecordedStore>,
+    path: PathBuf,
+}
+
+struct RecordedStore {
+    store: RecordStore,
+    /// Whether responses were recorded since the last flush.
+    dirty: bool,
+}
+
+impl RecordingBlockDataProvider {
+    /// Record all the responses of `inner` into the store at `path`.
+    pub fn record<P: AsRef<Path>>(inner: RpcBlockDataProvider, path: P) -> RaikoResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let store = if path.exists() {
+            RecordStore::load(&path)?
+        } else {
+            RecordStore::default()
+        };
+        debug!("Recording block data to {path:?}");
+        Ok(Self {
+            inner: Some(inner),
+            store: Mutex::new(RecordedStore {
+                store,
+                dirty: false,
+            }),
+            path,
+        })
+    }
+
+    /// Serve all the calls from the store at `path`, failing on anything not recorded.
+    pub fn replay<P: AsRef<Path>>(path: P) -> RaikoResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let store = RecordStore::load(&path)?;
+        debug!("Replaying block data from {path:?}");
+        Ok(Self {
+            inner: None,
+            store: Mutex::new(RecordedStore {
+                store,
+                dirty: false,
+            }),
+            path,
+        })
+    }
+
+    pub fn is_replay(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get a snapshot of the recorded data.
+    pub fn store(&self) -> RecordStore {
+        self.lock_store().store.clone()
+    }
+
+    /// Write the responses recorded since the last flush to the store file.
+    pub fn flush(&self) -> RaikoResult<()> {
+        let mut recorded = self.lock_store();
+        if recorded.dirty {
+            recorded.store.save(&self.path)?;
+            recorded.dirty = false;
+        }
+        Ok(())
+    }
+
+    fn lock_store(&self) -> std::sync::MutexGuard<'_, RecordedStore> {
+        self.store.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update_store<F: FnOnce(&mut RecordStore)>(&self, f: F) {
+        let mut recorded = self.lock_store();
+        f(&mut recorded.store);
+        recorded.dirty = true;
+    }
+}
+
+impl Drop for RecordingBlockDataProvider {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            error!(
+                "Failed to write the recorded block data to {:?}: {e}",
+                self.path
+            );
+        }
+    }
+}
+
+fn not_recorded(what: String) -> RaikoError {
+    RaikoError::RPC(format!("{what} not found in recorded block data"))
+}
+
+impl BlockDataProvider for RecordingBlockDataProvider {
+    async fn get_blocks(&self, blocks_to_fetch: &[(u64, bool)]) -> RaikoResult<Vec<Block>> {
+        let Some(inner) = &self.inner else {
+            let guard = self.lock_store();
+            let store = &guard.store;
+            return blocks_to_fetch
+                .iter()
+                .map(|(block_number, full)| {
+                    let blocks = if *full {
+                        &store.full_blocks
+                    } else {
+                        &store.blocks
+                    };
+                    blocks
+                        .get(block_number)
+                        .cloned()
+                        .ok_or_else(|| not_recorded(format!("Block {block_number} (full: {full})")))
+                })
+                .collect();
+        };
+
+        let blocks = inner.get_blocks(blocks_to_fetch).await?;
+        self.update_store(|store| {
+            for ((block_number, full), block) in blocks_to_fetch.iter().zip(blocks.iter()) {
+                let recorded = if *full {
+                    &mut store.full_blocks
+                } else {
+                    &mut store.blocks
+                };
+                recorded.insert(*block_number, block.clone());
+            }
+        });
+        Ok(blocks)
+    }
+
+    async fn get_accounts(
+        &self,
+        block_number: u64,
+        accounts: &[Address],
+    ) -> RaikoResult<Vec<AccountInfo>> {
+        let Some(inner) = &self.inner else {
+            let guard = self.lock_store();
+            let store = &guard.store;
+            let recorded = store.accounts.get(&block_number);
+            return accounts
+                .iter()
+                .map(|address| {
+                    recorded
+                        .and_then(|recorded| recorded.get(address))
+                        .cloned()
+                        .ok_or_else(|| {
+                            not_recorded(format!("Account {address} at block {block_number}"))
+                        })
+                })
+                .collect();
+        };
+
+        let infos = inner.get_accounts(block_number, accounts).await?;
+        self.update_store(|store| {
+            let recorded = store.accounts.entry(block_number).or_default();
+            for (address, info) in accounts.iter().zip(infos.iter()) {
+                recorded.insert(*address, info.clone());
+            }
+        });
+        Ok(infos)
+    }
+
+    async fn get_storage_values(
+        &self,
+        block_number: u64,
+        accounts: &[(Address, U256)],
+    ) -> RaikoResult<Vec<U256>> {
+        let Some(inner) = &self.inner else {
+            let guard = self.lock_store();
+            let store = &guard.store;
+            let recorded = store.storage_values.get(&block_number);
+            return accounts
+                .iter()
+                .map(|(address, key)| {
+                    recorded
+                        .and_then(|recorded| recorded.get(address))
+                        .and_then(|slots| slots.get(key))
+                        .copied()
+                        .ok_or_else(|| {
+                            not_recorded(format!(
+                                "Storage slot {key} of {address} at block {block_number}"
+                            ))
+                        })
+                })
+                .collect();
+        };
+
+        let values = inner.get_storage_values(block_number, accounts).await?;
+        self.update_store(|store| {
+            let recorded = store.storage_values.entry(block_number).or_default();
+            for ((address, key), value) in accounts.iter().zip(values.iter()) {
+                recorded.entry(*address).or_default().insert(*key, *value);
+            }
+        });
+        Ok(values)
+    }
+
+    async fn get_merkle_proofs(
+        &self,
+        block_number: u64,
+        accounts: HashMap<Address, Vec<U256>>,
+        offset: usize,
+        num_storage_proofs: usize,
+    ) -> RaikoResult<MerkleProof> {
+        let Some(inner) = &self.inner else {
+            let guard = self.lock_store();
+            let store = &guard.store;
+            let recorded = store.merkle_proofs.get(&block_number);
+            let mut proofs: MerkleProof = HashMap::new();
+            for (address, keys) in accounts {
+                let mut proof = recorded
+                    .and_then(|recorded| recorded.get(&address))
+                    .cloned()
+                    .ok_or_else(|| {
+                        not_recorded(format!("Merkle proof of {address} at block {block_number}"))
+                    })?;
+                let mut storage_proof = Vec::with_capacity(keys.len());
+                for key in keys {
+                    let slot = proof
+                        .storage_proof
+                        .iter()
+                        .find(|p| U256::from_be_bytes(p.key.0 .0) == key)
+                        .cloned()
+                        .ok_or_else(|| {
+                            not_recorded(format!(
+                                "Storage proof of slot {key} of {address} at block {block_number}"
+                            ))
+                        })?;
+                    storage_proof.push(slot);
+                }
+                proof.storage_proof = storage_proof;
+                proofs.insert(address, proof);
+            }
+            return Ok(proofs);
+        };
+
+        let proofs = inner
+            .get_merkle_proofs(block_number, accounts, offset, num_storage_proofs)
+            .await?;
+        self.update_store(|store| store.record_merkle_proofs(block_number, &proofs));
+        Ok(proofs)
+    }
+
+    async fn get_execution_witness(&self, block_number: u64) -> RaikoResult<ExecutionWitness> {
+        let Some(inner) = &self.inner else {
+            return self
+                .lock_store()
+                .store
+                .execution_witnesses
+                .get(&block_number)
+                .cloned()
+                .ok_or_else(|| not_recorded(format!("Execution witness of block {block_number}")));
+        };
+
+        let witness = inner.get_execution_witness(block_number).await?;
+        self.update_store(|store| {
+            store
+                .execution_witnesses
+                .insert(block_number, witness.clone());
+        });
+        Ok(witness)
+    }
+
+    async fn get_receipts(&self, block_number: u64) -> RaikoResult<Vec<ReceiptSummary>> {
+        let Some(inner) = &self.inner else {
+            return self
+                .lock_store()
+                .store
+                .receipts
+                .get(&block_number)
+                .cloned()
+                .ok_or_else(|| not_recorded(format!("Receipts of block {block_number}")));
+        };
+
+        let receipts = inner.get_receipts(block_number).await?;
+        self.update_store(|store| {
+            store.receipts.insert(block_number, receipts.clone());
+        });
+        Ok(receipts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    fn temp_store_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("raiko-recorder-{name}-{}.json", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_replay_from_store() {
+        let path = temp_store_path("replay");
+        let addr = address!("0000000000000000000000000000000000000001");
+
+        let mut store = RecordStore::default();
+        store
+            .accounts
+            .entry(1)
+            .or_default()
+            .insert(addr, AccountInfo::default());
+        store
+            .storage_values
+            .entry(1)
+            .or_default()
+            .entry(addr)
+            .or_default()
+            .insert(U256::from(7), U256::from(42));
+        store.save(&path).unwrap();
+
+        let provider = RecordingBlockDataProvider::replay(&path).unwrap();
+        assert!(provider.is_replay());
+        assert_eq!(
+            provider.get_accounts(1, &[addr]).await.unwrap(),
+            vec![AccountInfo::default()]
+        );
+        assert_eq!(
+            provider
+                .get_storage_values(1, &[(addr, U256::from(7))])
+                .await
+                .unwrap(),
+            vec![U256::from(42)]
+        );
+        // Anything that was not recorded must fail instead of reaching the network.
+        assert!(provider.get_accounts(2, &[addr]).await.is_err());
+        assert!(provider.get_blocks(&[(1, false)]).await.is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_records_are_written_on_flush() {
+        let path = temp_store_path("flush");
+        RecordStore::default().save(&path).unwrap();
+
+        let provider = RecordingBlockDataProvider::replay(&path).unwrap();
+        provider.update_store(|store| {
+            store.receipts.insert(1, Vec::new());
+        });
+        // Nothing is written before the flush
+        assert!(RecordStore::load(&path).unwrap().receipts.is_empty());
+        provider.flush().unwrap();
+        assert!(RecordStore::load(&path).unwrap().receipts.contains_key(&1));
+
+        // Dropping the provider flushes the rest
+        provider.update_store(|store| {
+            store.receipts.insert(2, Vec::new());
+        });
+        drop(provider);
+        assert!(RecordStore::load(&path).unwrap().receipts.contains_key(&2));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,110 +1,151 @@
 use alloy_primitives::{Address, Bytes, StorageKey, Uint, U256};
-use alloy_provider::{ProviderBuilder, ReqwestProvider, RootProvider};
+use alloy_provider::{Provider, ProviderBuilder, ReqwestProvider, RootProvider};
 use alloy_rpc_client::{ClientBuilder, RpcClient};
 use alloy_rpc_types::{Block, BlockId, BlockNumberOrTag, EIP1186AccountProofResponse};
 use alloy_transport_http::Http;
+use lazy_static::lazy_static;
 use raiko_lib::clear_line;
 use reqwest_alloy::Client;
 use reth_primitives::revm_primitives::{AccountInfo, Bytecode};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Mutex,
+};
 use tracing::debug;
 
 use crate::{
     interfaces::{RaikoError, RaikoResult},
-    provider::BlockDataProvider,
+    provider::{
+        failover::{with_failover, NotServed},
+        witness::ExecutionWitness,
+        BlockDataProvider, ReceiptSummary,
+    },
     MerkleProof,
 };
 
+/// JSON-RPC error code of a method the endpoint does not provide.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+lazy_static! {
+    /// Urls of the endpoints without `debug_executionWitness`, which are not asked for it again.
+    static ref WITNESS_UNSUPPORTED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
 #[derive(Clone)]
 pub struct RpcBlockDataProvider {
+    /// Provider of the primary endpoint.
     pub provider: ReqwestProvider,
+    /// Client of the primary endpoint.
     pub client: RpcClient<Http<Client>>,
+    /// Urls of all the endpoints, the primary one first.
+    urls: Vec<String>,
+    /// Clients of all the endpoints, in the same order as `urls`.
+    clients: Vec<RpcClient<Http<Client>>>,
+    /// Providers of all the endpoints, in the same order as `urls`.
+    providers: Vec<ReqwestProvider>,
     block_numbers: Vec<u64>,
 }
 
 impl RpcBlockDataProvider {
     /// async will be used for future preflight optimization
     pub async fn new(url: &str, block_number: u64) -> RaikoResult<Self> {
-        let url =
-            reqwest::Url::parse(url).map_err(|_| RaikoError::RPC("Invalid RPC URL".to_owned()))?;
-        debug!(
-            "provider rpc url: {:?} for block_number {}",
-            url, block_number
-        );
-        Ok(Self {
-            provider: ProviderBuilder::new().on_provider(RootProvider::new_http(url.clone())),
-            client: ClientBuilder::default().http(url),
-            block_numbers: vec![block_number, block_number + 1],
-        })
+        Self::new_with_fallbacks(&[url.to_owned()], block_number).await
+    }
+
+    /// Create a provider failing over to the next url of `urls` when a call fails.
+    pub async fn new_with_fallbacks(urls: &[String], block_number: u64) -> RaikoResult<Self> {
+        debug!("provider rpc urls: {urls:?} for block_number {block_number}");
+        Self::from_urls(urls, vec![block_number, block_number + 1])
     }
 
     pub async fn new_batch(url: &str, block_numbers: Vec<u64>) -> RaikoResult<Self> {
+        Self::new_batch_with_fallbacks(&[url.to_owned()], block_numbers).await
+    }
+
+    pub async fn new_batch_with_fallbacks(
+        urls: &[String],
+        block_numbers: Vec<u64>,
+    ) -> RaikoResult<Self> {
         assert!(
             !block_numbers.is_empty() && block_numbers.len() > 1,
             "batch block_numbers should have at least 2 elements"
         );
-        let url =
-            reqwest::Url::parse(url).map_err(|_| RaikoError::RPC("Invalid RPC URL".to_owned()))?;
         debug!(
-            "Batch provider rpc: {:?} for block_number {}",
-            url, block_numbers[0]
+            "Batch provider rpc: {urls:?} for block_number {}",
+            block_numbers[0]
         );
+        Self::from_urls(urls, block_numbers)
+    }
+
+    fn from_urls(urls: &[String], block_numbers: Vec<u64>) -> RaikoResult<Self> {
+        let parsed_urls = urls
+            .iter()
+            .map(|url| {
+                reqwest::Url::parse(url).map_err(|_| RaikoError::RPC("Invalid RPC URL".to_owned()))
+            })
+            .collect::<RaikoResult<Vec<_>>>()?;
+        let primary = parsed_urls
+            .first()
+            .cloned()
+            .ok_or_else(|| RaikoError::RPC("No RPC URL provided".to_owned()))?;
         Ok(Self {
-            provider: ProviderBuilder::new().on_provider(RootProvider::new_http(url.clone())),
-            client: ClientBuilder::default().http(url),
+            provider: ProviderBuilder::new().on_provider(RootProvider::new_http(primary.clone())),
+            client: ClientBuilder::default().http(primary),
+            urls: urls.to_vec(),
+            clients: parsed_urls
+                .iter()
+                .map(|url| ClientBuilder::default().http(url.clone()))
+                .collect(),
+            providers: parsed_urls
+                .into_iter()
+                .map(|url| ProviderBuilder::new().on_provider(RootProvider::new_http(url)))
+                .collect(),
             block_numbers,
         })
     }
 
+    /// The provider of the primary endpoint, without failover.
     pub fn provider(&self) -> &ReqwestProvider {
         &self.provider
     }
-}
 
-impl BlockDataProvider for RpcBlockDataProvider {
-    async fn get_blocks(&self, blocks_to_fetch: &[(u64, bool)]) -> RaikoResult<Vec<Block>> {
-        let mut all_blocks = Vec::with_capacity(blocks_to_fetch.len());
-
-        let max_batch_size = 32;
-        for blocks_to_fetch in blocks_to_fetch.chunks(max_batch_size) {
-            let mut batch = self.client.new_batch();
-            let mut requests = Vec::with_capacity(max_batch_size);
-
-            for (block_number, full) in blocks_to_fetch {
-                requests.push(Box::pin(
-                    batch
-                        .add_call(
-                            "eth_getBlockByNumber",
-                            &(BlockNumberOrTag::from(*block_number), full),
-                        )
-                        .map_err(|_| {
-                            RaikoError::RPC(
-                                "Failed adding eth_getBlockByNumber call to batch".to_owned(),
-                            )
-                        })?,
-                ));
-            }
+    /// Run `op` with the provider of each endpoint in turn until it succeeds, failing over
+    /// like the [`BlockDataProvider`] calls.
+    pub async fn with_provider<T, F, Fut>(&self, mut op: F) -> RaikoResult<T>
+    where
+        F: FnMut(ReqwestProvider) -> Fut,
+        Fut: Future<Output = RaikoResult<T>>,
+    {
+        with_failover(&self.urls, |i| op(self.providers[i].clone())).await
+    }
 
-            batch.send().await.map_err(|e| {
-                RaikoError::RPC(format!(
-                    "Error sending batch request for block {blocks_to_fetch:?}: {e}"
-                ))
-            })?;
-
-            let mut blocks = Vec::with_capacity(max_batch_size);
-            // Collect the data from the batch
-            for request in requests {
-                blocks.push(
-                    request.await.map_err(|e| {
-                        RaikoError::RPC(format!("Error collecting request data: {e}"))
-                    })?,
-                );
-            }
+    pub async fn get_block_number(&self) -> RaikoResult<u64> {
+        self.with_provider(|provider| async move {
+            provider
+                .get_block_number()
+                .await
+                .map_err(|e| RaikoError::RPC(format!("Error fetching the block number: {e}")))
+        })
+        .await
+    }
 
-            all_blocks.append(&mut blocks);
-        }
+    fn assert_block_number(&self, block_number: u64) {
+        assert!(
+            self.block_numbers.contains(&block_number),
+            "Block number {} not found in {:?}",
+            block_number,
+            self.block_numbers
+        );
+    }
+}
 
-        Ok(all_blocks)
+impl BlockDataProvider for RpcBlockDataProvider {
+    async fn get_blocks(&self, blocks_to_fetch: &[(u64, bool)]) -> RaikoResult<Vec<Block>> {
+        with_failover(&self.urls, |i| {
+            fetch_blocks(&self.clients[i], blocks_to_fetch)
+        })
+        .await
     }
 
     async fn get_accounts(
@@ -112,259 +153,395 @@ impl BlockDataProvider for RpcBlockDataProvider {
         block_number: u64,
         accounts: &[Address],
     ) -> RaikoResult<Vec<AccountInfo>> {
-        assert!(
-            self.block_numbers.contains(&block_number),
-            "Block number {} not found in {:?}",
-            block_number,
-            self.block_numbers
-        );
-        let mut all_accounts = Vec::with_capacity(accounts.len());
+        self.assert_block_number(block_number);
+        with_failover(&self.urls, |i| {
+            fetch_accounts(&self.clients[i], block_number, accounts)
+        })
+        .await
+    }
+
+    async fn get_storage_values(
+        &self,
+        block_number: u64,
+        accounts: &[(Address, U256)],
+    ) -> RaikoResult<Vec<U256>> {
+        self.assert_block_number(block_number);
+        with_failover(&self.urls, |i| {
+            fetch_storage_values(&self.clients[i], block_number, accounts)
+        })
+        .await
+    }
 
-        let max_batch_size = 250;
-        for accounts in accounts.chunks(max_batch_size) {
-            let mut batch = self.client.new_batch();
+    async fn get_merkle_proofs(
+        &self,
+        block_number: u64,
+        accounts: HashMap<Address, Vec<U256>>,
+        offset: usize,
+        num_storage_proofs: usize,
+    ) -> RaikoResult<MerkleProof> {
+        self.assert_block_number(block_number);
+        with_failover(&self.urls, |i| {
+            fetch_merkle_proofs(
+                &self.clients[i],
+                block_number,
+                accounts.clone(),
+                offset,
+                num_storage_proofs,
+            )
+        })
+        .await
+    }
 
-            let mut nonce_requests = Vec::with_capacity(max_batch_size);
-            let mut balance_requests = Vec::with_capacity(max_batch_size);
-            let mut code_requests = Vec::with_capacity(max_batch_size);
+    async fn get_execution_witness(&self, block_number: u64) -> RaikoResult<ExecutionWitness> {
+        // Only ask the endpoints which did not already tell they lack the method
+        let (indices, urls): (Vec<_>, Vec<_>) = {
+            let unsupported = WITNESS_UNSUPPORTED.lock().unwrap();
+            self.urls
+                .iter()
+                .enumerate()
+                .filter(|(_, url)| !unsupported.contains(*url))
+                .map(|(i, url)| (i, url.clone()))
+                .unzip()
+        };
+        if urls.is_empty() {
+            return Err(NotServed::error(
+                "No endpoint supports debug_executionWitness",
+            ));
+        }
 
-            for address in accounts {
-                nonce_requests.push(Box::pin(
-                    batch
-                        .add_call::<_, Uint<64, 1>>(
-                            "eth_getTransactionCount",
-                            &(address, Some(BlockId::from(block_number))),
-                        )
-                        .map_err(|_| {
-                            RaikoError::RPC(
-                                "Failed adding eth_getTransactionCount call to batch".to_owned(),
-                            )
-                        })?,
-                ));
-                balance_requests.push(Box::pin(
-                    batch
-                        .add_call::<_, Uint<256, 4>>(
-                            "eth_getBalance",
-                            &(address, Some(BlockId::from(block_number))),
-                        )
-                        .map_err(|_| {
-                            RaikoError::RPC("Failed adding eth_getBalance call to batch".to_owned())
-                        })?,
-                ));
-                code_requests.push(Box::pin(
-                    batch
-                        .add_call::<_, Bytes>(
-                            "eth_getCode",
-                            &(address, Some(BlockId::from(block_number))),
-                        )
-                        .map_err(|_| {
-                            RaikoError::RPC("Failed adding eth_getCode call to batch".to_owned())
-                        })?,
-                ));
+        with_failover(&urls, |i| {
+            let client = &self.clients[indices[i]];
+            let url = &urls[i];
+            async move {
+                client
+                    .request(
+                        "debug_executionWitness",
+                        (BlockNumberOrTag::from(block_number),),
+                    )
+                    .await
+                    .map_err(|e| {
+                        if e.as_error_resp()
+                            .is_some_and(|resp| resp.code == METHOD_NOT_FOUND)
+                        {
+                            debug!("Endpoint {url} does not support debug_executionWitness");
+                            WITNESS_UNSUPPORTED.lock().unwrap().insert(url.clone());
+                            return NotServed::error(format!(
+                                "Endpoint {url} does not support debug_executionWitness"
+                            ));
+                        }
+                        RaikoError::RPC(format!(
+                            "Error fetching execution witness for block {block_number}: {e}"
+                        ))
+                    })
             }
+        })
+        .await
+    }
 
-            batch
-                .send()
-                .await
-                .map_err(|e| RaikoError::RPC(format!("Error sending batch request {e}")))?;
+    async fn get_receipts(&self, block_number: u64) -> RaikoResult<Vec<ReceiptSummary>> {
+        with_failover(&self.urls, |i| {
+            let client = &self.clients[i];
+            async move {
+                client
+                    .request(
+                        "eth_getBlockReceipts",
+                        (BlockNumberOrTag::from(block_number),),
+                    )
+                    .await
+                    .map_err(|e| {
+                        RaikoError::RPC(format!(
+                            "Error fetching receipts of block {block_number}: {e}"
+                        ))
+                    })
+            }
+        })
+        .await
+    }
+}
 
-            let mut accounts = vec![];
-            // Collect the data from the batch
-            for ((nonce_request, balance_request), code_request) in nonce_requests
-                .into_iter()
-                .zip(balance_requests.into_iter())
-                .zip(code_requests.into_iter())
-            {
-                let (nonce, balance, code) = (
-                    nonce_request.await.map_err(|e| {
-                        RaikoError::RPC(format!("Failed to collect nonce request: {e}"))
+async fn fetch_blocks(
+    client: &RpcClient<Http<Client>>,
+    blocks_to_fetch: &[(u64, bool)],
+) -> RaikoResult<Vec<Block>> {
+    let mut all_blocks = Vec::with_capacity(blocks_to_fetch.len());
+
+    let max_batch_size = 32;
+    for blocks_to_fetch in blocks_to_fetch.chunks(max_batch_size) {
+        let mut batch = client.new_batch();
+        let mut requests = Vec::with_capacity(max_batch_size);
+
+        for (block_number, full) in blocks_to_fetch {
+            requests.push(Box::pin(
+                batch
+                    .add_call(
+                        "eth_getBlockByNumber",
+                        &(BlockNumberOrTag::from(*block_number), full),
+                    )
+                    .map_err(|_| {
+                        RaikoError::RPC(
+                            "Failed adding eth_getBlockByNumber call to batch".to_owned(),
+                        )
+                    })?,
+            ));
+        }
+
+        batch.send().await.map_err(|e| {
+            RaikoError::RPC(format!(
+                "Error sending batch request for block {blocks_to_fetch:?}: {e}"
+            ))
+        })?;
+
+        let mut blocks = Vec::with_capacity(max_batch_size);
+        // Collect the data from the batch
+        for request in requests {
+            blocks.push(
+                request
+                    .await
+                    .map_err(|e| RaikoError::RPC(format!("Error collecting request data: {e}")))?,
+            );
+        }
+
+        all_blocks.append(&mut blocks);
+    }
+
+    Ok(all_blocks)
+}
+
+async fn fetch_accounts(
+    client: &RpcClient<Http<Client>>,
+    block_number: u64,
+    accounts: &[Address],
+) -> RaikoResult<Vec<AccountInfo>> {
+    let mut all_accounts = Vec::with_capacity(accounts.len());
+
+    let max_batch_size = 250;
+    for accounts in accounts.chunks(max_batch_size) {
+        let mut batch = client.new_batch();
+
+        let mut nonce_requests = Vec::with_capacity(max_batch_size);
+        let mut balance_requests = Vec::with_capacity(max_batch_size);
+        let mut code_requests = Vec::with_capacity(max_batch_size);
+
+        for address in accounts {
+            nonce_requests.push(Box::pin(
+                batch
+                    .add_call::<_, Uint<64, 1>>(
+                        "eth_getTransactionCount",
+                        &(address, Some(BlockId::from(block_number))),
+                    )
+                    .map_err(|_| {
+                        RaikoError::RPC(
+                            "Failed adding eth_getTransactionCount call to batch".to_owned(),
+                        )
                     })?,
-                    balance_request.await.map_err(|e| {
-                        RaikoError::RPC(format!("Failed to collect balance request: {e}"))
+            ));
+            balance_requests.push(Box::pin(
+                batch
+                    .add_call::<_, Uint<256, 4>>(
+                        "eth_getBalance",
+                        &(address, Some(BlockId::from(block_number))),
+                    )
+                    .map_err(|_| {
+                        RaikoError::RPC("Failed adding eth_getBalance call to batch".to_owned())
                     })?,
-                    code_request.await.map_err(|e| {
-                        RaikoError::RPC(format!("Failed to collect code request: {e}"))
+            ));
+            code_requests.push(Box::pin(
+                batch
+                    .add_call::<_, Bytes>(
+                        "eth_getCode",
+                        &(address, Some(BlockId::from(block_number))),
+                    )
+                    .map_err(|_| {
+                        RaikoError::RPC("Failed adding eth_getCode call to batch".to_owned())
                     })?,
-                );
+            ));
+        }
 
-                let nonce = nonce.try_into().map_err(|_| {
-                    RaikoError::Conversion("Failed to convert nonce to u64".to_owned())
-                })?;
+        batch
+            .send()
+            .await
+            .map_err(|e| RaikoError::RPC(format!("Error sending batch request {e}")))?;
+
+        let mut accounts = vec![];
+        // Collect the data from the batch
+        for ((nonce_request, balance_request), code_request) in nonce_requests
+            .into_iter()
+            .zip(balance_requests.into_iter())
+            .zip(code_requests.into_iter())
+        {
+            let (nonce, balance, code) = (
+                nonce_request.await.map_err(|e| {
+                    RaikoError::RPC(format!("Failed to collect nonce request: {e}"))
+                })?,
+                balance_request.await.map_err(|e| {
+                    RaikoError::RPC(format!("Failed to collect balance request: {e}"))
+                })?,
+                code_request
+                    .await
+                    .map_err(|e| RaikoError::RPC(format!("Failed to collect code request: {e}")))?,
+            );
 
-                let bytecode = Bytecode::new_raw(code);
+            let nonce = nonce
+                .try_into()
+                .map_err(|_| RaikoError::Conversion("Failed to convert nonce to u64".to_owned()))?;
 
-                let account_info = AccountInfo::new(balance, nonce, bytecode.hash_slow(), bytecode);
+            let bytecode = Bytecode::new_raw(code);
 
-                accounts.push(account_info);
-            }
+            let account_info = AccountInfo::new(balance, nonce, bytecode.hash_slow(), bytecode);
 
-            all_accounts.append(&mut accounts);
+            accounts.push(account_info);
         }
 
-        Ok(all_accounts)
+        all_accounts.append(&mut accounts);
     }
 
-    async fn get_storage_values(
-        &self,
-        block_number: u64,
-        accounts: &[(Address, U256)],
-    ) -> RaikoResult<Vec<U256>> {
-        assert!(
-            self.block_numbers.contains(&block_number),
-            "Block number {} not found in {:?}",
-            block_number,
-            self.block_numbers
-        );
-        let mut all_values = Vec::with_capacity(accounts.len());
+    Ok(all_accounts)
+}
 
-        let max_batch_size = 1000;
-        for accounts in accounts.chunks(max_batch_size) {
-            let mut batch = self.client.new_batch();
+async fn fetch_storage_values(
+    client: &RpcClient<Http<Client>>,
+    block_number: u64,
+    accounts: &[(Address, U256)],
+) -> RaikoResult<Vec<U256>> {
+    let mut all_values = Vec::with_capacity(accounts.len());
+
+    let max_batch_size = 1000;
+    for accounts in accounts.chunks(max_batch_size) {
+        let mut batch = client.new_batch();
+
+        let mut requests = Vec::with_capacity(max_batch_size);
+
+        for (address, key) in accounts {
+            requests.push(Box::pin(
+                batch
+                    .add_call::<_, U256>(
+                        "eth_getStorageAt",
+                        &(address, key, Some(BlockId::from(block_number))),
+                    )
+                    .map_err(|_| {
+                        RaikoError::RPC("Failed adding eth_getStorageAt call to batch".to_owned())
+                    })?,
+            ));
+        }
 
-            let mut requests = Vec::with_capacity(max_batch_size);
+        batch
+            .send()
+            .await
+            .map_err(|e| RaikoError::RPC(format!("Error sending batch request {e}")))?;
 
-            for (address, key) in accounts {
+        let mut values = Vec::with_capacity(max_batch_size);
+        // Collect the data from the batch
+        for request in requests {
+            values.push(
+                request
+                    .await
+                    .map_err(|e| RaikoError::RPC(format!("Error collecting request data: {e}")))?,
+            );
+        }
+
+        all_values.append(&mut values);
+    }
+
+    Ok(all_values)
+}
+
+async fn fetch_merkle_proofs(
+    client: &RpcClient<Http<Client>>,
+    block_number: u64,
+    accounts: HashMap<Address, Vec<U256>>,
+    offset: usize,
+    num_storage_proofs: usize,
+) -> RaikoResult<MerkleProof> {
+    let mut storage_proofs: MerkleProof = HashMap::new();
+    let mut idx = offset;
+
+    let mut accounts = accounts.clone();
+
+    let batch_limit = 1000;
+    while !accounts.is_empty() {
+        #[cfg(debug_assertions)]
+        raiko_lib::inplace_print(&format!(
+            "fetching storage proof {idx}/{num_storage_proofs}..."
+        ));
+        #[cfg(not(debug_assertions))]
+        tracing::trace!("Fetching storage proof {idx}/{num_storage_proofs}...");
+
+        // Create a batch for all storage proofs
+        let mut batch = client.new_batch();
+
+        // Collect all requests
+        let mut requests = Vec::new();
+
+        let mut batch_size = 0;
+        while !accounts.is_empty() && batch_size < batch_limit {
+            let mut address_to_remove = None;
+
+            if let Some((address, keys)) = accounts.iter_mut().next() {
+                // Calculate how many keys we can still process
+                let num_keys_to_process = if batch_size + keys.len() < batch_limit {
+                    keys.len()
+                } else {
+                    batch_limit - batch_size
+                };
+
+                // If we can process all keys, remove the address from the map after the loop
+                if num_keys_to_process == keys.len() {
+                    address_to_remove = Some(*address);
+                }
+
+                // Extract the keys to process
+                let keys_to_process = keys
+                    .drain(0..num_keys_to_process)
+                    .map(StorageKey::from)
+                    .collect::<Vec<_>>();
+
+                // Add the request
                 requests.push(Box::pin(
                     batch
-                        .add_call::<_, U256>(
-                            "eth_getStorageAt",
-                            &(address, key, Some(BlockId::from(block_number))),
+                        .add_call::<_, EIP1186AccountProofResponse>(
+                            "eth_getProof",
+                            &(
+                                *address,
+                                keys_to_process.clone(),
+                                BlockId::from(block_number),
+                            ),
                         )
                         .map_err(|_| {
-                            RaikoError::RPC(
-                                "Failed adding eth_getStorageAt call to batch".to_owned(),
-                            )
+                            RaikoError::RPC("Failed adding eth_getProof call to batch".to_owned())
                         })?,
                 ));
-            }
 
-            batch
-                .send()
-                .await
-                .map_err(|e| RaikoError::RPC(format!("Error sending batch request {e}")))?;
-
-            let mut values = Vec::with_capacity(max_batch_size);
-            // Collect the data from the batch
-            for request in requests {
-                values.push(
-                    request.await.map_err(|e| {
-                        RaikoError::RPC(format!("Error collecting request data: {e}"))
-                    })?,
-                );
+                // Keep track of how many keys were processed
+                // Add an additional 1 for the account proof itself
+                batch_size += 1 + keys_to_process.len();
             }
 
-            all_values.append(&mut values);
+            // Remove the address if all keys were processed for this account
+            if let Some(address) = address_to_remove {
+                accounts.remove(&address);
+            }
         }
 
-        Ok(all_values)
-    }
+        // Send the batch
+        batch
+            .send()
+            .await
+            .map_err(|e| RaikoError::RPC(format!("Error sending batch request {e}")))?;
 
-    async fn get_merkle_proofs(
-        &self,
-        block_number: u64,
-        accounts: HashMap<Address, Vec<U256>>,
-        offset: usize,
-        num_storage_proofs: usize,
-    ) -> RaikoResult<MerkleProof> {
-        assert!(
-            self.block_numbers.contains(&block_number),
-            "Block number {} not found in {:?}",
-            block_number,
-            self.block_numbers
-        );
-        let mut storage_proofs: MerkleProof = HashMap::new();
-        let mut idx = offset;
-
-        let mut accounts = accounts.clone();
-
-        let batch_limit = 1000;
-        while !accounts.is_empty() {
-            #[cfg(debug_assertions)]
-            raiko_lib::inplace_print(&format!(
-                "fetching storage proof {idx}/{num_storage_proofs}..."
-            ));
-            #[cfg(not(debug_assertions))]
-            tracing::trace!("Fetching storage proof {idx}/{num_storage_proofs}...");
-
-            // Create a batch for all storage proofs
-            let mut batch = self.client.new_batch();
-
-            // Collect all requests
-            let mut requests = Vec::new();
-
-            let mut batch_size = 0;
-            while !accounts.is_empty() && batch_size < batch_limit {
-                let mut address_to_remove = None;
-
-                if let Some((address, keys)) = accounts.iter_mut().next() {
-                    // Calculate how many keys we can still process
-                    let num_keys_to_process = if batch_size + keys.len() < batch_limit {
-                        keys.len()
-                    } else {
-                        batch_limit - batch_size
-                    };
-
-                    // If we can process all keys, remove the address from the map after the loop
-                    if num_keys_to_process == keys.len() {
-                        address_to_remove = Some(*address);
-                    }
-
-                    // Extract the keys to process
-                    let keys_to_process = keys
-                        .drain(0..num_keys_to_process)
-                        .map(StorageKey::from)
-                        .collect::<Vec<_>>();
-
-                    // Add the request
-                    requests.push(Box::pin(
-                        batch
-                            .add_call::<_, EIP1186AccountProofResponse>(
-                                "eth_getProof",
-                                &(
-                                    *address,
-                                    keys_to_process.clone(),
-                                    BlockId::from(block_number),
-                                ),
-                            )
-                            .map_err(|_| {
-                                RaikoError::RPC(
-                                    "Failed adding eth_getProof call to batch".to_owned(),
-                                )
-                            })?,
-                    ));
-
-                    // Keep track of how many keys were processed
-                    // Add an additional 1 for the account proof itself
-                    batch_size += 1 + keys_to_process.len();
-                }
-
-                // Remove the address if all keys were processed for this account
-                if let Some(address) = address_to_remove {
-                    accounts.remove(&address);
-                }
-            }
-
-            // Send the batch
-            batch
-                .send()
+        // Collect the data from the batch
+        for request in requests {
+            let mut proof = request
                 .await
-                .map_err(|e| RaikoError::RPC(format!("Error sending batch request {e}")))?;
-
-            // Collect the data from the batch
-            for request in requests {
-                let mut proof = request
-                    .await
-                    .map_err(|e| RaikoError::RPC(format!("Error collecting request data: {e}")))?;
-                idx += proof.storage_proof.len();
-                if let Some(map_proof) = storage_proofs.get_mut(&proof.address) {
-                    map_proof.storage_proof.append(&mut proof.storage_proof);
-                } else {
-                    storage_proofs.insert(proof.address, proof);
-                }
+                .map_err(|e| RaikoError::RPC(format!("Error collecting request data: {e}")))?;
+            idx += proof.storage_proof.len();
+            if let Some(map_proof) = storage_proofs.get_mut(&proof.address) {
+                map_proof.storage_proof.append(&mut proof.storage_proof);
+            } else {
+                storage_proofs.insert(proof.address, proof);
             }
         }
-        clear_line();
-
-        Ok(storage_proofs)
     }
+    clear_line();
+
+    Ok(storage_proofs)
 }
@@ -0,0 +1,182 @@
+use alloy_primitives::{Address, Bytes, B256, U256};
+use raiko_lib::primitives::{
+    keccak::keccak,
+    mpt::{
+        resolve_nodes, MptNode, MptNodeData, MptNodeReference, StateAccount, StorageEntry,
+        EMPTY_ROOT, KECCAK_EMPTY,
+    },
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+
+use crate::interfaces::{RaikoError, RaikoResult};
+
+/// The response of `debug_executionWitness`: everything needed to execute a block on top of
+/// the state of its parent.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionWitness {
+    /// RLP encoded state and storage trie nodes.
+    #[serde(deserialize_with = "deserialize_bytes_list")]
+    pub state: Vec<Bytes>,
+    /// Bytecode of all the contracts executed.
+    #[serde(default, deserialize_with = "deserialize_bytes_list")]
+    pub codes: Vec<Bytes>,
+    /// Preimages of the hashed trie keys, i.e. the accessed addresses and storage slots.
+    #[serde(default, deserialize_with = "deserialize_bytes_list")]
+    pub keys: Vec<Bytes>,
+}
+
+/// Nodes return the witness fields either as plain lists or keyed by their hash.
+fn deserialize_bytes_list<'de, D>(deserializer: D) -> Result<Vec<Bytes>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BytesList {
+        List(Vec<Bytes>),
+        Map(HashMap<B256, Bytes>),
+        Null(()),
+    }
+
+    Ok(match BytesList::deserialize(deserializer)? {
+        BytesList::List(list) => list,
+        BytesList::Map(map) => map.into_values().collect(),
+        BytesList::Null(()) => Vec::new(),
+    })
+}
+
+/// The state parts of a [`raiko_lib::input::GuestInput`] built from an [`ExecutionWitness`].
+pub struct WitnessState {
+    pub parent_state_trie: MptNode,
+    pub parent_storage: HashMap<Address, StorageEntry>,
+    pub contracts: Vec<Bytes>,
+}
+
+impl ExecutionWitness {
+    /// Build the parent state trie, the storage tries of all the accessed accounts and the
+    /// contracts directly from the witness, without any further RPC call.
+    pub fn into_state(self, state_root: B256) -> RaikoResult<WitnessState> {
+        let mut node_store = HashMap::with_capacity(self.state.len());
+        for encoded in &self.state {
+            let node = MptNode::decode(encoded)
+                .map_err(|e| RaikoError::Preflight(format!("Invalid witness trie node: {e}")))?;
+            // Nodes shorter than 32 bytes are embedded in their parent and never referenced.
+            if let reference @ MptNodeReference::Digest(_) = node.reference() {
+                node_store.insert(reference, node);
+            }
+        }
+        let resolve = |root: B256| -> RaikoResult<MptNode> {
+            if root == EMPTY_ROOT || root == B256::ZERO {
+                return Ok(MptNode::default());
+            }
+            let node = node_store
+                .get(&MptNodeReference::Digest(root))
+                .ok_or_else(|| {
+                    RaikoError::Preflight(format!("Witness is missing the trie root {root}"))
+                })?;
+            Ok(resolve_nodes(node, &node_store))
+        };
+
+        let parent_state_trie = resolve(state_root)?;
+
+        let (addresses, slots): (Vec<_>, Vec<_>) =
+            self.keys.iter().partition(|key| key.len() == 20);
+        let slots = slots
+            .into_iter()
+            .filter(|key| key.len() == 32)
+            .map(|key| U256::from_be_slice(key))
+            .collect::<Vec<_>>();
+
+        let mut parent_storage = HashMap::with_capacity(addresses.len());
+        for key in addresses {
+            let address = Address::from_slice(key);
+            let account = parent_state_trie
+                .get_rlp::<StateAccount>(&keccak(address))
+                .map_err(|e| {
+                    RaikoError::Preflight(format!("Witness is incomplete for {address}: {e}"))
+                })?
+                .unwrap_or_default();
+            let storage_trie = match resolve(account.storage_root) {
+                Ok(trie) => trie,
+                // Storage of an account that is never read does not need to be in the witness.
+                Err(_) => MptNodeData::Digest(account.storage_root).into(),
+            };
+
+            // The witness doesn't tell which account a slot belongs to, so keep every slot
+            // the storage trie can answer for. Accounts without code and storage can't be read.
+            let account_slots =
+                if account.storage_root == EMPTY_ROOT && account.code_hash == KECCAK_EMPTY {
+                    Vec::new()
+                } else {
+                    slots
+                        .iter()
+                        .filter(|slot| storage_trie.get(&keccak(slot.to_be_bytes::<32>())).is_ok())
+                        .copied()
+                        .collect()
+                };
+            parent_storage.insert(address, (storage_trie, account_slots));
+        }
+
+        Ok(WitnessState {
+            parent_state_trie,
+            parent_storage,
+            contracts: self.codes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+    use alloy_rlp::Encodable;
+
+    #[test]
+    fn test_deserialize_witness_formats() {
+        let list: ExecutionWitness =
+            serde_json::from_str(r#"{"state":["0x01"],"codes":["0x02"],"keys":["0x03"]}"#).unwrap();
+        let map: ExecutionWitness = serde_json::from_str(
+            r#"{"state":{"0x0000000000000000000000000000000000000000000000000000000000000000":"0x01"},"codes":null}"#,
+        )
+        .unwrap();
+        assert_eq!(list.state, map.state);
+        assert!(map.codes.is_empty());
+        assert!(map.keys.is_empty());
+    }
+
+    #[test]
+    fn test_witness_into_state() {
+        let address = address!("00000000000000000000000000000000000000aa");
+        let account = StateAccount {
+            nonce: 1,
+            balance: U256::from(100),
+            ..Default::default()
+        };
+        let mut state_trie = MptNode::default();
+        state_trie
+            .insert_rlp(&keccak(address), account.clone())
+            .unwrap();
+
+        let mut encoded = Vec::new();
+        state_trie.encode(&mut encoded);
+        let witness = ExecutionWitness {
+            state: vec![encoded.into()],
+            codes: vec![],
+            keys: vec![Bytes::copy_from_slice(address.as_slice())],
+        };
+
+        let state = witness.into_state(state_trie.hash()).unwrap();
+        assert_eq!(state.parent_state_trie.hash(), state_trie.hash());
+        assert_eq!(
+            state
+                .parent_state_trie
+                .get_rlp::<StateAccount>(&keccak(address))
+                .unwrap(),
+            Some(account)
+        );
+        let (storage_trie, slots) = &state.parent_storage[&address];
+        assert_eq!(storage_trie.hash(), EMPTY_ROOT);
+        assert!(slots.is_empty());
+    }
+}
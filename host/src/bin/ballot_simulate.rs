@@ -0,0 +1,183 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use chrono::DateTime;
+use clap::Parser;
+use raiko_ballot::{simulate, BallotRule, BlockMetadata, BlockSample};
+use raiko_core::provider::{rpc::RpcBlockDataProvider, BlockDataProvider};
+use raiko_lib::{consts::SupportedChainSpecs, primitives::BlockHash, proof_type::ProofType};
+use tracing::info;
+
+/// How many blocks are fetched per RPC batch.
+const FETCH_BATCH_SIZE: u64 = 100;
+
+/// Replay a ballot config over historical blocks, and report how many proofs of each type it
+/// would have requested per day, and which draws the per-day limits dropped.
+///
+/// The blocks are read from a file with a line per block, or fetched from the RPC of a network:
+///
+/// `<block_hash>,<unix_timestamp>[,<gas_used>,<tx_count>,<batch_size>,<uses_blobs>]`
+///
+/// The optional metadata columns are only needed by the ballot rules, `-` when unknown.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Ballot config in json format, like the one of `/admin/set_ballot`
+    #[arg(
+        long,
+        require_equals = true,
+        help = "e.g. {\"Sp1\":[0.1,100],\"Risc0\":[0.2,100]}"
+    )]
+    ballot: String,
+
+    /// Ballot rules on the block metadata in json format
+    #[arg(
+        long,
+        require_equals = true,
+        default_value = "[]",
+        help = "e.g. [{\"proof_type\":\"Sp1\",\"action\":\"always\",\"when\":{\"min_batch_size\":11}}]"
+    )]
+    ballot_rules: String,
+
+    /// File of the blocks to replay
+    #[arg(long, require_equals = true, conflicts_with = "network")]
+    blocks: Option<PathBuf>,
+
+    /// Network to fetch the blocks to replay from, with --from and --to
+    #[arg(long, require_equals = true, requires_all = ["from", "to"])]
+    network: Option<String>,
+
+    /// First block to replay
+    #[arg(long, require_equals = true)]
+    from: Option<u64>,
+
+    /// Last block to replay, inclusive
+    #[arg(long, require_equals = true)]
+    to: Option<u64>,
+
+    /// Path to a chain spec file that includes supported chain list
+    #[arg(long, require_equals = true)]
+    chain_spec_path: Option<PathBuf>,
+
+    /// Write the report to this file instead of stdout
+    #[arg(long, require_equals = true)]
+    output: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenv::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(std::env::var("RUST_LOG").unwrap_or("info".to_owned()))
+        .with_writer(std::io::stderr)
+        .init();
+    let args = Args::parse();
+
+    let config: BTreeMap<ProofType, (f64, u64)> =
+        serde_json::from_str(&args.ballot).context("invalid ballot config")?;
+    let rules: Vec<BallotRule> =
+        serde_json::from_str(&args.ballot_rules).context("invalid ballot rules")?;
+    let blocks = match (&args.blocks, &args.network) {
+        (Some(path), _) => read_blocks(path)?,
+        (None, Some(network)) => fetch_blocks(&args, network).await?,
+        (None, None) => bail!("either --blocks or --network is required"),
+    };
+
+    let report = simulate(config, rules, blocks).map_err(|e| anyhow!(e))?;
+    for day in &report.days {
+        info!(
+            "{}: {} blocks, requested: {:?}, rate limited: {:?}",
+            day.date, day.blocks, day.requested, day.rate_limited
+        );
+    }
+    info!(
+        "Total: {} blocks, requested: {:?}, rate limited: {:?}",
+        report.blocks, report.requested, report.rate_limited
+    );
+
+    let report = serde_json::to_string_pretty(&report)?;
+    match &args.output {
+        Some(path) => std::fs::write(path, report)?,
+        None => println!("{report}"),
+    }
+    Ok(())
+}
+
+fn read_blocks(path: &PathBuf) -> anyhow::Result<Vec<BlockSample>> {
+    let content = std::fs::read_to_string(path)?;
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| parse_block(line).with_context(|| format!("line {}: {line}", i + 1)))
+        .collect()
+}
+
+fn parse_block(line: &str) -> anyhow::Result<BlockSample> {
+    let columns = line
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|column| !column.is_empty())
+        .collect::<Vec<_>>();
+    let [block_hash, timestamp, metadata @ ..] = columns.as_slice() else {
+        bail!("expected <block_hash>,<unix_timestamp>");
+    };
+    if metadata.len() > 4 {
+        bail!("expected at most 4 metadata columns");
+    }
+    let column = |i: usize| metadata.get(i).copied().filter(|column| *column != "-");
+    Ok(BlockSample {
+        block_hash: block_hash.parse::<BlockHash>()?,
+        timestamp: DateTime::from_timestamp(timestamp.parse()?, 0)
+            .ok_or_else(|| anyhow!("invalid timestamp {timestamp}"))?,
+        metadata: BlockMetadata {
+            gas_used: column(0).map(str::parse).transpose()?,
+            tx_count: column(1).map(str::parse).transpose()?,
+            batch_size: column(2).map(str::parse).transpose()?,
+            uses_blobs: column(3).map(str::parse).transpose()?,
+        },
+    })
+}
+
+async fn fetch_blocks(args: &Args, network: &str) -> anyhow::Result<Vec<BlockSample>> {
+    let (Some(from), Some(to)) = (args.from, args.to) else {
+        bail!("--from and --to are required with --network");
+    };
+    let chain_specs = match &args.chain_spec_path {
+        Some(path) => SupportedChainSpecs::merge_from_file(path.clone())?,
+        None => SupportedChainSpecs::default(),
+    };
+    let chain_spec = chain_specs
+        .get_chain_spec(network)
+        .ok_or_else(|| anyhow!("Unsupported network: {network}"))?;
+    let provider =
+        RpcBlockDataProvider::new_with_fallbacks(&chain_spec.rpc_endpoints(), from).await?;
+
+    let mut samples = Vec::new();
+    let mut start = from;
+    while start <= to {
+        let end = to.min(start + FETCH_BATCH_SIZE - 1);
+        let numbers = (start..=end).map(|n| (n, false)).collect::<Vec<_>>();
+        // The blocks are returned in the order of their numbers
+        for (number, block) in (start..=end).zip(provider.get_blocks(&numbers).await?) {
+            // Whether the block was proposed with blobs is not known from the block
+            let metadata = BlockMetadata {
+                gas_used: Some(block.header.gas_used as u64),
+                tx_count: Some(block.transactions.len() as u64),
+                batch_size: Some(1),
+                uses_blobs: None,
+            };
+            samples.push(BlockSample {
+                block_hash: block
+                    .header
+                    .hash
+                    .ok_or_else(|| anyhow!("missing hash of block {number}"))?,
+                timestamp: DateTime::from_timestamp(block.header.timestamp as i64, 0)
+                    .ok_or_else(|| anyhow!("invalid timestamp of block {number}"))?,
+                metadata,
+            });
+        }
+        info!("Fetched blocks {start}..={end} of {network}");
+        start = end + 1;
+    }
+    Ok(samples)
+}
@@ -1,16 +1,20 @@
 #![allow(incomplete_features)]
 use raiko_host::{
-    interfaces::HostResult, parse_ballot, parse_chain_specs, parse_opts, server::serve,
+    interfaces::HostResult, parse_artifact_store, parse_ballot, parse_chain_specs,
+    parse_deadline_policy, parse_opts, parse_retry_policy, parse_scheduler_config, server::serve,
 };
-use raiko_reqpool::RedisPoolConfig;
-use std::path::PathBuf;
-use tracing::{debug, info};
+use raiko_reqpool::{ArtifactStore, RedisPoolConfig};
+use std::{path::PathBuf, time::Duration};
+use tracing::{debug, error, info};
 use tracing_appender::{
     non_blocking::WorkerGuard,
     rolling::{Builder, Rotation},
 };
 use tracing_subscriber::FmtSubscriber;
 
+/// How often the artifacts older than their retention are pruned.
+const ARTIFACT_PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
 #[tokio::main]
 async fn main() -> HostResult<()> {
     dotenv::dotenv().ok();
@@ -21,19 +25,28 @@ async fn main() -> HostResult<()> {
     let chain_specs = parse_chain_specs(&opts);
     let ballot = parse_ballot(&opts);
     let default_request_config = opts.proof_request_opt.clone();
-    let max_proving_concurrency = opts.concurrency_limit;
-    let pool = raiko_reqpool::Pool::open(RedisPoolConfig {
+    let scheduler_config = parse_scheduler_config(&opts);
+    let mut pool = raiko_reqpool::Pool::open(RedisPoolConfig {
         redis_url: opts.redis_url.clone(),
         redis_ttl: opts.redis_ttl,
         enable_redis_pool: opts.enable_redis_pool,
+        sqlite_path: opts.sqlite_path.clone(),
     })
     .map_err(|e| anyhow::anyhow!(e))?;
+    if let Some(artifact_store) = parse_artifact_store(&opts) {
+        if artifact_store.retention().is_some() {
+            spawn_artifact_pruning(artifact_store.clone());
+        }
+        pool = pool.with_artifact_store(artifact_store);
+    }
     let actor = raiko_reqactor::start_actor(
         pool,
         ballot,
         chain_specs.clone(),
         default_request_config.clone(),
-        max_proving_concurrency,
+        scheduler_config,
+        parse_retry_policy(&opts),
+        parse_deadline_policy(&opts),
     )
     .await;
 
@@ -49,6 +62,21 @@ async fn main() -> HostResult<()> {
     Ok(())
 }
 
+fn spawn_artifact_pruning(artifact_store: ArtifactStore) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ARTIFACT_PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let artifact_store = artifact_store.clone();
+            match tokio::task::spawn_blocking(move || artifact_store.prune()).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => error!("Failed to prune artifacts: {e}"),
+                Err(e) => error!("Failed to prune artifacts: {e}"),
+            }
+        }
+    });
+}
+
 fn subscribe_log(
     log_path: &Option<PathBuf>,
     log_level: &String,
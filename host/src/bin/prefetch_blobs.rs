@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use raiko_core::preflight::blob_archive::{prefetch_blobs, BlobArchive, BLOB_ARCHIVE_DIR_ENV};
+use raiko_lib::consts::SupportedChainSpecs;
+use tracing::info;
+
+/// Archive the blobs of a range of L1 blocks, so that the batches proposed in them can still
+/// be proven once the beacon nodes pruned the sidecars.
+#[derive(Debug, Parser)]
+struct Args {
+    /// L1 network to fetch the blobs of, e.g. ethereum or holesky
+    #[arg(long, require_equals = true)]
+    l1_network: String,
+
+    /// First L1 block of the range
+    #[arg(long, require_equals = true)]
+    from: u64,
+
+    /// Last L1 block of the range, inclusive
+    #[arg(long, require_equals = true)]
+    to: u64,
+
+    /// Directory of the blob archive
+    #[arg(long, require_equals = true, env = BLOB_ARCHIVE_DIR_ENV)]
+    archive_dir: PathBuf,
+
+    /// Path to a chain spec file that includes supported chain list
+    #[arg(long, require_equals = true)]
+    chain_spec_path: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenv::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(std::env::var("RUST_LOG").unwrap_or("info".to_owned()))
+        .init();
+    let args = Args::parse();
+
+    let chain_specs = match &args.chain_spec_path {
+        Some(path) => SupportedChainSpecs::merge_from_file(path.clone())?,
+        None => SupportedChainSpecs::default(),
+    };
+    let l1_chain_spec = chain_specs
+        .get_chain_spec(&args.l1_network)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported L1 network: {}", args.l1_network))?;
+
+    let archive = BlobArchive::new(&args.archive_dir);
+    let archived = prefetch_blobs(&archive, &l1_chain_spec, args.from..=args.to).await?;
+    info!(
+        "Archived {archived} blobs of L1 blocks {}..={} in {:?}",
+        args.from,
+        args.to,
+        archive.dir()
+    );
+    Ok(())
+}
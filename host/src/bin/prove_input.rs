@@ -0,0 +1,214 @@
+use std::{collections::HashMap, path::PathBuf, str::FromStr};
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use raiko_core::{
+    build_header, derive_batch_blocks, header_diff,
+    interfaces::{ProofRequest, RaikoError},
+    HeaderFieldMismatch, Raiko,
+};
+use raiko_lib::{
+    consts::ChainSpec,
+    input::{GuestBatchInput, GuestBatchOutput, GuestInput, GuestOutput},
+    proof_type::ProofType,
+    protocol_instance::ProtocolInstance,
+    prover::Proof,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Execute a guest input dumped by another host, without any RPC access, and optionally
+/// prove it.
+///
+/// Inputs are read as JSON when the file has a `.json` extension (e.g. the output of the
+/// native prover `json_guest_input` option), and as bincode otherwise (e.g. the host input
+/// cache).
+#[derive(Debug, Parser)]
+struct Args {
+    /// Path to the guest input
+    input: PathBuf,
+
+    /// The input is a batch input
+    #[arg(long)]
+    batch: bool,
+
+    /// Proof type used for the protocol instance hash and the proof
+    #[arg(long, require_equals = true, default_value = "native")]
+    proof_type: String,
+
+    /// Also run the prover of `proof_type` once the input executed successfully
+    #[arg(long)]
+    prove: bool,
+
+    /// Path to a config file with the prover specific options, like the host config
+    #[arg(long, require_equals = true, default_value = "host/config/config.json")]
+    config_path: PathBuf,
+
+    /// Write the proof to this json file instead of printing it
+    #[arg(long, require_equals = true)]
+    output: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenv::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(std::env::var("RUST_LOG").unwrap_or("info".to_owned()))
+        .init();
+    let args = Args::parse();
+    let proof_type = ProofType::from_str(&args.proof_type)?;
+    let prover_args = read_prover_args(&args.config_path)?;
+
+    if args.batch {
+        let input: GuestBatchInput = read_input(&args.input)?;
+        let request = ProofRequest {
+            block_number: input
+                .inputs
+                .first()
+                .map_or(0, |block_input| block_input.block.number),
+            batch_id: input.taiko.batch_id,
+            l1_inclusion_block_number: 0,
+            l2_block_numbers: input
+                .inputs
+                .iter()
+                .map(|block_input| block_input.block.number)
+                .collect(),
+            network: input.taiko.chain_spec.name.clone(),
+            l1_network: String::new(),
+            graffiti: input.taiko.prover_data.graffiti,
+            prover: input.taiko.prover_data.prover,
+            proof_type,
+            blob_proof_type: input.taiko.blob_proof_type.clone(),
+            prover_args,
+        };
+
+        let derived = derive_batch_blocks(&input)?;
+        let mut matching = true;
+        for derived in &derived {
+            matching &= print_header_diff(
+                derived.block.number,
+                &header_diff(&derived.block.header, &derived.header),
+            );
+        }
+        if !matching {
+            bail!(
+                "batch {} does not match the expected blocks",
+                request.batch_id
+            );
+        }
+        let blocks = derived
+            .into_iter()
+            .map(|derived| {
+                derived.executed?;
+                Ok(derived.block)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let hash = ProtocolInstance::new_batch(&input, blocks.clone(), proof_type)?.instance_hash();
+        println!("protocol instance hash: {hash}");
+
+        if args.prove {
+            let raiko = Raiko::new(
+                ChainSpec::default(),
+                input.taiko.chain_spec.clone(),
+                request,
+            );
+            let proof = raiko
+                .batch_prove(input, &GuestBatchOutput { blocks, hash }, None)
+                .await?;
+            write_proof(&proof, args.output.as_ref())?;
+        }
+    } else {
+        let input: GuestInput = read_input(&args.input)?;
+        let request = ProofRequest {
+            block_number: input.block.number,
+            batch_id: 0,
+            l1_inclusion_block_number: 0,
+            l2_block_numbers: Vec::new(),
+            network: input.chain_spec.name.clone(),
+            l1_network: String::new(),
+            graffiti: input.taiko.prover_data.graffiti,
+            prover: input.taiko.prover_data.prover,
+            proof_type,
+            blob_proof_type: input.taiko.blob_proof_type.clone(),
+            prover_args,
+        };
+
+        let header = match build_header(&input) {
+            Ok(header) => header,
+            Err(RaikoError::HeaderMismatch(mismatch)) => {
+                print_header_diff(mismatch.block_number, &mismatch.fields);
+                bail!(
+                    "block {} does not match the expected block",
+                    mismatch.block_number
+                );
+            }
+            Err(e) => return Err(e.into()),
+        };
+        print_header_diff(header.number, &[]);
+        let hash = ProtocolInstance::new(&input, &header, proof_type)?.instance_hash();
+        println!("protocol instance hash: {hash}");
+
+        if args.prove {
+            let raiko = Raiko::new(ChainSpec::default(), input.chain_spec.clone(), request);
+            let proof = raiko
+                .prove(input, &GuestOutput { header, hash }, None)
+                .await?;
+            write_proof(&proof, args.output.as_ref())?;
+        }
+    }
+    Ok(())
+}
+
+fn read_input<T: DeserializeOwned>(path: &PathBuf) -> anyhow::Result<T> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+    let reader = std::io::BufReader::new(file);
+    if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_reader(reader).context("Failed to parse json input")
+    } else {
+        bincode::deserialize_from(reader).context("Failed to parse bincode input")
+    }
+}
+
+fn write_proof(proof: &Proof, output: Option<&PathBuf>) -> anyhow::Result<()> {
+    match output {
+        Some(path) => {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {path:?}"))?;
+            serde_json::to_writer(file, proof).context("Failed to write proof")
+        }
+        None => {
+            println!("{}", serde_json::to_string_pretty(proof)?);
+            Ok(())
+        }
+    }
+}
+
+/// The prover options of the config file, missing files are treated as empty configs.
+fn read_prover_args(path: &PathBuf) -> anyhow::Result<HashMap<String, Value>> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {path:?}"))?;
+    let config: Value = serde_json::from_reader(std::io::BufReader::new(file))
+        .context("Failed to read config file")?;
+    Ok(config
+        .as_object()
+        .map(|config| config.clone().into_iter().collect())
+        .unwrap_or_default())
+}
+
+/// Print the fields that differ from the expected header, returns whether the headers match.
+fn print_header_diff(block_number: u64, diff: &[HeaderFieldMismatch]) -> bool {
+    if diff.is_empty() {
+        println!("block {block_number}: header matches");
+        return true;
+    }
+    println!("block {block_number}: header mismatch");
+    for mismatch in diff {
+        println!(
+            "  {}: expected {}, found {}",
+            mismatch.field, mismatch.expected, mismatch.actual
+        );
+    }
+    false
+}
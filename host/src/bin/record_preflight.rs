@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail};
+use clap::Parser;
+use raiko_core::{
+    interfaces::ProofRequest,
+    provider::{recorder::RecordingBlockDataProvider, rpc::RpcBlockDataProvider},
+    Raiko,
+};
+use raiko_lib::consts::SupportedChainSpecs;
+use tracing::info;
+
+/// Run the preflight of a block while recording every response of the RPC node to a fixture,
+/// or replay such a fixture without any access to the node, e.g. to reproduce a failing block
+/// offline.
+///
+/// The L1 lookups of Taiko blocks are not part of the fixture, only the L2 block data is.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Network of the block, e.g. ethereum or taiko_mainnet
+    #[arg(long, require_equals = true, default_value = "ethereum")]
+    network: String,
+
+    /// L1 network of the block, for Taiko networks
+    #[arg(long, require_equals = true, default_value = "ethereum")]
+    l1_network: String,
+
+    /// Block to run the preflight of
+    #[arg(long, require_equals = true)]
+    block: u64,
+
+    /// L1 block the block was proposed in, for Taiko networks, 0 to look it up
+    #[arg(long, require_equals = true, default_value_t = 0)]
+    l1_inclusion_block: u64,
+
+    /// Record the responses of the RPC node to this fixture, appending to it if it exists
+    #[arg(long, require_equals = true, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Serve the preflight from this fixture only
+    #[arg(long, require_equals = true)]
+    replay: Option<PathBuf>,
+
+    /// Write the guest input to this json file, which `prove-input` can execute
+    #[arg(long, require_equals = true)]
+    output: Option<PathBuf>,
+
+    /// Path to a chain spec file that includes supported chain list
+    #[arg(long, require_equals = true)]
+    chain_spec_path: Option<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenv::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(std::env::var("RUST_LOG").unwrap_or("info".to_owned()))
+        .init();
+    let args = Args::parse();
+
+    let chain_specs = match &args.chain_spec_path {
+        Some(path) => SupportedChainSpecs::merge_from_file(path.clone())?,
+        None => SupportedChainSpecs::default(),
+    };
+    let taiko_chain_spec = chain_specs
+        .get_chain_spec(&args.network)
+        .ok_or_else(|| anyhow!("Unsupported network: {}", args.network))?;
+    let l1_chain_spec = chain_specs
+        .get_chain_spec(&args.l1_network)
+        .ok_or_else(|| anyhow!("Unsupported L1 network: {}", args.l1_network))?;
+
+    let provider = match (&args.record, &args.replay) {
+        (Some(path), _) => {
+            let rpc = RpcBlockDataProvider::new_with_fallbacks(
+                &taiko_chain_spec.rpc_endpoints(),
+                args.block.saturating_sub(1),
+            )
+            .await?;
+            RecordingBlockDataProvider::record(rpc, path)?
+        }
+        (None, Some(path)) => RecordingBlockDataProvider::replay(path)?,
+        (None, None) => bail!("either --record or --replay is required"),
+    };
+
+    let request = ProofRequest {
+        block_number: args.block,
+        batch_id: 0,
+        l1_inclusion_block_number: args.l1_inclusion_block,
+        l2_block_numbers: Vec::new(),
+        network: args.network.clone(),
+        l1_network: args.l1_network.clone(),
+        graffiti: Default::default(),
+        prover: Default::default(),
+        proof_type: Default::default(),
+        blob_proof_type: Default::default(),
+        prover_args: Default::default(),
+    };
+    let raiko = Raiko::new(l1_chain_spec, taiko_chain_spec, request);
+    // The provider writes the recorded responses to the fixture when the preflight drops it
+    let input = raiko.generate_input(provider).await?;
+    info!("Preflight of block {} succeeded", args.block);
+
+    if let Some(path) = &args.output {
+        std::fs::write(path, serde_json::to_string(&input)?)?;
+        info!("Wrote the guest input to {path:?}");
+    }
+    Ok(())
+}
@@ -77,7 +77,6 @@ mod test {
     use crate::cache;
 
     use alloy_primitives::{Address, B256};
-    use alloy_provider::Provider;
 
     use raiko_core::{interfaces::ProofRequest, provider::rpc::RpcBlockDataProvider, Raiko};
     use raiko_lib::input::BlobProofType;
@@ -137,7 +136,7 @@ mod test {
     async fn get_latest_block_num(chain_spec: &ChainSpec) -> u64 {
         let provider = RpcBlockDataProvider::new(&chain_spec.rpc, 0).await.unwrap();
 
-        provider.provider.get_block_number().await.unwrap()
+        provider.get_block_number().await.unwrap()
     }
 
     #[ignore = "holeksy down"]
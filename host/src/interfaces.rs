@@ -90,7 +90,10 @@ impl IntoResponse for HostError {
             HostError::Anyhow(e) => ("anyhow_error", e.to_string()),
             HostError::HandleDropped => ("handle_dropped", "".to_owned()),
             HostError::CapacityFull => ("capacity_full", "".to_owned()),
-            HostError::SystemPaused => ("system_paused", "".to_owned()),
+            HostError::SystemPaused => (
+                "system_paused",
+                "System is paused, retry once it is resumed".to_owned(),
+            ),
         };
         let status = Status::Error {
             error: error.to_owned(),
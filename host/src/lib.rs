@@ -1,13 +1,15 @@
 use std::collections::BTreeMap;
-use std::{alloc, path::PathBuf};
+use std::{alloc, path::PathBuf, str::FromStr};
 
 use anyhow::Context;
 use cap::Cap;
 use clap::Parser;
-use raiko_ballot::Ballot;
+use raiko_ballot::{Ballot, BallotRule};
 use raiko_core::{interfaces::ProofRequestOpt, merge};
 use raiko_lib::consts::SupportedChainSpecs;
 use raiko_lib::proof_type::ProofType;
+use raiko_reqactor::{DeadlinePolicy, RetryPolicy, SchedulerConfig, SchedulingClass};
+use raiko_reqpool::{ArtifactStore, ArtifactStoreConfig};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -84,6 +86,10 @@ pub struct Opts {
     #[arg(long, default_value = "false")]
     pub enable_redis_pool: bool,
 
+    #[arg(long, require_equals = true)]
+    /// Keep the requests in an embedded SQLite database at this path, instead of redis or memory
+    pub sqlite_path: Option<PathBuf>,
+
     /// Ballot config in json format. If not provided, '{}' will be used.
     #[arg(
         long,
@@ -92,6 +98,55 @@ pub struct Opts {
         help = "e.g. {\"Sp1\":0.1,\"Risc0\":0.2}"
     )]
     pub ballot: String,
+
+    /// Ballot rules on the block metadata in json format, applied in order before the ballot
+    /// probabilities. If not provided, '[]' will be used.
+    #[arg(
+        long,
+        require_equals = true,
+        default_value = "[]",
+        help = "e.g. [{\"proof_type\":\"Sp1\",\"action\":\"always\",\"when\":{\"min_batch_size\":11}}]"
+    )]
+    pub ballot_rules: String,
+
+    /// Concurrency limits per scheduling class in json format, `null` for unlimited. Classes
+    /// without a limit share `concurrency_limit`. If not provided, '{}' will be used.
+    #[arg(
+        long,
+        require_equals = true,
+        default_value = "{}",
+        help = "e.g. {\"guest_input\":8,\"sp1\":1,\"sp1_network\":null}"
+    )]
+    pub scheduling_limits: String,
+
+    /// Retry policy of the failed requests in json format. If not provided, '{}' will be used,
+    /// retrying RPC, blob and remote prover timeout failures up to 3 attempts.
+    #[arg(
+        long,
+        require_equals = true,
+        default_value = "{}",
+        help = "e.g. {\"max_attempts\":3,\"backoff_secs\":30,\"retryable\":[\"rpc\"]}"
+    )]
+    pub retry_policy: String,
+
+    /// Time allowed to each proving attempt per proof type in json format, in seconds, for the
+    /// requests without a deadline of their own. If not provided, '{}' will be used.
+    #[arg(
+        long,
+        require_equals = true,
+        default_value = "{}",
+        help = "e.g. {\"Sp1\":3600,\"Risc0\":3600}"
+    )]
+    pub proof_timeouts: String,
+
+    /// Artifact store of the large proofs in json format. If not provided, the proofs are kept
+    /// in the pool.
+    #[arg(
+        long,
+        require_equals = true,
+        help = "e.g. {\"backend\":\"fs\",\"root\":\"/tmp/artifacts\",\"retention_secs\":604800}"
+    )]
+    pub artifact_store: Option<String>,
 }
 
 impl Opts {
@@ -157,11 +212,51 @@ pub fn parse_chain_specs(opts: &Opts) -> SupportedChainSpecs {
 pub fn parse_ballot(opts: &Opts) -> Ballot {
     let probs: BTreeMap<ProofType, (f64, u64)> =
         serde_json::from_str(&opts.ballot).unwrap_or_default();
-    let ballot = Ballot::new(probs).expect("Failed to create ballot");
+    let rules: Vec<BallotRule> = match opts.ballot_rules.trim() {
+        "" => Vec::new(),
+        rules => serde_json::from_str(rules).expect("Failed to parse ballot rules"),
+    };
+    let ballot = Ballot::new(probs)
+        .and_then(|ballot| ballot.with_rules(rules))
+        .expect("Failed to create ballot");
     ballot.validate().expect("Failed to validate ballot");
     ballot
 }
 
+pub fn parse_scheduler_config(opts: &Opts) -> SchedulerConfig {
+    let limits: BTreeMap<String, Option<usize>> = match opts.scheduling_limits.trim() {
+        "" => BTreeMap::new(),
+        limits => serde_json::from_str(limits).expect("Failed to parse scheduling limits"),
+    };
+    limits.into_iter().fold(
+        SchedulerConfig::new(opts.concurrency_limit),
+        |config, (class, limit)| {
+            let class = SchedulingClass::from_str(&class).expect("Invalid scheduling class");
+            config.with_class_limit(class, limit)
+        },
+    )
+}
+
+pub fn parse_retry_policy(opts: &Opts) -> RetryPolicy {
+    match opts.retry_policy.trim() {
+        "" => RetryPolicy::default(),
+        policy => serde_json::from_str(policy).expect("Failed to parse retry policy"),
+    }
+}
+
+pub fn parse_deadline_policy(opts: &Opts) -> DeadlinePolicy {
+    match opts.proof_timeouts.trim() {
+        "" => DeadlinePolicy::default(),
+        timeouts => serde_json::from_str(timeouts).expect("Failed to parse proof timeouts"),
+    }
+}
+
+pub fn parse_artifact_store(opts: &Opts) -> Option<ArtifactStore> {
+    let config: ArtifactStoreConfig = serde_json::from_str(opts.artifact_store.as_ref()?)
+        .expect("Failed to parse artifact store config");
+    Some(ArtifactStore::open(config).expect("Failed to open artifact store"))
+}
+
 #[global_allocator]
 static ALLOCATOR: Cap<alloc::System> = Cap::new(alloc::System, usize::MAX);
 
@@ -61,6 +61,31 @@ lazy_static! {
         "number of requests currently being processed"
     )
     .unwrap();
+    pub static ref STATE_CACHE_HITS: IntGauge = register_int_gauge!(
+        "state_cache_hits",
+        "number of preflights started from the cached state of the parent block"
+    )
+    .unwrap();
+    pub static ref STATE_CACHE_MISSES: IntGauge = register_int_gauge!(
+        "state_cache_misses",
+        "number of preflights without a cached state of the parent block"
+    )
+    .unwrap();
+    pub static ref STATE_CACHE_EVICTIONS: IntGauge = register_int_gauge!(
+        "state_cache_evictions",
+        "number of cached states evicted to stay within the memory budget"
+    )
+    .unwrap();
+    pub static ref STATE_CACHE_INVALIDATIONS: IntGauge = register_int_gauge!(
+        "state_cache_invalidations",
+        "number of cached states dropped after a reorg"
+    )
+    .unwrap();
+    pub static ref STATE_CACHE_BYTES: IntGauge = register_int_gauge!(
+        "state_cache_bytes",
+        "estimated memory used by the cached states"
+    )
+    .unwrap();
 }
 
 /// Increase the count of requests currently being processed.
@@ -124,6 +149,21 @@ pub fn inc_guest_error(guest: &ProofType, block_id: u64) {
     GUEST_PROOF_ERROR_COUNT.with(&labels).inc();
 }
 
+/// Update the state cache metrics from the counters of the preflight state cache.
+#[cfg(feature = "statedb_lru")]
+pub fn observe_state_cache() {
+    let stats = raiko_core::preflight::state_cache_stats();
+    STATE_CACHE_HITS.set(stats.hits as i64);
+    STATE_CACHE_MISSES.set(stats.misses as i64);
+    STATE_CACHE_EVICTIONS.set(stats.evictions as i64);
+    STATE_CACHE_INVALIDATIONS.set(stats.invalidations as i64);
+    STATE_CACHE_BYTES.set(stats.bytes as i64);
+}
+
+/// Without the state cache, its metrics stay at zero.
+#[cfg(not(feature = "statedb_lru"))]
+pub fn observe_state_cache() {}
+
 /// Convert a duration to a float with 3 decimal places (seconds,milliseconds).
 fn duration_to_f64(d: Duration) -> f64 {
     (d.as_secs_f64() * 1_000.0).round() / 1_000.0
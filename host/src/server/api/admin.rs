@@ -3,18 +3,24 @@ use axum::response::Response;
 use axum::routing::get;
 use axum::Json;
 use axum::{extract::State, routing::post, Router};
-use raiko_ballot::Ballot;
+use raiko_ballot::{Ballot, BallotRule};
 use raiko_lib::proof_type::ProofType;
+use raiko_reqpool::BallotDraw;
 use std::collections::BTreeMap;
 
 use crate::interfaces::HostResult;
-use raiko_reqactor::Actor;
+use raiko_reqactor::{Actor, DrainStatus};
 
 pub fn create_router() -> Router<Actor> {
     Router::new()
         .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/drain", get(drain))
         .route("/set_ballot", post(set_ballot))
         .route("/get_ballot", get(get_ballot))
+        .route("/set_ballot_rules", post(set_ballot_rules))
+        .route("/get_ballot_rules", get(get_ballot_rules))
+        .route("/ballot/history", get(ballot_history))
 }
 
 async fn pause(State(actor): State<Actor>) -> HostResult<&'static str> {
@@ -22,11 +28,26 @@ async fn pause(State(actor): State<Actor>) -> HostResult<&'static str> {
     Ok("System paused successfully")
 }
 
+async fn resume(State(actor): State<Actor>) -> HostResult<&'static str> {
+    actor.resume().await.map_err(|e| anyhow::anyhow!(e))?;
+    Ok("System resumed successfully")
+}
+
+/// Whether the in-flight works of a paused system are done.
+async fn drain(State(actor): State<Actor>) -> HostResult<Json<DrainStatus>> {
+    let drain_status = actor.drain_status().map_err(|e| anyhow::anyhow!(e))?;
+    Ok(Json(drain_status))
+}
+
 async fn set_ballot(
     State(actor): State<Actor>,
     Json(probs): Json<BTreeMap<ProofType, (f64, u64)>>,
 ) -> HostResult<&'static str> {
-    let ballot = Ballot::new(probs).map_err(|e| anyhow::anyhow!(e))?;
+    // The rules are kept
+    let rules = actor.get_ballot().rules().to_vec();
+    let ballot = Ballot::new(probs)
+        .and_then(|ballot| ballot.with_rules(rules))
+        .map_err(|e| anyhow::anyhow!(e))?;
     actor.set_ballot(ballot);
     Ok("Ballot set successfully")
 }
@@ -35,3 +56,27 @@ async fn get_ballot(State(actor): State<Actor>) -> Response {
     let ballot = actor.get_ballot().probabilities().to_owned();
     Json(ballot).into_response()
 }
+
+/// Replace the rules on the block metadata, keeping the probabilities and draw state.
+async fn set_ballot_rules(
+    State(actor): State<Actor>,
+    Json(rules): Json<Vec<BallotRule>>,
+) -> HostResult<&'static str> {
+    let ballot = actor
+        .get_ballot()
+        .with_rules(rules)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    actor.set_ballot(ballot);
+    Ok("Ballot rules set successfully")
+}
+
+async fn get_ballot_rules(State(actor): State<Actor>) -> Response {
+    let rules = actor.get_ballot().rules().to_vec();
+    Json(rules).into_response()
+}
+
+/// The proof types drawn for the block hashes, the latest first.
+async fn ballot_history(State(actor): State<Actor>) -> HostResult<Json<Vec<BallotDraw>>> {
+    let history = actor.ballot_history().map_err(|e| anyhow::anyhow!(e))?;
+    Ok(Json(history))
+}
@@ -2,7 +2,7 @@ use axum::{body::Body, http::header, response::Response, routing::get, Router};
 use prometheus::{Encoder, TextEncoder};
 use utoipa::OpenApi;
 
-use crate::interfaces::HostResult;
+use crate::{interfaces::HostResult, metrics::observe_state_cache};
 use raiko_reqactor::Actor;
 
 #[utoipa::path(
@@ -25,6 +25,11 @@ use raiko_reqactor::Actor;
 /// - guest_proof_time_histogram - time taken for proof generation by this guest
 /// - prepare_input_time_histogram - time taken for prepare input
 /// - total_time_histogram - time taken for the whole proof request
+/// - state_cache_hits - preflights started from the cached state of the parent block
+/// - state_cache_misses - preflights without a cached state of the parent block
+/// - state_cache_evictions - cached states evicted to stay within the memory budget
+/// - state_cache_invalidations - cached states dropped after a reorg
+/// - state_cache_bytes - estimated memory used by the cached states
 /// - process_cpu_seconds_total - total user and system CPU time spent in seconds
 /// - process_open_fds - number of open file descriptors
 /// - process_max_fds - maximum number of open file descriptors
@@ -33,6 +38,7 @@ use raiko_reqactor::Actor;
 /// - process_start_time_seconds - start time of the process since unix epoch in seconds
 /// - process_threads - number of threads
 async fn metrics_handler() -> HostResult<Response> {
+    observe_state_cache();
     let encoder = TextEncoder::new();
     let mut buffer = vec![];
     let mf = prometheus::gather();
@@ -31,6 +31,8 @@ pub mod proof;
         schemas(
             raiko_core::interfaces::ProofRequestOpt,
             raiko_core::interfaces::ProverSpecificOpts,
+            raiko_core::HeaderMismatch,
+            raiko_core::HeaderFieldMismatch,
             crate::interfaces::HostError,
             GuestOutputDoc,
             ProofResponse,
@@ -53,6 +55,13 @@ pub struct Docs;
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 #[serde(untagged)]
 pub enum ProofResponse {
+    /// A failed task whose re-executed block differs from the expected one.
+    HeaderMismatch {
+        /// The status of the submitted task.
+        status: TaskStatus,
+        /// The diverging header fields.
+        header_mismatch: raiko_core::HeaderMismatch,
+    },
     Status {
         /// The status of the submitted task.
         status: TaskStatus,
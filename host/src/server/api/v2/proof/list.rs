@@ -1,18 +1,152 @@
-use axum::{extract::State, routing::get, Json, Router};
-use serde_json::Value;
-use utoipa::OpenApi;
+use std::collections::HashMap;
 
-use crate::interfaces::HostResult;
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::DateTime;
+use raiko_lib::proof_type::ProofType;
+use raiko_reqpool::{RequestFilter, RequestKey, Status, StatusWithContext};
+use raiko_tasks::TaskReport;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, OpenApi};
+
+use crate::{
+    interfaces::{HostError, HostResult},
+    server::api::v2::proof::report::{to_task_descriptor, to_task_status},
+};
 use raiko_reqactor::Actor;
 
-#[utoipa::path(post, path = "/proof/list",
+/// How many tasks are listed when the query sets no limit.
+const DEFAULT_LIST_LIMIT: usize = 100;
+/// The most tasks listed at once.
+const MAX_LIST_LIMIT: usize = 1000;
+
+/// The filters and the page of the listed tasks, the unset filters match any task.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// The status of the tasks, e.g. `registered`, `work_in_progress`, `success`, `failed` or
+    /// `cancelled`
+    pub status: Option<String>,
+    /// The proof type of the tasks, e.g. `sp1`
+    pub proof_type: Option<String>,
+    pub chain_id: Option<u64>,
+    /// The lowest block number or batch id of the tasks
+    pub from: Option<u64>,
+    /// The highest block number or batch id of the tasks, inclusive
+    pub to: Option<u64>,
+    /// Unix timestamp in seconds, the earliest status update of the tasks
+    pub since: Option<i64>,
+    /// Unix timestamp in seconds, the latest status update of the tasks, inclusive
+    pub until: Option<i64>,
+    /// How many of the matching tasks to skip
+    #[serde(default)]
+    pub offset: usize,
+    /// How many tasks to list, 100 by default and at most 1000
+    pub limit: Option<usize>,
+}
+
+impl ListQuery {
+    pub fn filter(&self) -> HostResult<RequestFilter> {
+        let timestamp = |name: &str, secs: Option<i64>| {
+            secs.map(|secs| {
+                DateTime::from_timestamp(secs, 0).ok_or_else(|| {
+                    HostError::InvalidRequestConfig(format!("Invalid {name} timestamp {secs}"))
+                })
+            })
+            .transpose()
+        };
+        if let Some(status) = &self.status {
+            if !Status::NAMES.contains(&status.as_str()) {
+                return Err(HostError::InvalidRequestConfig(format!(
+                    "Unknown status {status}, expected one of {:?}",
+                    Status::NAMES
+                )));
+            }
+        }
+        Ok(RequestFilter {
+            status: self.status.clone(),
+            proof_type: self
+                .proof_type
+                .as_deref()
+                .map(str::parse::<ProofType>)
+                .transpose()
+                .map_err(HostError::InvalidRequestConfig)?,
+            chain_id: self.chain_id,
+            from: self.from,
+            to: self.to,
+            since: timestamp("since", self.since)?,
+            until: timestamp("until", self.until)?,
+        })
+    }
+
+    /// The page of the tasks selected by the offset and limit, the latest status updates
+    /// first.
+    pub fn paginate<K: Ord, T>(
+        &self,
+        statuses: impl IntoIterator<Item = (K, StatusWithContext)>,
+        to_task: impl Fn(K, StatusWithContext) -> T,
+    ) -> TaskPage<T> {
+        let mut statuses = statuses.into_iter().collect::<Vec<_>>();
+        // Ties are broken by the keys, so that pages do not overlap
+        statuses.sort_by(|(a_key, a), (b_key, b)| {
+            b.timestamp()
+                .cmp(a.timestamp())
+                .then_with(|| a_key.cmp(b_key))
+        });
+        let limit = self.limit.unwrap_or(DEFAULT_LIST_LIMIT).min(MAX_LIST_LIMIT);
+        TaskPage {
+            total: statuses.len(),
+            offset: self.offset,
+            tasks: statuses
+                .into_iter()
+                .skip(self.offset)
+                .take(limit)
+                .map(|(request_key, status)| to_task(request_key, status))
+                .collect(),
+        }
+    }
+}
+
+/// A page of the listed tasks.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaskPage<T> {
+    /// How many tasks match the filters, over all the pages
+    pub total: usize,
+    pub offset: usize,
+    pub tasks: Vec<T>,
+}
+
+pub(crate) fn list_statuses(
+    actor: &Actor,
+    query: &ListQuery,
+) -> HostResult<HashMap<RequestKey, StatusWithContext>> {
+    Ok(actor
+        .pool_list_status_by(&query.filter()?)
+        .map_err(|e| anyhow::anyhow!(e))?)
+}
+
+#[utoipa::path(get, path = "/proof/list",
     tag = "Proving",
+    params(ListQuery),
     responses (
-        (status = 200, description = "Successfully listed all proofs & Ids", body = CancelStatus)
+        (status = 200, description = "Successfully listed a page of the tasks matching the filters")
     )
 )]
-async fn list_handler(State(_actor): State<Actor>) -> HostResult<Json<Value>> {
-    todo!()
+/// List the tasks.
+///
+/// Retrieve a page of `{ total, offset, tasks }`, the tasks being `[descriptor, status]` items
+/// of the latest updated tasks first.
+async fn list_handler(
+    State(actor): State<Actor>,
+    Query(query): Query<ListQuery>,
+) -> HostResult<Json<TaskPage<TaskReport>>> {
+    let statuses = list_statuses(&actor, &query)?;
+    Ok(Json(query.paginate(statuses, |request_key, status| {
+        (to_task_descriptor(request_key), to_task_status(status))
+    })))
 }
 
 #[derive(OpenApi)]
@@ -26,3 +160,66 @@ pub fn create_docs() -> utoipa::openapi::OpenApi {
 pub fn create_router() -> Router<Actor> {
     Router::new().route("/", get(list_handler))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raiko_lib::primitives::B256;
+    use raiko_reqpool::GuestInputRequestKey;
+
+    fn status_at(secs: i64) -> StatusWithContext {
+        StatusWithContext::new(
+            Status::Registered,
+            DateTime::from_timestamp(secs, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_list_query_filter() {
+        let query = ListQuery {
+            proof_type: Some("sp1".to_string()),
+            since: Some(1_700_000_000),
+            ..Default::default()
+        };
+        let filter = query.filter().unwrap();
+        assert_eq!(filter.proof_type, Some(ProofType::Sp1));
+        assert_eq!(filter.since.unwrap().timestamp(), 1_700_000_000);
+
+        let query = ListQuery {
+            proof_type: Some("sp2".to_string()),
+            ..Default::default()
+        };
+        assert!(query.filter().is_err());
+
+        let query = ListQuery {
+            status: Some("work_in_progress".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            query.filter().unwrap().status.as_deref(),
+            Some("work_in_progress")
+        );
+        let query = ListQuery {
+            status: Some("sucess".to_string()),
+            ..Default::default()
+        };
+        assert!(query.filter().is_err());
+    }
+
+    #[test]
+    fn test_list_query_paginate() {
+        let statuses = (0..5u64).map(|i| {
+            let key = RequestKey::GuestInput(GuestInputRequestKey::new(1, i, B256::ZERO));
+            (key, status_at(1_700_000_000 + i as i64))
+        });
+        let query = ListQuery {
+            offset: 1,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = query.paginate(statuses, |request_key, _| request_key.numbers()[0]);
+        assert_eq!(page.total, 5);
+        // The latest updated first
+        assert_eq!(page.tasks, vec![3, 2]);
+    }
+}
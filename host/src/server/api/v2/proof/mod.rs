@@ -9,7 +9,9 @@ use raiko_tasks::TaskStatus;
 use serde_json::Value;
 use utoipa::OpenApi;
 
-use crate::server::utils::{draw_for_zk_any_request, is_zk_any_request};
+use crate::server::utils::{
+    draw_for_zk_any_request, ensure_not_paused, is_zk_any_request, request_deadline,
+};
 use crate::{
     interfaces::HostResult,
     metrics::{inc_current_req, inc_guest_req_count, inc_host_req_count},
@@ -41,11 +43,13 @@ pub mod report;
 /// - risc0 - uses the risc0 prover
 async fn proof_handler(State(actor): State<Actor>, Json(req): Json<Value>) -> HostResult<Status> {
     inc_current_req();
+    ensure_not_paused(&actor)?;
 
     // Override the existing proof request config from the config file and command line
     // options with the request from the client.
     let mut config = actor.default_request_config().clone();
     config.merge(&req)?;
+    let deadline = request_deadline(&req)?;
 
     // For zk_any request, draw zk proof type based on the block hash.
     if is_zk_any_request(&req) {
@@ -117,6 +121,7 @@ async fn proof_handler(State(actor): State<Actor>, Json(req): Json<Value>) -> Ho
                 proof_request.blob_proof_type,
                 prover_args,
             )
+            .with_deadline(deadline)
             .into();
 
             let result = crate::server::prove(&actor, request_key, request_entity).await;
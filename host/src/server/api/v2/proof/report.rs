@@ -1,7 +1,7 @@
 use crate::interfaces::HostResult;
 use axum::{extract::State, routing::get, Json, Router};
 use raiko_reqactor::Actor;
-use raiko_reqpool::{RequestKey, Status, StatusWithContext};
+use raiko_reqpool::{AggregationRequestKey, RequestKey, Status, StatusWithContext};
 use raiko_tasks::{
     AggregationTaskDescriptor, BatchGuestInputTaskDescriptor, BatchProofTaskDescriptor,
     GuestInputTaskDescriptor, ProofTaskDescriptor, TaskDescriptor, TaskReport, TaskStatus,
@@ -21,15 +21,26 @@ use utoipa::OpenApi;
 async fn report_handler(State(actor): State<Actor>) -> HostResult<Json<Value>> {
     let statuses = actor.pool_list_status().map_err(|e| anyhow::anyhow!(e))?;
 
-    // For compatibility with the old API, we need to convert the statuses to the old format.
-    let to_task_status = |status: StatusWithContext| match status.into_status() {
+    let task_report: Vec<TaskReport> = statuses
+        .into_iter()
+        .map(|(request_key, status)| (to_task_descriptor(request_key), to_task_status(status)))
+        .collect();
+    Ok(Json(serde_json::to_value(task_report)?))
+}
+
+// For compatibility with the old API, we need to convert the statuses to the old format.
+pub(crate) fn to_task_status(status: StatusWithContext) -> TaskStatus {
+    match status.into_status() {
         Status::Registered => TaskStatus::Registered,
         Status::WorkInProgress => TaskStatus::WorkInProgress,
         Status::Cancelled => TaskStatus::Cancelled,
         Status::Success { .. } => TaskStatus::Success,
-        Status::Failed { error } => TaskStatus::AnyhowError(error),
-    };
-    let to_task_descriptor = |request_key: RequestKey| match request_key {
+        Status::Failed { error, .. } => TaskStatus::AnyhowError(error),
+    }
+}
+
+pub(crate) fn to_task_descriptor(request_key: RequestKey) -> TaskDescriptor {
+    match request_key {
         RequestKey::GuestInput(key) => TaskDescriptor::GuestInput(GuestInputTaskDescriptor {
             chain_id: *key.chain_id(),
             block_id: *key.block_number(),
@@ -42,10 +53,7 @@ async fn report_handler(State(actor): State<Actor>) -> HostResult<Json<Value>> {
             proof_system: *key.proof_type(),
             prover: key.prover_address().clone(),
         }),
-        RequestKey::Aggregation(key) => TaskDescriptor::Aggregation(AggregationTaskDescriptor {
-            aggregation_ids: key.block_numbers().clone(),
-            proof_type: Some(key.proof_type().to_string()),
-        }),
+        RequestKey::Aggregation(key) => TaskDescriptor::Aggregation(to_aggregation_descriptor(key)),
         RequestKey::BatchProof(key) => TaskDescriptor::BatchProof(BatchProofTaskDescriptor {
             chain_id: *key.guest_input_key().chain_id(),
             batch_id: *key.guest_input_key().batch_id(),
@@ -60,13 +68,14 @@ async fn report_handler(State(actor): State<Actor>) -> HostResult<Json<Value>> {
                 l1_height: *key.l1_inclusion_height(),
             })
         }
-    };
+    }
+}
 
-    let task_report: Vec<TaskReport> = statuses
-        .into_iter()
-        .map(|(request_key, status)| (to_task_descriptor(request_key), to_task_status(status)))
-        .collect();
-    Ok(Json(serde_json::to_value(task_report)?))
+pub(crate) fn to_aggregation_descriptor(key: AggregationRequestKey) -> AggregationTaskDescriptor {
+    AggregationTaskDescriptor {
+        aggregation_ids: key.block_numbers().clone(),
+        proof_type: Some(key.proof_type().to_string()),
+    }
 }
 
 #[derive(OpenApi)]
@@ -0,0 +1,54 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use raiko_reqactor::Actor;
+use raiko_reqpool::ArtifactId;
+use utoipa::OpenApi;
+
+use crate::interfaces::HostResult;
+
+#[utoipa::path(get, path = "/artifacts/{id}",
+    tag = "Proving",
+    params(
+        ("id" = String, Path, description = "The hex-encoded sha256 of the artifact")
+    ),
+    responses (
+        (status = 200, description = "Successfully retrieved the artifact", content_type = "application/octet-stream"),
+        (status = 404, description = "The artifact is not in the store")
+    )
+)]
+/// Download an artifact, like a proof or a guest input, from the artifact store.
+///
+/// The statuses of the requests whose proofs were moved to the store reference the artifact
+/// by its id.
+async fn artifact_handler(
+    State(actor): State<Actor>,
+    Path(id): Path<String>,
+) -> HostResult<Response> {
+    let Ok(id) = id.parse::<ArtifactId>() else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let artifact = tokio::task::spawn_blocking(move || actor.pool_get_artifact(&id)).await??;
+    match artifact {
+        Some(data) => {
+            Ok(([(header::CONTENT_TYPE, "application/octet-stream")], data).into_response())
+        }
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(artifact_handler))]
+struct Docs;
+
+pub fn create_docs() -> utoipa::openapi::OpenApi {
+    Docs::openapi()
+}
+
+pub fn create_router() -> Router<Actor> {
+    Router::new().route("/:id", get(artifact_handler))
+}
@@ -14,6 +14,7 @@ pub use crate::server::api::v2::ProofResponse;
 pub use crate::server::api::v2::PruneStatus;
 pub use crate::server::api::v2::Status;
 
+mod artifact;
 mod proof;
 
 #[derive(OpenApi)]
@@ -36,6 +37,8 @@ mod proof;
         schemas(
             raiko_core::interfaces::ProofRequestOpt,
             raiko_core::interfaces::ProverSpecificOpts,
+            raiko_core::HeaderMismatch,
+            raiko_core::HeaderFieldMismatch,
             crate::interfaces::HostError,
             GuestOutputDoc,
             ProofResponse,
@@ -61,6 +64,7 @@ pub fn create_docs() -> utoipa::openapi::OpenApi {
         v1::health::create_docs(),
         v1::metrics::create_docs(),
         proof::create_docs(),
+        artifact::create_docs(),
     ]
     .into_iter()
     .fold(Docs::openapi(), |mut doc, sub_doc| {
@@ -76,6 +80,7 @@ pub fn create_router() -> Router<Actor> {
         // Only add the concurrency limit to the proof route. We want to still be able to call
         // healthchecks and metrics to have insight into the system.
         .nest("/proof", proof::create_router())
+        .nest("/artifacts", artifact::create_router())
         .nest("/health", v1::health::create_router())
         .nest("/metrics", v1::metrics::create_router())
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", docs.clone()))
@@ -9,7 +9,12 @@ use utoipa::OpenApi;
 use crate::{
     interfaces::HostResult,
     metrics::{inc_current_req, inc_guest_req_count, inc_host_req_count},
-    server::{api::v3::Status, to_v3_status, HostError},
+    server::{
+        api::v3::Status,
+        to_v3_status,
+        utils::{deadline_from_timestamp, ensure_not_paused},
+        HostError,
+    },
 };
 use raiko_reqactor::Actor;
 
@@ -37,6 +42,7 @@ async fn aggregation_handler(
     Json(mut aggregation_request): Json<AggregationOnlyRequest>,
 ) -> HostResult<Status> {
     inc_current_req();
+    ensure_not_paused(&actor)?;
     // Override the existing proof request config from the config file and command line
     // options with the request from the client.
     let default_request_config = actor.default_request_config();
@@ -56,6 +62,10 @@ async fn aggregation_handler(
         return Err(anyhow::anyhow!("No proofs provided").into());
     }
 
+    let deadline = aggregation_request
+        .deadline
+        .map(deadline_from_timestamp)
+        .transpose()?;
     let agg_request_key =
         AggregationRequestKey::new(proof_type, aggregation_request.aggregation_ids.clone()).into();
     let agg_request_entity = AggregationRequestEntity::new(
@@ -64,6 +74,7 @@ async fn aggregation_handler(
         proof_type,
         aggregation_request.prover_args,
     )
+    .with_deadline(deadline)
     .into();
 
     let result = crate::server::prove(&actor, agg_request_key, agg_request_entity).await;
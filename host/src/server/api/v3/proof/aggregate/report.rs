@@ -1,21 +1,47 @@
-use axum::{extract::State, routing::get, Json, Router};
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use raiko_reqpool::RequestKey;
 use raiko_tasks::AggregationTaskReport;
 use utoipa::OpenApi;
 
-use crate::interfaces::HostResult;
+use crate::{
+    interfaces::HostResult,
+    server::api::v2::proof::{
+        list::{list_statuses, ListQuery, TaskPage},
+        report::{to_aggregation_descriptor, to_task_status},
+    },
+};
 use raiko_reqactor::Actor;
 
-#[utoipa::path(post, path = "/proof/aggregate/report",
+#[utoipa::path(get, path = "/proof/aggregate/report",
     tag = "Proving",
+    params(ListQuery),
     responses (
-        (status = 200, description = "Successfully retrieved a report of all aggregation tasks", body = AggregationTaskReport)
+        (status = 200, description = "Successfully retrieved a report of the aggregation tasks matching the filters")
     )
 )]
-/// List all aggregation tasks.
+/// List the aggregation tasks.
 ///
-/// Retrieve a list of aggregation task reports.
-async fn report_handler(State(_actor): State<Actor>) -> HostResult<Json<AggregationTaskReport>> {
-    todo!()
+/// Retrieve a page of `{ total, offset, tasks }`, the tasks being `[descriptor, status]` items
+/// of the latest updated aggregation tasks first. An aggregation is in the block range when any
+/// of its blocks is.
+async fn report_handler(
+    State(actor): State<Actor>,
+    Query(query): Query<ListQuery>,
+) -> HostResult<Json<TaskPage<AggregationTaskReport>>> {
+    let aggregations =
+        list_statuses(&actor, &query)?
+            .into_iter()
+            .filter_map(|(request_key, status)| match request_key {
+                RequestKey::Aggregation(key) => Some((key, status)),
+                _ => None,
+            });
+    Ok(Json(query.paginate(aggregations, |key, status| {
+        (to_aggregation_descriptor(key), to_task_status(status))
+    })))
 }
 
 #[derive(OpenApi)]
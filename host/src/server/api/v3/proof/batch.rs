@@ -4,7 +4,10 @@ use crate::{
         api::v3::{ProofResponse, Status},
         handler::prove_many,
         prove_aggregation,
-        utils::{draw_for_zk_any_batch_request, is_zk_any_request, to_v3_status},
+        utils::{
+            draw_for_zk_any_batch_request, ensure_not_paused, is_zk_any_request, request_deadline,
+            to_v3_status,
+        },
     },
 };
 use axum::{extract::State, routing::post, Json, Router};
@@ -41,10 +44,12 @@ async fn batch_handler(
     State(actor): State<Actor>,
     Json(batch_request_opt): Json<Value>,
 ) -> HostResult<Status> {
+    ensure_not_paused(&actor)?;
     tracing::debug!(
         "Received batch request: {}",
         serde_json::to_string(&batch_request_opt)?
     );
+    let deadline = request_deadline(&batch_request_opt)?;
 
     let batch_request = {
         // Override the existing proof request config from the config file and command line
@@ -128,7 +133,10 @@ async fn batch_handler(
             batch_request.prover.clone(),
             batch_request.proof_type,
             batch_request.prover_args.clone().into(),
-        );
+        )
+        .with_deadline(deadline)
+        // The guest input is read from the input request by key once it is generated
+        .with_dependencies(vec![input_request_key.clone().into()]);
 
         sub_input_request_keys.push(input_request_key.into());
         sub_request_keys.push(request_key.into());
@@ -137,6 +145,10 @@ async fn batch_handler(
         sub_batch_ids.push(*batch_id);
     }
 
+    // Register the whole chain "generate batch input -> prove each batch -> aggregate" at once,
+    // the actor starts each request as soon as the ones it depends on are done
+    let _input_statuses =
+        prove_many(&actor, sub_input_request_keys, sub_input_request_entities).await?;
     let result = if batch_request.aggregate {
         prove_aggregation(
             &actor,
@@ -147,73 +159,27 @@ async fn batch_handler(
                 batch_request.proof_type,
                 batch_request.prover_args,
             )
+            .with_deadline(deadline)
             .into(),
             sub_request_keys,
             sub_request_entities,
         )
         .await
     } else {
-        let statuses =
-            prove_many(&actor, sub_input_request_keys, sub_input_request_entities).await?;
-        let is_all_sub_success = statuses
-            .iter()
-            .all(|status| matches!(status, raiko_reqpool::Status::Success { .. }));
-        if !is_all_sub_success {
-            Ok(raiko_reqpool::Status::Registered)
-        } else {
-            let guest_inputs_of_entities = statuses
-                .iter()
-                .map(|status| match status {
-                    // get saved guest input and pass down to real prover
-                    raiko_reqpool::Status::Success { proof, .. } => proof.proof.clone().unwrap(),
-                    _ => unreachable!("is_all_sub_success checked"),
-                })
-                .collect::<Vec<_>>();
-            let sub_request_entities = sub_request_entities
-                .iter()
-                .zip(guest_inputs_of_entities)
-                .to_owned()
-                .map(|(entity, guest_input)| match entity {
-                    raiko_reqpool::RequestEntity::BatchProof(request_entity) => {
-                        let mut prover_args = request_entity.prover_args().clone();
-                        prover_args.insert(
-                            "batch_guest_input".to_string(),
-                            serde_json::to_value(guest_input).expect(""),
-                        );
-                        BatchProofRequestEntity::new_with_guest_input_entity(
-                            request_entity.guest_input_entity().clone(),
-                            request_entity.prover().clone(),
-                            *request_entity.proof_type(),
-                            prover_args,
-                        )
-                        .into()
-                    }
-                    _ => entity.clone(),
-                })
-                .collect::<Vec<_>>();
-
-            prove_many(&actor, sub_request_keys, sub_request_entities)
-                .await
-                .map(|statuses| {
-                    let is_all_sub_success = statuses
-                        .iter()
-                        .all(|status| matches!(status, raiko_reqpool::Status::Success { .. }));
-                    if !is_all_sub_success {
-                        raiko_reqpool::Status::WorkInProgress
-                    } else {
-                        raiko_reqpool::Status::Success {
-                            // NOTE: Return the proof of the first sub-request
-                            proof: {
-                                if let raiko_reqpool::Status::Success { proof, .. } = &statuses[0] {
-                                    proof.clone()
-                                } else {
-                                    Proof::default()
-                                }
-                            },
-                        }
-                    }
-                })
-        }
+        prove_many(&actor, sub_request_keys, sub_request_entities)
+            .await
+            .map(|statuses| {
+                match statuses.iter().find(|status| !status.is_success()) {
+                    Some(status) => status.clone(),
+                    // NOTE: Return the proof of the first sub-request
+                    None => statuses
+                        .into_iter()
+                        .next()
+                        .unwrap_or(raiko_reqpool::Status::Success {
+                            proof: Proof::default(),
+                        }),
+                }
+            })
     };
     tracing::debug!("Batch proof result: {}", serde_json::to_string(&result)?);
     Ok(to_v3_status(
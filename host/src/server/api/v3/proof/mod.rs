@@ -4,7 +4,7 @@ use crate::{
     server::{
         api::{v2, v3::Status},
         prove_aggregation,
-        utils::to_v3_status,
+        utils::{ensure_not_paused, to_v3_status},
     },
 };
 use axum::{extract::State, routing::post, Json, Router};
@@ -43,6 +43,7 @@ async fn proof_handler(
     Json(mut aggregation_request): Json<AggregationRequest>,
 ) -> HostResult<Status> {
     inc_current_req();
+    ensure_not_paused(&actor)?;
 
     // Override the existing proof request config from the config file and command line
     // options with the request from the client.
@@ -27,6 +27,10 @@ pub async fn cancel(actor: &Actor, request_key: RequestKey) -> Result<Status, St
 }
 
 /// Prove the aggregation request and its sub-requests.
+///
+/// The sub-requests and the aggregation request depending on them are registered at once, the
+/// actor proves the aggregation as soon as the sub-requests are proven, without waiting for
+/// the client to poll again.
 pub async fn prove_aggregation(
     actor: &Actor,
     request_key: AggregationRequestKey,
@@ -35,28 +39,10 @@ pub async fn prove_aggregation(
     sub_request_entities: Vec<RequestEntity>,
 ) -> Result<Status, String> {
     // Prove the sub-requests
-    let statuses = prove_many(actor, sub_request_keys, sub_request_entities).await?;
-    let is_all_sub_success = statuses
-        .iter()
-        .all(|status| matches!(status, Status::Success { .. }));
-    if !is_all_sub_success {
-        return Ok(Status::Registered);
-    }
+    let _statuses = prove_many(actor, sub_request_keys.clone(), sub_request_entities).await?;
 
-    // Prove the aggregation request
-    let proofs = statuses
-        .into_iter()
-        .map(|status| match status {
-            Status::Success { proof } => proof,
-            _ => unreachable!("checked above"),
-        })
-        .collect();
-    let request_entity = AggregationRequestEntity::new(
-        request_entity_without_proofs.aggregation_ids().clone(),
-        proofs,
-        request_entity_without_proofs.proof_type().clone(),
-        request_entity_without_proofs.prover_args().clone(),
-    );
+    // Prove the aggregation request, with the proofs of the sub-requests read by key
+    let request_entity = request_entity_without_proofs.with_dependencies(sub_request_keys);
     prove(actor, request_key.into(), request_entity.into()).await
 }
 
@@ -103,8 +89,8 @@ pub async fn cancel_aggregation(
 
 // Send the action to the Actor and return the response status
 async fn act(actor: &Actor, action: Action) -> Result<Status, String> {
-    // Check if the system is paused
-    if actor.is_paused() {
+    // New proving work is not accepted while paused, cancelling still is
+    if actor.is_paused() && matches!(action, Action::Prove { .. }) {
         return Err("System is paused".to_string());
     }
 
@@ -1,9 +1,15 @@
 use crate::{
-    interfaces::HostResult,
+    interfaces::{HostError, HostResult},
     server::api::{v2, v3},
 };
-use raiko_core::{interfaces::RaikoError, provider::get_task_data};
-use raiko_lib::proof_type::ProofType;
+use chrono::{DateTime, Utc};
+use raiko_ballot::BlockMetadata;
+use raiko_core::{
+    interfaces::RaikoError,
+    preflight::parse_l1_batch_proposal_for_pacaya_fork,
+    provider::{get_task_data, rpc::RpcBlockDataProvider, BlockDataProvider},
+};
+use raiko_lib::{consts::ChainSpec, proof_type::ProofType};
 use raiko_reqactor::Actor;
 use raiko_reqpool::Status;
 use raiko_tasks::TaskStatus;
@@ -29,7 +35,14 @@ pub fn to_v2_status(
                     Status::Cancelled => v2::ProofResponse::Status {
                         status: TaskStatus::Cancelled,
                     },
-                    Status::Failed { error } => v2::ProofResponse::Status {
+                    Status::Failed {
+                        error,
+                        header_mismatch: Some(header_mismatch),
+                    } => v2::ProofResponse::HeaderMismatch {
+                        status: TaskStatus::AnyhowError(error),
+                        header_mismatch,
+                    },
+                    Status::Failed { error, .. } => v2::ProofResponse::Status {
                         status: TaskStatus::AnyhowError(error),
                     },
                     Status::Success { proof } => v2::ProofResponse::Proof { proof },
@@ -74,6 +87,34 @@ pub fn to_v3_cancel_status(result: Result<Status, String>) -> v3::CancelStatus {
     to_v2_cancel_status(result)
 }
 
+/// The `deadline` of a raw proof request, a unix timestamp in seconds.
+pub fn request_deadline(request: &Value) -> HostResult<Option<DateTime<Utc>>> {
+    match &request["deadline"] {
+        Value::Null => Ok(None),
+        deadline => match deadline.as_u64() {
+            Some(timestamp) => deadline_from_timestamp(timestamp).map(Some),
+            None => Err(HostError::InvalidRequestConfig(
+                "Invalid deadline".to_string(),
+            )),
+        },
+    }
+}
+
+pub fn deadline_from_timestamp(timestamp: u64) -> HostResult<DateTime<Utc>> {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+        .ok_or_else(|| HostError::InvalidRequestConfig("Invalid deadline".to_string()))
+}
+
+/// Reject new proof requests while the system is paused, before drawing or fetching anything.
+pub fn ensure_not_paused(actor: &Actor) -> HostResult<()> {
+    if actor.is_paused() {
+        return Err(HostError::SystemPaused);
+    }
+    Ok(())
+}
+
 // A zk_any request looks like: { "proof_type": "zk_any", "zk_any": { "aggregation": <bool> } }
 pub fn is_zk_any_request(proof_request_opt: &Value) -> bool {
     let proof_type = proof_request_opt["proof_type"].as_str();
@@ -100,7 +141,17 @@ pub async fn draw_for_zk_any_request(
                 "Missing block number".to_string(),
             ))?;
     let (_, blockhash) = get_task_data(&network, block_number, actor.chain_specs()).await?;
-    Ok(actor.draw(&blockhash))
+    // A request polled again reuses its draw, without fetching the metadata
+    if let Some(proof_type) = actor.recorded_draw(&blockhash, block_number) {
+        return Ok(proof_type);
+    }
+    // The metadata is only fetched when the ballot rules depend on it
+    let mut metadata = BlockMetadata::default();
+    if !actor.get_ballot().rules().is_empty() {
+        let taiko_chain_spec = actor.get_chain_spec(network)?;
+        metadata = blocks_metadata(&taiko_chain_spec, &[block_number]).await?;
+    }
+    Ok(actor.draw(&blockhash, block_number, &metadata))
 }
 
 pub async fn draw_for_zk_any_batch_request(
@@ -126,10 +177,92 @@ pub async fn draw_for_zk_any_batch_request(
     let first_batch = batches.first().ok_or(RaikoError::InvalidRequestConfig(
         "batches is empty".to_string(),
     ))?;
+    let batch_id = first_batch["batch_id"]
+        .as_u64()
+        .ok_or(RaikoError::InvalidRequestConfig(
+            "Missing batch_id".to_string(),
+        ))?;
     let l1_inclusion_block_number = first_batch["l1_inclusion_block_number"].as_u64().ok_or(
         RaikoError::InvalidRequestConfig("Missing l1_inclusion_block_number".to_string()),
     )?;
     let (_, blockhash) =
         get_task_data(&l1_network, l1_inclusion_block_number, actor.chain_specs()).await?;
-    Ok(actor.draw(&blockhash))
+    // The batches proposed in the same L1 block are drawn apart, and a request polled again
+    // reuses its draw, without fetching the metadata
+    if let Some(proof_type) = actor.recorded_draw(&blockhash, batch_id) {
+        return Ok(proof_type);
+    }
+    // The metadata is only fetched when the ballot rules depend on it
+    let mut metadata = BlockMetadata::default();
+    if !actor.get_ballot().rules().is_empty() {
+        let network =
+            batch_proof_request_opt["network"]
+                .as_str()
+                .ok_or(RaikoError::InvalidRequestConfig(
+                    "Missing network".to_string(),
+                ))?;
+        metadata = batches_metadata(actor, network, l1_network, batches).await?;
+    }
+    Ok(actor.draw(&blockhash, batch_id, &metadata))
+}
+
+/// The metadata of all the L2 blocks of the batches, which are proven together.
+async fn batches_metadata(
+    actor: &Actor,
+    network: &str,
+    l1_network: &str,
+    batches: &[Value],
+) -> HostResult<BlockMetadata> {
+    let taiko_chain_spec = actor.get_chain_spec(network)?;
+    let l1_chain_spec = actor.get_chain_spec(l1_network)?;
+    let mut block_numbers = Vec::new();
+    let mut uses_blobs = false;
+    for batch in batches {
+        let (Some(batch_id), Some(l1_inclusion_block_number)) = (
+            batch["batch_id"].as_u64(),
+            batch["l1_inclusion_block_number"].as_u64(),
+        ) else {
+            return Err(RaikoError::InvalidRequestConfig(
+                "Missing batch_id or l1_inclusion_block_number".to_string(),
+            )
+            .into());
+        };
+        let (batch_block_numbers, blob_used) = parse_l1_batch_proposal_for_pacaya_fork(
+            &l1_chain_spec,
+            &taiko_chain_spec,
+            l1_inclusion_block_number,
+            batch_id,
+        )
+        .await?;
+        block_numbers.extend(batch_block_numbers);
+        uses_blobs |= blob_used;
+    }
+    let mut metadata = blocks_metadata(&taiko_chain_spec, &block_numbers).await?;
+    metadata.uses_blobs = Some(uses_blobs);
+    Ok(metadata)
+}
+
+async fn blocks_metadata(
+    taiko_chain_spec: &ChainSpec,
+    block_numbers: &[u64],
+) -> HostResult<BlockMetadata> {
+    let Some(first_block_number) = block_numbers.first() else {
+        return Ok(BlockMetadata::default());
+    };
+    let provider = RpcBlockDataProvider::new_with_fallbacks(
+        &taiko_chain_spec.rpc_endpoints(),
+        *first_block_number,
+    )
+    .await?;
+    let blocks_to_fetch = block_numbers
+        .iter()
+        .map(|block_number| (*block_number, false))
+        .collect::<Vec<_>>();
+    let blocks = provider.get_blocks(&blocks_to_fetch).await?;
+    Ok(BlockMetadata {
+        gas_used: Some(blocks.iter().map(|b| b.header.gas_used as u64).sum()),
+        tx_count: Some(blocks.iter().map(|b| b.transactions.len() as u64).sum()),
+        batch_size: Some(block_numbers.len() as u64),
+        uses_blobs: None,
+    })
 }
@@ -75,6 +75,7 @@ pub async fn make_aggregate_proof_request(
         aggregation_ids: block_numbers,
         proofs,
         proof_type: Some(proof_type.to_string()),
+        deadline: None,
         prover_args: ProverSpecificOpts {
             native: Some(json!({
                 "json_guest_input": json_guest_input,
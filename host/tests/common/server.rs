@@ -1,7 +1,7 @@
 use crate::common::Client;
 use raiko_ballot::Ballot;
 use raiko_host::{parse_chain_specs, server::serve, Opts};
-use raiko_reqactor::start_actor;
+use raiko_reqactor::{start_actor, DeadlinePolicy, RetryPolicy, SchedulerConfig};
 use raiko_reqpool::memory_pool;
 use rand::Rng;
 
@@ -52,7 +52,9 @@ impl TestServerBuilder {
             ballot,
             chain_specs.clone(),
             default_request_config.clone(),
-            max_proving_concurrency,
+            SchedulerConfig::new(max_proving_concurrency),
+            RetryPolicy::default(),
+            DeadlinePolicy::default(),
         )
         .await;
 
@@ -16,6 +16,58 @@ async fn test_pause() -> Result<(), reqwest::Error> {
     Ok(())
 }
 
+#[test_log::test(tokio::test)]
+async fn test_pause_drain_resume() -> Result<(), reqwest::Error> {
+    let (_server, client) = setup().await;
+
+    client
+        .reqwest_client
+        .post(client.build_url("/admin/pause"))
+        .send()
+        .await?;
+
+    // Nothing is in flight, so the system is drained right away
+    let drain_status: Value = client
+        .reqwest_client
+        .get(client.build_url("/admin/drain"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(drain_status["paused"], true);
+    assert_eq!(drain_status["drained"], true);
+
+    // Proof requests are rejected while paused
+    let response: Value = client
+        .reqwest_client
+        .post(client.build_url("/v3/proof/batch"))
+        .json(&serde_json::json!({}))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(response["status"], "error");
+    assert_eq!(response["error"], "system_paused");
+
+    let response = client
+        .reqwest_client
+        .post(client.build_url("/admin/resume"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await?, "System resumed successfully");
+
+    let drain_status: Value = client
+        .reqwest_client
+        .get(client.build_url("/admin/drain"))
+        .send()
+        .await?
+        .json()
+        .await?;
+    assert_eq!(drain_status["paused"], false);
+    Ok(())
+}
+
 #[test_log::test(tokio::test)]
 async fn test_admin_ballot() {
     let (_server, client) = setup().await;
@@ -47,3 +99,72 @@ async fn test_admin_ballot() {
 
     assert_eq!(updating_ballot, updated_ballot);
 }
+
+#[test_log::test(tokio::test)]
+async fn test_admin_ballot_rules() {
+    let (_server, client) = setup().await;
+
+    let rules = serde_json::json!([
+        {"proof_type": "Sp1", "action": "always", "when": {"min_batch_size": 11}},
+        {"proof_type": "Risc0", "action": "only", "when": {"max_gas_used": 1000000}}
+    ]);
+    let set_response = client
+        .reqwest_client
+        .post(&client.build_url("/admin/set_ballot_rules"))
+        .json(&rules)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        set_response.text().await.unwrap(),
+        "Ballot rules set successfully".to_string()
+    );
+
+    // Setting the probabilities keeps the rules
+    client
+        .reqwest_client
+        .post(&client.build_url("/admin/set_ballot"))
+        .json(&serde_json::json!({"Sp1": [0.5, 0]}))
+        .send()
+        .await
+        .unwrap();
+    let updated_rules: Value = client
+        .reqwest_client
+        .get(client.build_url("/admin/get_ballot_rules"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(rules, updated_rules);
+
+    // Invalid ranges are rejected
+    let response = client
+        .reqwest_client
+        .post(&client.build_url("/admin/set_ballot_rules"))
+        .json(&serde_json::json!([
+            {"proof_type": "Sp1", "action": "always", "when": {"min_tx_count": 10, "max_tx_count": 1}}
+        ]))
+        .send()
+        .await
+        .unwrap();
+    assert!(!response.status().is_success());
+}
+
+#[test_log::test(tokio::test)]
+async fn test_admin_ballot_history() {
+    let (_server, client) = setup().await;
+
+    // Nothing is drawn without proof requests
+    let history: Value = client
+        .reqwest_client
+        .get(client.build_url("/admin/ballot/history"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(history, serde_json::json!([]));
+}
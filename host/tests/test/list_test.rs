@@ -0,0 +1,64 @@
+use crate::common::setup;
+use serde_json::Value;
+
+#[test_log::test(tokio::test)]
+async fn test_v2_list_empty_pool() {
+    let (_server, client) = setup().await;
+
+    let page: Value = client
+        .get("/v2/proof/list?status=registered&chain_id=167000&from=1&to=100&limit=10")
+        .await
+        .expect("failed to list the tasks")
+        .json()
+        .await
+        .expect("failed to decode the listed tasks");
+    assert_eq!(page["total"], 0);
+    assert_eq!(page["offset"], 0);
+    assert_eq!(page["tasks"], serde_json::json!([]));
+}
+
+#[test_log::test(tokio::test)]
+async fn test_v3_aggregate_report_empty_pool() {
+    let (_server, client) = setup().await;
+
+    let page: Value = client
+        .get("/v3/proof/aggregate/report?proof_type=sp1&since=1700000000&offset=5")
+        .await
+        .expect("failed to report the aggregation tasks")
+        .json()
+        .await
+        .expect("failed to decode the aggregation report");
+    assert_eq!(page["total"], 0);
+    assert_eq!(page["offset"], 5);
+    assert_eq!(page["tasks"], serde_json::json!([]));
+}
+
+#[test_log::test(tokio::test)]
+async fn test_list_invalid_proof_type() {
+    let (_server, client) = setup().await;
+
+    let response: Value = client
+        .get("/v2/proof/list?proof_type=unknown")
+        .await
+        .expect("failed to list the tasks")
+        .json()
+        .await
+        .expect("failed to decode the error");
+    assert_eq!(response["status"], "error");
+    assert_eq!(response["error"], "invalid_request_config");
+}
+
+#[test_log::test(tokio::test)]
+async fn test_list_invalid_status() {
+    let (_server, client) = setup().await;
+
+    let response: Value = client
+        .get("/v3/proof/aggregate/report?status=sucess")
+        .await
+        .expect("failed to report the aggregation tasks")
+        .json()
+        .await
+        .expect("failed to decode the error");
+    assert_eq!(response["status"], "error");
+    assert_eq!(response["error"], "invalid_request_config");
+}
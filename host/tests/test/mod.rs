@@ -1,6 +1,7 @@
 pub mod admin_test;
 pub mod aggregate_test;
 pub mod cancel_test;
+pub mod list_test;
 pub mod manual_test;
 pub mod prove_test;
 
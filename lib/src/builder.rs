@@ -1,19 +1,21 @@
-use core::mem;
-use std::sync::Arc;
+use core::{iter, mem};
+use std::{collections::HashSet, sync::Arc};
 
 use crate::primitives::keccak::keccak;
-use crate::primitives::mpt::StateAccount;
+use crate::primitives::mpt::{
+    collect_nodes, node_from_digest, resolve_nodes, MptNode, MptNodeReference, StateAccount,
+};
 use crate::utils::{generate_transactions, generate_transactions_for_batch_blocks};
 use crate::{
-    consts::{ChainSpec, MAX_BLOCK_HASH_AGE},
+    consts::{ChainSpec, Eip1559Constants, MAX_BLOCK_HASH_AGE},
     guest_mem_forget,
-    input::{GuestBatchInput, GuestInput},
+    input::{BatchSharedState, GuestBatchInput, GuestInput},
     mem_db::{AccountState, DbAccount, MemDb},
     CycleTracker,
 };
 use anyhow::{bail, ensure, Result};
 use reth_chainspec::{
-    ChainSpecBuilder, Hardfork, HOLESKY, MAINNET, TAIKO_A7, TAIKO_DEV, TAIKO_MAINNET,
+    BaseFeeParams, ChainSpecBuilder, Hardfork, HOLESKY, MAINNET, TAIKO_A7, TAIKO_DEV, TAIKO_MAINNET,
 };
 use reth_evm::execute::{BlockExecutionOutput, BlockValidationError, Executor, ProviderError};
 use reth_evm_ethereum::execute::{
@@ -25,7 +27,9 @@ use reth_primitives::revm_primitives::{
     Account, AccountInfo, AccountStatus, Bytecode, Bytes, HashMap, SpecId,
 };
 use reth_primitives::{
-    Address, Block, BlockWithSenders, Header, TransactionSigned, B256, KECCAK_EMPTY, U256,
+    proofs::{calculate_receipt_root, calculate_withdrawals_root},
+    Address, Block, BlockWithSenders, Bloom, Header, Receipt, TransactionSigned, B256,
+    KECCAK_EMPTY, U256,
 };
 use tracing::{debug, error};
 
@@ -56,27 +60,148 @@ pub fn calculate_block_header(input: &GuestInput) -> Header {
 }
 
 pub fn calculate_batch_blocks_final_header(input: &GuestBatchInput) -> Vec<Block> {
-    let pool_txs_list = generate_transactions_for_batch_blocks(&input.taiko);
-    let mut final_blocks = Vec::new();
-    for (i, pool_txs) in pool_txs_list.iter().enumerate() {
-        let mut builder = RethBlockBuilder::new(
-            &input.inputs[i],
-            create_mem_db(&mut input.inputs[i].clone()).unwrap(),
-        );
+    let final_blocks = build_batch_blocks(input).expect("execute single batched block");
+    validate_final_batch_blocks(input, &final_blocks);
+    final_blocks
+}
+
+/// Builds all the blocks of a batch.
+pub fn build_batch_blocks(batch_input: &GuestBatchInput) -> Result<Vec<Block>> {
+    let mut final_blocks = Vec::with_capacity(batch_input.inputs.len());
+    for_each_batch_block(batch_input, |builder, pool_txs| {
+        builder.execute_transactions(pool_txs, false)?;
+        final_blocks.push(builder.finalize_block()?);
+        Ok(())
+    })?;
+    Ok(final_blocks)
+}
 
-        let mut execute_tx = vec![input.inputs[i].taiko.anchor_tx.clone().unwrap()];
+/// Runs `execute` with the builder and the transactions of every block of a batch, in order.
+///
+/// With a [BatchSharedState], every block after the first is executed on top of the tries left
+/// by the previous block, and the parts it needs that are still missing are resolved from the
+/// shared nodes.
+pub fn for_each_batch_block(
+    batch_input: &GuestBatchInput,
+    mut execute: impl FnMut(&mut RethBlockBuilder<MemDb>, Vec<TransactionSigned>) -> Result<()>,
+) -> Result<()> {
+    let pool_txs_list = generate_transactions_for_batch_blocks(&batch_input.taiko);
+    let shared_state = &batch_input.shared_state;
+    let node_store: HashMap<MptNodeReference, MptNode> = shared_state
+        .nodes
+        .iter()
+        .map(|node| (node.reference(), node.clone()))
+        .collect();
+
+    // the state left by the previous block: its state trie, the latest storage trie of every
+    // account seen so far and the ancestors of the next block
+    let mut state_trie = None;
+    let mut storage_tries: HashMap<Address, MptNode> = HashMap::new();
+    let mut ancestor_headers = Vec::new();
+
+    for (input, pool_txs) in batch_input.inputs.iter().zip(pool_txs_list) {
+        let input = match &state_trie {
+            Some(state_trie) => apply_shared_state(
+                input,
+                state_trie,
+                &storage_tries,
+                &ancestor_headers,
+                shared_state,
+                &node_store,
+            ),
+            None => input.clone(),
+        };
+
+        let mut builder = RethBlockBuilder::new(&input, create_mem_db(&mut input.clone())?);
+        let mut execute_tx = vec![input.taiko.anchor_tx.clone().unwrap()];
         execute_tx.extend_from_slice(&pool_txs);
-        builder
-            .execute_transactions(execute_tx.clone(), false)
-            .expect("execute");
-        final_blocks.push(
-            builder
-                .finalize_block()
-                .expect("execute single batched block"),
-        );
+        execute(&mut builder, execute_tx)?;
+
+        if !shared_state.is_empty() {
+            let post_input = builder.input;
+            ancestor_headers = iter::once(post_input.parent_header)
+                .chain(post_input.ancestor_headers)
+                .take(MAX_BLOCK_HASH_AGE as usize - 1)
+                .collect();
+            storage_tries.extend(
+                post_input
+                    .parent_storage
+                    .into_iter()
+                    .map(|(address, (storage_trie, _))| (address, storage_trie)),
+            );
+            state_trie = Some(post_input.parent_state_trie);
+        }
     }
-    validate_final_batch_blocks(input, &final_blocks);
-    final_blocks
+    Ok(())
+}
+
+/// Rebuilds the full state of a block reduced by [share_batch_state] from the state left by
+/// the previous block.
+fn apply_shared_state(
+    input: &GuestInput,
+    state_trie: &MptNode,
+    storage_tries: &HashMap<Address, MptNode>,
+    ancestor_headers: &[Header],
+    shared_state: &BatchSharedState,
+    node_store: &HashMap<MptNodeReference, MptNode>,
+) -> GuestInput {
+    let mut input = input.clone();
+    input.parent_state_trie = resolve_nodes(state_trie, node_store);
+    for (address, (storage_trie, _)) in input.parent_storage.iter_mut() {
+        let resolved = match storage_tries.get(address) {
+            Some(previous) if previous.hash() == storage_trie.hash() => {
+                resolve_nodes(previous, node_store)
+            }
+            _ => resolve_nodes(storage_trie, node_store),
+        };
+        *storage_trie = resolved;
+    }
+    if input.contracts.is_empty() {
+        input.contracts = shared_state.contracts.clone();
+    }
+    if input.ancestor_headers.is_empty() {
+        input.ancestor_headers = ancestor_headers.to_vec();
+    }
+    input
+}
+
+/// Moves the state of every block after the first into the [BatchSharedState] of the batch,
+/// so that the nodes and contracts used by several blocks are only carried once.
+pub fn share_batch_state(batch_input: &mut GuestBatchInput) {
+    if batch_input.inputs.len() < 2 || !batch_input.shared_state.is_empty() {
+        return;
+    }
+
+    let mut nodes = HashMap::new();
+    let mut contracts = HashSet::new();
+    let (first, rest) = batch_input.inputs.split_first_mut().unwrap();
+    let mut previous_headers = (first.parent_header.clone(), first.ancestor_headers.clone());
+    for input in rest {
+        collect_nodes(&input.parent_state_trie, &mut nodes);
+        input.parent_state_trie = node_from_digest(input.parent_state_trie.hash());
+        for (storage_trie, _) in input.parent_storage.values_mut() {
+            collect_nodes(storage_trie, &mut nodes);
+            *storage_trie = node_from_digest(storage_trie.hash());
+        }
+        contracts.extend(mem::take(&mut input.contracts));
+
+        // the ancestors can be derived from the previous block unless close to genesis
+        let (previous_parent_header, previous_ancestors) = previous_headers;
+        let derived_ancestors: Vec<Header> = iter::once(previous_parent_header)
+            .chain(previous_ancestors)
+            .take(MAX_BLOCK_HASH_AGE as usize - 1)
+            .collect();
+        let ancestors = mem::take(&mut input.ancestor_headers);
+        if derived_ancestors != ancestors {
+            input.ancestor_headers = ancestors.clone();
+        }
+        previous_headers = (input.parent_header.clone(), ancestors);
+    }
+
+    batch_input.shared_state = BatchSharedState {
+        nodes: nodes.into_values().collect(),
+        contracts: contracts.into_iter().collect(),
+    };
 }
 
 // to check the linkages between the blocks
@@ -131,6 +256,8 @@ pub struct RethBlockBuilder<DB> {
     pub chain_spec: ChainSpec,
     pub input: GuestInput,
     pub db: Option<DB>,
+    /// Receipts of the last executed transactions.
+    pub receipts: Vec<Receipt>,
 }
 
 impl<DB: Database<Error = ProviderError> + DatabaseCommit + OptimisticDatabase>
@@ -142,6 +269,7 @@ impl<DB: Database<Error = ProviderError> + DatabaseCommit + OptimisticDatabase>
             chain_spec: input.chain_spec.clone(),
             db: Some(db),
             input: input.clone(),
+            receipts: Vec::new(),
         }
     }
 
@@ -151,6 +279,25 @@ impl<DB: Database<Error = ProviderError> + DatabaseCommit + OptimisticDatabase>
         pool_txs: Vec<TransactionSigned>,
         optimistic: bool,
     ) -> Result<()> {
+        self.execute_block(pool_txs, optimistic, false)?
+    }
+
+    /// Executes all input transactions like [Self::execute_transactions], but applies the state
+    /// changes even when the checks after the execution fail, and returns the result of these
+    /// checks separately so that the derived header can still be compared with the expected one.
+    pub fn execute_transactions_unchecked(
+        &mut self,
+        pool_txs: Vec<TransactionSigned>,
+    ) -> Result<Result<()>> {
+        self.execute_block(pool_txs, false, true)
+    }
+
+    fn execute_block(
+        &mut self,
+        pool_txs: Vec<TransactionSigned>,
+        optimistic: bool,
+        keep_unchecked: bool,
+    ) -> Result<Result<()>> {
         // Get the chain spec
         let chain_spec = &self.input.chain_spec;
         let total_difficulty = U256::ZERO;
@@ -248,9 +395,15 @@ impl<DB: Database<Error = ProviderError> + DatabaseCommit + OptimisticDatabase>
             .map(|&i| block.body[i].clone())
             .collect();
 
+        // Keep the receipts, so a failing validation can still be reported field by field
+        self.receipts = receipts;
+
         // Header validation
         let block = block.seal_slow();
-        if !optimistic {
+        let checked = (|| -> Result<()> {
+            if optimistic {
+                return Ok(());
+            }
             let consensus = EthBeaconConsensus::new(reth_chain_spec.clone());
             // Validates extra data
             consensus.validate_header_with_total_difficulty(&block.header, total_difficulty)?;
@@ -270,9 +423,13 @@ impl<DB: Database<Error = ProviderError> + DatabaseCommit + OptimisticDatabase>
                     senders: block.senders,
                 },
                 &reth_chain_spec.clone(),
-                &receipts,
+                &self.receipts,
                 &requests,
             )?;
+            Ok(())
+        })();
+        if checked.is_err() && !keep_unchecked {
+            return Ok(checked);
         }
 
         // Apply DB changes
@@ -298,7 +455,7 @@ impl<DB: Database<Error = ProviderError> + DatabaseCommit + OptimisticDatabase>
             .collect();
         self.db.as_mut().unwrap().commit(changes);
 
-        Ok(())
+        Ok(checked)
     }
 }
 
@@ -310,6 +467,42 @@ impl RethBlockBuilder<MemDb> {
         Ok(self.input.block.header.clone())
     }
 
+    /// Builds the header resulting from the executed transactions, without checking it against
+    /// the input block, so that any divergence can be reported field by field.
+    pub fn derived_header(&mut self) -> Result<Header> {
+        let receipts: Vec<_> = self
+            .receipts
+            .iter()
+            .cloned()
+            .map(Receipt::with_bloom)
+            .collect();
+        let mut header = self.input.block.header.clone();
+        header.state_root = self.calculate_state_root()?;
+        header.gas_used = self
+            .receipts
+            .last()
+            .map_or(0, |receipt| receipt.cumulative_gas_used);
+        header.receipts_root = calculate_receipt_root(&receipts);
+        header.logs_bloom = receipts
+            .iter()
+            .fold(Bloom::ZERO, |bloom, receipt| bloom | receipt.bloom);
+        header.withdrawals_root = self
+            .input
+            .block
+            .withdrawals
+            .as_ref()
+            .map(|withdrawals| calculate_withdrawals_root(withdrawals));
+        // The base fee of Taiko blocks is set by the protocol, and checked by the anchor
+        // transaction against the one of the L2 contract during the execution
+        if !self.input.chain_spec.is_taiko() {
+            header.base_fee_per_gas = next_block_base_fee(
+                &self.input.parent_header,
+                self.input.chain_spec.gas_constants(),
+            );
+        }
+        Ok(header)
+    }
+
     /// Finalizes the block building and returns the header
     pub fn finalize_block(&mut self) -> Result<Block> {
         let state_root = self.calculate_state_root()?;
@@ -383,10 +576,21 @@ impl RethBlockBuilder<MemDb> {
         debug!("Accounts touched {account_touched:?}");
         debug!("Storages touched {storage_touched:?}");
 
-        Ok(state_trie.hash())
+        let state_root = state_trie.hash();
+        // keep the updated trie, the next block of a batch is built on top of it
+        self.input.parent_state_trie = state_trie;
+        Ok(state_root)
     }
 }
 
+/// The EIP-1559 base fee of the child of `parent`, when the parent has one.
+fn next_block_base_fee(parent: &Header, constants: &Eip1559Constants) -> Option<u64> {
+    parent.next_block_base_fee(BaseFeeParams::new(
+        constants.base_fee_change_denominator.to(),
+        constants.elasticity_multiplier.to(),
+    ))
+}
+
 pub fn create_mem_db(input: &mut GuestInput) -> Result<MemDb> {
     // Verify state trie root
     if input.parent_state_trie.hash() != input.parent_header.state_root {
@@ -140,6 +140,12 @@ pub struct ChainSpec {
     pub l2_contract: Option<Address>,
     pub rpc: String,
     pub beacon_rpc: Option<String>,
+    /// Additional RPC endpoints, tried in order when `rpc` fails.
+    #[serde(default)]
+    pub rpc_fallbacks: Vec<String>,
+    /// Additional beacon endpoints, tried in order when `beacon_rpc` fails.
+    #[serde(default)]
+    pub beacon_rpc_fallbacks: Vec<String>,
     pub verifier_address_forks: BTreeMap<SpecId, BTreeMap<ProofType, Option<Address>>>,
     pub genesis_time: u64,
     pub seconds_per_slot: u64,
@@ -165,6 +171,8 @@ impl ChainSpec {
             l2_contract: None,
             rpc: "".to_string(),
             beacon_rpc: None,
+            rpc_fallbacks: Vec::new(),
+            beacon_rpc_fallbacks: Vec::new(),
             verifier_address_forks: BTreeMap::new(),
             genesis_time: 0u64,
             seconds_per_slot: 1u64,
@@ -191,6 +199,22 @@ impl ChainSpec {
         }
     }
 
+    /// Returns all the RPC endpoints, the primary one first.
+    pub fn rpc_endpoints(&self) -> Vec<String> {
+        core::iter::once(self.rpc.clone())
+            .chain(self.rpc_fallbacks.iter().cloned())
+            .collect()
+    }
+
+    /// Returns all the beacon endpoints, the primary one first.
+    pub fn beacon_rpc_endpoints(&self) -> Vec<String> {
+        self.beacon_rpc
+            .iter()
+            .chain(self.beacon_rpc_fallbacks.iter())
+            .cloned()
+            .collect()
+    }
+
     /// Returns the Eip1559 constants
     pub fn gas_constants(&self) -> &Eip1559Constants {
         &self.eip_1559_constants
@@ -378,6 +402,8 @@ mod tests {
             l2_contract: None,
             rpc: "".to_string(),
             beacon_rpc: None,
+            rpc_fallbacks: Vec::new(),
+            beacon_rpc_fallbacks: Vec::new(),
             verifier_address_forks: BTreeMap::from([(
                 SpecId::FRONTIER,
                 BTreeMap::from([
@@ -66,6 +66,32 @@ pub struct TaikoGuestBatchInput {
 pub struct GuestBatchInput {
     pub inputs: Vec<GuestInput>,
     pub taiko: TaikoGuestBatchInput,
+    /// State shared by the blocks of the batch, empty when every input carries its own state.
+    ///
+    /// The default only applies to self-describing formats like JSON. The bincode encoding of
+    /// the batch inputs built before this field existed cannot be read anymore, and has to be
+    /// generated again.
+    #[serde(default)]
+    pub shared_state: BatchSharedState,
+}
+
+/// Trie nodes and contracts shared by all the blocks of a batch after the first one.
+///
+/// When used, only the first input of the batch carries its full state. The tries of every
+/// following input are reduced to their root, and the block is executed on top of the tries
+/// left by the previous block, resolving what is missing from these nodes.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BatchSharedState {
+    /// The union of the state and storage trie nodes needed by the blocks.
+    pub nodes: Vec<MptNode>,
+    /// The code of all unique contracts needed by the blocks.
+    pub contracts: Vec<Bytes>,
+}
+
+impl BatchSharedState {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.contracts.is_empty()
+    }
 }
 
 /// External aggregation input.
@@ -344,4 +370,26 @@ mod test {
         let input_de: GuestInput = serde_json::from_value(input_ser).unwrap();
         print!("{:?}", input_de);
     }
+
+    #[test]
+    fn test_guest_batch_input_bincode_roundtrip() {
+        let mut storage_trie = MptNode::default();
+        storage_trie.insert(&[0x01; 32], vec![0x2a]).unwrap();
+        let input = GuestBatchInput {
+            inputs: vec![GuestInput::default(), GuestInput::default()],
+            shared_state: BatchSharedState {
+                nodes: vec![storage_trie.clone()],
+                contracts: vec![Bytes::from_static(&[0x60, 0x00])],
+            },
+            ..Default::default()
+        };
+        let input_de: GuestBatchInput =
+            bincode::deserialize(&bincode::serialize(&input).unwrap()).unwrap();
+        assert_eq!(input_de.inputs.len(), 2);
+        assert_eq!(input_de.shared_state.nodes[0].hash(), storage_trie.hash());
+        assert_eq!(
+            input_de.shared_state.contracts,
+            input.shared_state.contracts
+        );
+    }
 }
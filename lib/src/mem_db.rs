@@ -83,11 +83,6 @@ impl DbAccount {
     }
 }
 
-// todo: make batch mem db a LRU cache db, then we don't need to fetch
-// everything from provider as long as we execute in-sequence blocks
-pub struct BatchMemDb {
-    pub dbs: Vec<MemDb>,
-}
 /// In-memory EVM database.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct MemDb {
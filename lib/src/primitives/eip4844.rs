@@ -2,7 +2,8 @@ use kzg::kzg_types::{ZFr, ZG1};
 use kzg_traits::{
     eip_4844::{
         blob_to_kzg_commitment_rust, blob_to_polynomial, compute_kzg_proof_rust,
-        evaluate_polynomial_in_evaluation_form, hash_to_bls_field, Blob,
+        evaluate_polynomial_in_evaluation_form, hash_to_bls_field, verify_blob_kzg_proof_rust,
+        Blob,
     },
     Fr, G1,
 };
@@ -119,6 +120,22 @@ pub fn calc_kzg_proof_commitment(blob: &[u8]) -> Result<KzgGroup, Eip4844Error>
     )
 }
 
+/// Verify the KZG proof of a blob against its commitment, which is much cheaper than
+/// recomputing the commitment from the blob.
+pub fn verify_blob_kzg_proof(
+    blob: &[u8],
+    commitment: &KzgGroup,
+    proof: &KzgGroup,
+) -> Result<bool, Eip4844Error> {
+    let blob_fields = Blob::from_bytes(blob)
+        .and_then(|b| deserialize_blob_rust(&b))
+        .map_err(|_| Eip4844Error::DeserializeBlob)?;
+    let commitment = ZG1::from_bytes(commitment).map_err(Eip4844Error::KzgDataPoison)?;
+    let proof = ZG1::from_bytes(proof).map_err(Eip4844Error::KzgDataPoison)?;
+    verify_blob_kzg_proof_rust(&blob_fields, &commitment, &proof, &KZG_SETTINGS.clone())
+        .map_err(Eip4844Error::ComputeKzgProof)
+}
+
 pub fn commitment_to_version_hash(commitment: &[u8; 48]) -> B256 {
     let mut hash = Sha256::digest(commitment);
     hash[0] = VERSIONED_HASH_VERSION_KZG;
@@ -222,6 +239,18 @@ mod test {
         .unwrap());
     }
 
+    #[test]
+    fn test_verify_blob_kzg_proof() {
+        // The commitment of the zero blob and all its proofs are the point at infinity
+        let zero_blob = [0u8; 131072];
+        let infinity = calc_kzg_proof_commitment(&zero_blob).unwrap();
+        assert!(verify_blob_kzg_proof(&zero_blob, &infinity, &infinity).unwrap());
+
+        let data = (0u64..131072).map(|v| (v % 64) as u8).collect::<Vec<u8>>();
+        let commitment = calc_kzg_proof_commitment(&data).unwrap();
+        assert!(!verify_blob_kzg_proof(&data, &commitment, &infinity).unwrap());
+    }
+
     #[test]
     fn test_verify_kzg_proof_in_precompile() {
         let data = (0u64..131072).map(|v| (v % 64) as u8).collect::<Vec<u8>>();
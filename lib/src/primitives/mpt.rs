@@ -1003,6 +1003,41 @@ pub fn resolve_nodes(root: &MptNode, node_store: &HashMap<MptNodeReference, MptN
     trie
 }
 
+/// Collects all the resolved nodes of `root` that are referenced by their hash into
+/// `node_store`. Children referenced by hash are replaced by their digest, so that every node
+/// is stored only once and the trie can be rebuilt with [resolve_nodes].
+pub fn collect_nodes(root: &MptNode, node_store: &mut HashMap<MptNodeReference, MptNode>) {
+    let shallow_child = |child: &MptNode| -> MptNode {
+        match child.reference() {
+            MptNodeReference::Digest(digest) => MptNodeData::Digest(digest).into(),
+            MptNodeReference::Bytes(_) => child.clone(),
+        }
+    };
+    let shallow_node: MptNode = match root.as_data() {
+        MptNodeData::Null | MptNodeData::Digest(_) => return,
+        MptNodeData::Leaf(_, _) => root.clone(),
+        MptNodeData::Branch(children) => {
+            let children: Vec<_> = children
+                .iter()
+                .map(|child| {
+                    child.as_ref().map(|node| {
+                        collect_nodes(node, node_store);
+                        Box::new(shallow_child(node))
+                    })
+                })
+                .collect();
+            MptNodeData::Branch(children.try_into().unwrap()).into()
+        }
+        MptNodeData::Extension(prefix, target) => {
+            collect_nodes(target, node_store);
+            MptNodeData::Extension(prefix.clone(), Box::new(shallow_child(target))).into()
+        }
+    };
+    if let reference @ MptNodeReference::Digest(_) = root.reference() {
+        node_store.insert(reference, shallow_node);
+    }
+}
+
 /// Returns a list of all possible nodes that can be created by shortening the path of the
 /// given node.
 /// When nodes in an MPT are deleted, leaves or extensions may be extended. To still be
@@ -1135,7 +1170,7 @@ fn add_orphaned_leafs(
 }
 
 /// Creates a new MPT node from a digest.
-fn node_from_digest(digest: B256) -> MptNode {
+pub fn node_from_digest(digest: B256) -> MptNode {
     match digest {
         EMPTY_ROOT | B256::ZERO => MptNode::default(),
         _ => MptNodeData::Digest(digest).into(),
@@ -1367,6 +1402,38 @@ mod tests {
         assert!(trie.is_empty());
     }
 
+    #[test]
+    pub fn test_collect_and_resolve_nodes() {
+        let mut trie = MptNode::default();
+        for i in 0..64u64 {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), U256::from(i))
+                .unwrap();
+        }
+
+        let mut node_store = HashMap::new();
+        collect_nodes(&trie, &mut node_store);
+        // none of the stored nodes embeds another hashed node
+        for node in node_store.values() {
+            if let MptNodeData::Branch(children) = node.as_data() {
+                for child in children.iter().flatten() {
+                    assert!(
+                        child.is_digest()
+                            || matches!(child.reference(), MptNodeReference::Bytes(_))
+                    );
+                }
+            }
+        }
+
+        let resolved = resolve_nodes(&node_from_digest(trie.hash()), &node_store);
+        assert_eq!(resolved.hash(), trie.hash());
+        for i in 0..64u64 {
+            assert_eq!(
+                resolved.get_rlp::<U256>(&keccak(i.to_be_bytes())).unwrap(),
+                Some(U256::from(i))
+            );
+        }
+    }
+
     #[test]
     pub fn test_index_trie() {
         const N: usize = 512;
@@ -7,9 +7,10 @@ use std::{
     path::{Path, PathBuf},
     process::{Command as StdCommand, Output, Stdio},
     str::{self, FromStr},
+    sync::Arc,
 };
 
-use duct::{cmd, Expression};
+use duct::{cmd, Expression, Handle};
 use once_cell::sync::Lazy;
 use raiko_lib::{
     input::{
@@ -494,7 +495,7 @@ async fn prove(
     instance_id: u64,
     proof_type: ProofType,
 ) -> ProverResult<SgxResponse, ProverError> {
-    tokio::task::spawn_blocking(move || {
+    let gramine_cmd = tokio::task::spawn_blocking(move || {
         gramine_cmd = gramine_cmd
             .before_spawn(move |cmd| {
                 cmd.arg("one-shot")
@@ -516,20 +517,14 @@ async fn prove(
                 .map_err(|e| ProverError::GuestError(format!("Failed to serialize input: {e}")))?;
             gramine_cmd = gramine_cmd.stdin_bytes(bytes);
         }
-
-        let output_success = gramine_cmd.run();
-        match output_success {
-            Ok(output) => {
-                handle_output(&output, "SGX prove")?;
-                Ok(parse_sgx_result(output.stdout)?)
-            }
-            Err(output_err) => Err(ProverError::GuestError(
-                handle_gramine_error("Could not run SGX guest prover", output_err).to_string(),
-            )),
-        }
+        Ok::<_, ProverError>(gramine_cmd)
     })
     .await
-    .map_err(|e| ProverError::GuestError(e.to_string()))?
+    .map_err(|e| ProverError::GuestError(e.to_string()))??;
+
+    let output = run_guest(gramine_cmd).await?;
+    handle_output(&output, "SGX prove")?;
+    Ok(parse_sgx_result(output.stdout)?)
 }
 
 async fn batch_prove(
@@ -538,7 +533,7 @@ async fn batch_prove(
     instance_id: u64,
     proof_type: ProofType,
 ) -> ProverResult<SgxResponse, ProverError> {
-    tokio::task::spawn_blocking(move || {
+    let gramine_cmd = tokio::task::spawn_blocking(move || {
         gramine_cmd = gramine_cmd
             .before_spawn(move |cmd| {
                 cmd.arg("one-batch-shot")
@@ -560,20 +555,41 @@ async fn batch_prove(
                 .map_err(|e| ProverError::GuestError(format!("Failed to serialize input: {e}")))?;
             gramine_cmd = gramine_cmd.stdin_bytes(bytes);
         }
-
-        let output_success = gramine_cmd.run();
-        match output_success {
-            Ok(output) => {
-                handle_output(&output, "SGX prove")?;
-                Ok(parse_sgx_result(output.stdout)?)
-            }
-            Err(output_err) => Err(ProverError::GuestError(
-                handle_gramine_error("Could not run SGX guest prover", output_err).to_string(),
-            )),
-        }
+        Ok::<_, ProverError>(gramine_cmd)
     })
     .await
-    .map_err(|e| ProverError::GuestError(e.to_string()))?
+    .map_err(|e| ProverError::GuestError(e.to_string()))??;
+
+    let output = run_guest(gramine_cmd).await?;
+    handle_output(&output, "SGX prove")?;
+    Ok(parse_sgx_result(output.stdout)?)
+}
+
+/// Kills the guest process when dropped before the guest exited.
+struct KillOnDrop(Arc<Handle>);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if let Ok(None) = self.0.try_wait() {
+            error!("Killing the SGX guest, its proving was cancelled");
+            let _ = self.0.kill();
+        }
+    }
+}
+
+/// Run the guest until it exits. The guest is killed when the returned future is dropped, so
+/// that a cancelled request does not keep the SGX instance busy.
+async fn run_guest(gramine_cmd: Expression) -> ProverResult<Output, ProverError> {
+    let handle = Arc::new(gramine_cmd.start().map_err(|e| {
+        ProverError::GuestError(handle_gramine_error("Could not run SGX guest prover", e))
+    })?);
+    let _guard = KillOnDrop(handle.clone());
+    tokio::task::spawn_blocking(move || handle.wait().cloned())
+        .await
+        .map_err(|e| ProverError::GuestError(e.to_string()))?
+        .map_err(|e| {
+            ProverError::GuestError(handle_gramine_error("Could not run SGX guest prover", e))
+        })
 }
 
 async fn aggregate(
@@ -24,3 +24,12 @@ impl Action {
 }
 
 impl_display_using_json_pretty!(Action);
+
+/// The pause message sent from **external** to the actor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PauseAction {
+    /// Stop starting new work and let the in-flight work finish.
+    Pause,
+    /// Start the work held back while paused.
+    Resume,
+}
@@ -7,17 +7,32 @@ use std::{
     },
 };
 
-use raiko_ballot::Ballot;
+use raiko_ballot::{Ballot, BlockMetadata};
 use raiko_core::interfaces::ProofRequestOpt;
 use raiko_lib::{
     consts::{ChainSpec, SupportedChainSpecs},
     proof_type::ProofType,
 };
-use raiko_reqpool::{Pool, RequestKey, StatusWithContext};
+use raiko_reqpool::{
+    ArtifactId, BallotDraw, Pool, RequestFilter, RequestKey, Status, StatusWithContext,
+};
 use reth_primitives::BlockHash;
+use serde::{Deserialize, Serialize};
 use tokio::sync::{mpsc::Sender, oneshot};
 
-use crate::Action;
+use crate::{Action, InFlight, PauseAction};
+
+/// Progress of draining the work of a paused system.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrainStatus {
+    pub paused: bool,
+    /// Requests still being proven by this host.
+    pub work_in_progress: usize,
+    /// Requests waiting to be started once resumed.
+    pub registered: usize,
+    /// Paused and no request is being proven by this host anymore.
+    pub drained: bool,
+}
 
 /// Actor is the main interface interacting with the backend and the pool.
 #[derive(Debug, Clone)]
@@ -25,8 +40,10 @@ pub struct Actor {
     default_request_config: ProofRequestOpt,
     chain_specs: SupportedChainSpecs,
     action_tx: Sender<(Action, oneshot::Sender<Result<StatusWithContext, String>>)>,
-    pause_tx: Sender<()>,
+    pause_tx: Sender<PauseAction>,
     is_paused: Arc<AtomicBool>,
+    /// The unfinished proving works of the backend of this host.
+    in_flight: InFlight,
 
     // TODO: Remove Mutex. currently, in order to pass `&mut Pool`, we need to use Arc<Mutex<Pool>>.
     pool: Arc<Mutex<Pool>>,
@@ -41,7 +58,7 @@ impl Actor {
         default_request_config: ProofRequestOpt,
         chain_specs: SupportedChainSpecs,
         action_tx: Sender<(Action, oneshot::Sender<Result<StatusWithContext, String>>)>,
-        pause_tx: Sender<()>,
+        pause_tx: Sender<PauseAction>,
     ) -> Self {
         Self {
             default_request_config,
@@ -49,11 +66,19 @@ impl Actor {
             action_tx,
             pause_tx,
             is_paused: Arc::new(AtomicBool::new(false)),
+            in_flight: InFlight::default(),
             ballot: Arc::new(Mutex::new(ballot)),
             pool: Arc::new(Mutex::new(pool)),
         }
     }
 
+    /// Share the proving works of the backend, so that draining only waits for the ones of
+    /// this host, not the ones of the other hosts sharing the pool.
+    pub(crate) fn with_in_flight(mut self, in_flight: InFlight) -> Self {
+        self.in_flight = in_flight;
+        self
+    }
+
     /// Return the default request config.
     pub fn default_request_config(&self) -> &ProofRequestOpt {
         &self.default_request_config
@@ -87,10 +112,28 @@ impl Actor {
         self.pool.lock().unwrap().list()
     }
 
+    /// List the requests of the pool matching `filter`.
+    pub fn pool_list_status_by(
+        &self,
+        filter: &RequestFilter,
+    ) -> Result<HashMap<RequestKey, StatusWithContext>, String> {
+        self.pool.lock().unwrap().list_by(filter)
+    }
+
     pub fn pool_remove_request(&self, request_key: &RequestKey) -> Result<usize, String> {
         self.pool.lock().unwrap().remove(request_key)
     }
 
+    /// Get an artifact from the artifact store of the pool, `None` without a store.
+    pub fn pool_get_artifact(&self, id: &ArtifactId) -> Result<Option<Vec<u8>>, String> {
+        // The store may be remote, do not hold the lock of the pool while reading from it
+        let artifacts = self.pool.lock().unwrap().artifact_store().cloned();
+        match artifacts {
+            Some(artifacts) => artifacts.get(id),
+            None => Ok(None),
+        }
+    }
+
     /// Send an action to the backend and wait for the response.
     pub async fn act(&self, action: Action) -> Result<StatusWithContext, String> {
         let (resp_tx, resp_rx) = oneshot::channel();
@@ -107,25 +150,51 @@ impl Actor {
             .map_err(|e| format!("failed to receive action response: {e}"))?
     }
 
-    /// Set the pause flag and notify the task manager to pause, then wait for the task manager to
-    /// finish the pause process.
-    ///
-    /// Note that this function is blocking until the task manager finishes the pause process.
+    /// Set the pause flag and notify the backend to stop starting new work. The in-flight work
+    /// keeps running, see [`Actor::drain_status`] to know when it is done.
     pub async fn pause(&self) -> Result<(), String> {
         self.is_paused.store(true, Ordering::SeqCst);
         self.pause_tx
-            .send(())
+            .send(PauseAction::Pause)
             .await
             .map_err(|e| format!("failed to send pause signal: {e}"))?;
         Ok(())
     }
 
+    /// Clear the pause flag and notify the backend to start the work held back while paused.
+    pub async fn resume(&self) -> Result<(), String> {
+        self.pause_tx
+            .send(PauseAction::Resume)
+            .await
+            .map_err(|e| format!("failed to send resume signal: {e}"))?;
+        self.is_paused.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Count the requests that are still being proven by this host or waiting to be started.
+    pub fn drain_status(&self) -> Result<DrainStatus, String> {
+        let registered = self
+            .pool_list_status()?
+            .values()
+            .filter(|status| status.status() == &Status::Registered)
+            .count();
+        let paused = self.is_paused();
+        let work_in_progress = self.in_flight.len();
+        Ok(DrainStatus {
+            paused,
+            work_in_progress,
+            registered,
+            drained: paused && work_in_progress == 0,
+        })
+    }
+
     pub fn get_ballot(&self) -> Ballot {
         self.ballot.lock().unwrap().clone()
     }
 
     pub fn is_ballot_disabled(&self) -> bool {
-        self.ballot.lock().unwrap().probabilities().is_empty()
+        let ballot = self.ballot.lock().unwrap();
+        ballot.probabilities().is_empty() && ballot.rules().is_empty()
     }
 
     pub fn set_ballot(&self, new_ballot: Ballot) {
@@ -133,13 +202,84 @@ impl Actor {
         *ballot = new_ballot;
     }
 
-    /// Draw proof types based on the block hash.
-    pub fn draw(&self, block_hash: &BlockHash) -> Option<ProofType> {
-        self.ballot
+    /// Draw proof types based on the block hash and the metadata of the blocks to prove, once per
+    /// request of the block hash identified by `request_id`, its block number or batch id.
+    ///
+    /// The draws and the last draw times of the proof types are kept in the pool, so that the
+    /// hosts sharing it draw the same proof type for a request and the per-day limits hold
+    /// across restarts. The proof types of `always` rules are not limited per day. The draw
+    /// falls back to the in-memory ballot when the pool fails.
+    pub fn draw(
+        &self,
+        block_hash: &BlockHash,
+        request_id: u64,
+        metadata: &BlockMetadata,
+    ) -> Option<ProofType> {
+        let ballot = self.get_ballot();
+        match self.pool_draw(&ballot, block_hash, request_id, metadata) {
+            Ok(proof_type) => proof_type,
+            Err(err) => {
+                tracing::warn!("Actor failed to draw {block_hash} in the pool: {err}");
+                self.ballot
+                    .lock()
+                    .unwrap()
+                    .deref_mut()
+                    .draw_with_poisson(block_hash, request_id, metadata)
+            }
+        }
+    }
+
+    /// The draw recorded in the pool for the request of the block hash, `None` when it was not
+    /// drawn yet or the pool fails, so that the metadata of the blocks is only fetched to draw.
+    pub fn recorded_draw(
+        &self,
+        block_hash: &BlockHash,
+        request_id: u64,
+    ) -> Option<Option<ProofType>> {
+        match self
+            .pool
             .lock()
             .unwrap()
-            .deref_mut()
-            .draw_with_poisson(block_hash)
+            .get_ballot_draw(block_hash, request_id)
+        {
+            Ok(draw) => draw.map(|draw| draw.proof_type),
+            Err(err) => {
+                tracing::warn!("Actor failed to get the draw of {block_hash} in the pool: {err}");
+                None
+            }
+        }
+    }
+
+    fn pool_draw(
+        &self,
+        ballot: &Ballot,
+        block_hash: &BlockHash,
+        request_id: u64,
+        metadata: &BlockMetadata,
+    ) -> Result<Option<ProofType>, String> {
+        let mut pool = self.pool.lock().unwrap();
+        if let Some(draw) = pool.get_ballot_draw(block_hash, request_id)? {
+            return Ok(draw.proof_type);
+        }
+
+        let proof_type = ballot.draw_for(block_hash, metadata);
+        // The proof types of `always` rules are not limited per day
+        let interval = match (proof_type, ballot.always_for(metadata)) {
+            (Some(drawn), None) => ballot.draw_interval(&drawn),
+            _ => None,
+        };
+        // Another host may have drawn the request meanwhile, agree on its draw. The turn of the
+        // proof type is only taken along with the draw recorded by this host.
+        let draw = pool.record_ballot_draw(
+            BallotDraw::new(*block_hash, request_id, proof_type),
+            interval,
+        )?;
+        Ok(draw.proof_type)
+    }
+
+    /// List the draws of the ballot recorded in the pool, the latest first.
+    pub fn ballot_history(&self) -> Result<Vec<BallotDraw>, String> {
+        self.pool.lock().unwrap().list_ballot_draws()
     }
 }
 
@@ -147,6 +287,7 @@ impl Actor {
 mod tests {
     use super::*;
     use alloy_primitives::Address;
+    use raiko_ballot::{BallotRule, BlockCondition, RuleAction};
     use raiko_lib::{
         consts::SupportedChainSpecs,
         input::BlobProofType,
@@ -157,7 +298,7 @@ mod tests {
         memory_pool, RequestEntity, RequestKey, SingleProofRequestEntity, SingleProofRequestKey,
         StatusWithContext,
     };
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
     use tokio::sync::mpsc;
 
     #[tokio::test]
@@ -184,6 +325,91 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_resume_clears_is_paused_flag() {
+        let (action_tx, _) = mpsc::channel(1);
+        let (pause_tx, mut pause_rx) = mpsc::channel(2);
+
+        let pool = memory_pool("test_resume_clears_is_paused_flag");
+        let actor = Actor::new(
+            pool,
+            Ballot::default(),
+            ProofRequestOpt::default(),
+            SupportedChainSpecs::default(),
+            action_tx,
+            pause_tx,
+        );
+
+        actor.pause().await.expect("Pause should succeed");
+        let drain_status = actor.drain_status().unwrap();
+        assert!(drain_status.paused && drain_status.drained);
+
+        actor.resume().await.expect("Resume should succeed");
+        assert!(
+            !actor.is_paused(),
+            "Actor should not be paused after resume()"
+        );
+        assert!(!actor.drain_status().unwrap().drained);
+        assert_eq!(pause_rx.recv().await, Some(PauseAction::Pause));
+        assert_eq!(pause_rx.recv().await, Some(PauseAction::Resume));
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_work_of_this_host_only() {
+        let (action_tx, _) = mpsc::channel(1);
+        let (pause_tx, _pause_rx) = mpsc::channel(1);
+
+        let mut pool = memory_pool("test_drain_waits_for_work_of_this_host_only");
+        let in_flight = InFlight::default();
+        let actor = Actor::new(
+            pool.clone(),
+            Ballot::default(),
+            ProofRequestOpt::default(),
+            SupportedChainSpecs::default(),
+            action_tx,
+            pause_tx,
+        )
+        .with_in_flight(in_flight.clone());
+
+        // A request proven by another host sharing the pool
+        let request_key = RequestKey::SingleProof(SingleProofRequestKey::new(
+            ChainId::default(),
+            1,
+            B256::default(),
+            ProofType::default(),
+            "test_prover".to_string(),
+        ));
+        let request_entity = RequestEntity::SingleProof(SingleProofRequestEntity::new(
+            1,
+            1,
+            "test_network".to_string(),
+            "test_l1_network".to_string(),
+            B256::default(),
+            Address::default(),
+            ProofType::default(),
+            BlobProofType::default(),
+            HashMap::new(),
+        ));
+        pool.add(
+            request_key.clone(),
+            request_entity,
+            StatusWithContext::new_registered().transition(Status::WorkInProgress),
+        )
+        .unwrap();
+
+        actor.pause().await.expect("Pause should succeed");
+        let drain_status = actor.drain_status().unwrap();
+        assert_eq!(drain_status.work_in_progress, 0);
+        assert!(drain_status.drained);
+
+        in_flight
+            .lock()
+            .insert(request_key, tokio_util::sync::CancellationToken::new());
+        let drain_status = actor.drain_status().unwrap();
+        assert_eq!(drain_status.work_in_progress, 1);
+        assert!(!drain_status.drained);
+    }
+
     #[tokio::test]
     async fn test_act_sends_action_and_returns_response() {
         let (action_tx, mut action_rx) = mpsc::channel(1);
@@ -245,4 +471,106 @@ mod tests {
         // Wait for the handler to complete
         handle.await.expect("Handler should complete");
     }
+
+    #[tokio::test]
+    async fn test_draw_is_shared_by_hosts_of_the_pool() {
+        // Two hosts sharing the pool, each with its own in-memory ballot
+        let new_host = || {
+            let (action_tx, _) = mpsc::channel(1);
+            let (pause_tx, _) = mpsc::channel(1);
+            Actor::new(
+                memory_pool("test_draw_is_shared_by_hosts_of_the_pool"),
+                Ballot::new(BTreeMap::from([(ProofType::Sp1, (1.0, 1))])).unwrap(),
+                ProofRequestOpt::default(),
+                SupportedChainSpecs::default(),
+                action_tx,
+                pause_tx,
+            )
+        };
+        let (host_a, host_b) = (new_host(), new_host());
+
+        let block_hash = B256::with_last_byte(1);
+        assert_eq!(host_b.recorded_draw(&block_hash, 1), None);
+        assert_eq!(
+            host_a.draw(&block_hash, 1, &BlockMetadata::default()),
+            Some(ProofType::Sp1)
+        );
+        assert_eq!(
+            host_b.recorded_draw(&block_hash, 1),
+            Some(Some(ProofType::Sp1))
+        );
+        assert_eq!(
+            host_b.draw(&block_hash, 1, &BlockMetadata::default()),
+            Some(ProofType::Sp1)
+        );
+
+        // The single draw of the day is taken, whichever host draws, also by the other batches
+        // proposed in the same L1 block
+        assert_eq!(host_b.draw(&block_hash, 2, &BlockMetadata::default()), None);
+        let other_block_hash = B256::with_last_byte(2);
+        assert_eq!(
+            host_b.draw(&other_block_hash, 3, &BlockMetadata::default()),
+            None
+        );
+        assert_eq!(
+            host_a.draw(&other_block_hash, 3, &BlockMetadata::default()),
+            None
+        );
+
+        let history = host_a.ballot_history().unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|draw| (draw.block_hash, draw.request_id, draw.proof_type))
+                .collect::<Vec<_>>(),
+            vec![
+                (other_block_hash, 3, None),
+                (block_hash, 2, None),
+                (block_hash, 1, Some(ProofType::Sp1))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_draw_of_always_rules_is_not_limited() {
+        let (action_tx, _) = mpsc::channel(1);
+        let (pause_tx, _) = mpsc::channel(1);
+        let ballot = Ballot::new(BTreeMap::from([(ProofType::Sp1, (1.0, 1))]))
+            .unwrap()
+            .with_rules(vec![BallotRule {
+                proof_type: ProofType::Sp1,
+                action: RuleAction::Always,
+                when: BlockCondition {
+                    min_batch_size: Some(10),
+                    ..Default::default()
+                },
+            }])
+            .unwrap();
+        let actor = Actor::new(
+            memory_pool("test_draw_of_always_rules_is_not_limited"),
+            ballot,
+            ProofRequestOpt::default(),
+            SupportedChainSpecs::default(),
+            action_tx,
+            pause_tx,
+        );
+        let large_batch = BlockMetadata {
+            batch_size: Some(10),
+            ..Default::default()
+        };
+
+        let block_hash = B256::with_last_byte(1);
+        for batch_id in 1..=3 {
+            assert_eq!(
+                actor.draw(&block_hash, batch_id, &large_batch),
+                Some(ProofType::Sp1)
+            );
+        }
+        // The draws of the always rule leave the per-day limit untouched
+        assert_eq!(
+            actor.draw(&block_hash, 4, &BlockMetadata::default()),
+            Some(ProofType::Sp1)
+        );
+        assert_eq!(actor.draw(&block_hash, 5, &BlockMetadata::default()), None);
+    }
 }
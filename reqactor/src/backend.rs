@@ -1,10 +1,12 @@
 use base64::{engine::general_purpose, Engine as _};
 use bincode;
+use chrono::{DateTime, Utc};
 use raiko_core::{
-    interfaces::{aggregate_proofs, ProofRequest},
+    interfaces::{aggregate_proofs, ProofRequest, RaikoError},
+    locate_diverging_transaction,
     preflight::parse_l1_batch_proposal_tx_for_pacaya_fork,
     provider::rpc::RpcBlockDataProvider,
-    Raiko,
+    HeaderMismatch, Raiko,
 };
 use raiko_lib::{
     consts::SupportedChainSpecs,
@@ -13,20 +15,29 @@ use raiko_lib::{
     utils::{zlib_compress_data, zlib_decompress_data},
 };
 use raiko_reqpool::{
-    AggregationRequestEntity, BatchGuestInputRequestEntity, BatchProofRequestEntity,
+    AggregationRequestEntity, BatchGuestInputRequestEntity, BatchProofRequestEntity, FailureClass,
     GuestInputRequestEntity, RequestEntity, RequestKey, SingleProofRequestEntity, Status,
     StatusWithContext,
 };
 use reth_primitives::B256;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Duration;
 use tokio::sync::{
     mpsc::{self, Receiver, Sender},
-    oneshot, Semaphore,
+    oneshot,
 };
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, trace};
 
-use crate::{Action, Pool};
+use crate::{
+    deadline::wait_until,
+    lease::Leases,
+    retry::{classify_error, classify_failure},
+    scheduler::{Priority, Scheduler, SchedulingClass},
+    work::{is_local_zk, WorkSlot},
+    Action, DeadlinePolicy, PauseAction, Pool, RetryPolicy, SchedulerConfig,
+};
 
 /// Backend runs in the background, and handles the actions from the actor.
 #[derive(Clone)]
@@ -34,10 +45,96 @@ pub(crate) struct Backend {
     pool: Pool,
     chain_specs: SupportedChainSpecs,
     internal_tx: Sender<RequestKey>,
-    proving_semaphore: Arc<Semaphore>,
+    scheduler: Scheduler,
+    retry_policy: Arc<RetryPolicy>,
+    deadline_policy: Arc<DeadlinePolicy>,
+    /// Claims on the requests, so that hosts sharing the pool never prove the same request.
+    leases: Leases,
+    in_flight: InFlight,
+    /// While paused, registered requests are parked instead of started.
+    paused: bool,
+    parked: Vec<RequestKey>,
+    /// Work-in-progress requests re-checked by the safety-net sweep, in case their completion
+    /// signal is lost.
+    watching: HashSet<RequestKey>,
+    /// The registered requests waiting for each unfinished dependency.
+    dependents: HashMap<RequestKey, HashSet<RequestKey>>,
+}
+
+/// The state of the dependencies of a registered request.
+enum Dependencies {
+    /// All of them succeeded, with their proofs in order.
+    Ready(Vec<Proof>),
+    /// Some of them are still unfinished.
+    Waiting(Vec<RequestKey>),
+    /// One of them failed or was cancelled, the request can never be proven.
+    Failed(String),
+}
+
+/// Cancellation tokens of the in-flight proving works.
+///
+/// The lock is held while the result of a work is written to the pool, so that a cancelled
+/// request never gets its status overwritten by the work it cancelled.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InFlight(Arc<Mutex<HashMap<RequestKey, CancellationToken>>>);
+
+impl InFlight {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, HashMap<RequestKey, CancellationToken>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// How many proving works of this host are unfinished.
+    pub(crate) fn len(&self) -> usize {
+        self.lock().len()
+    }
+}
+
+/// Why a proving work failed, kept in the pool as [`Status::Failed`].
+#[derive(Debug, Clone)]
+pub struct ProveFailure {
+    pub error: String,
+    pub class: FailureClass,
+    pub header_mismatch: Option<HeaderMismatch>,
+}
+
+impl ProveFailure {
+    /// A failure caused by `err`, classified from its type.
+    fn caused_by(context: &str, err: RaikoError) -> Self {
+        Self {
+            class: classify_error(&err),
+            error: format!("{context}: {err:?}"),
+            header_mismatch: None,
+        }
+    }
+
+    fn deadline_exceeded(deadline: DateTime<Utc>) -> Self {
+        Self {
+            error: format!("deadline exceeded at {deadline}"),
+            class: FailureClass::DeadlineExceeded,
+            header_mismatch: None,
+        }
+    }
+}
+
+impl From<String> for ProveFailure {
+    fn from(error: String) -> Self {
+        Self {
+            class: classify_failure(&error),
+            error,
+            header_mismatch: None,
+        }
+    }
+}
+
+impl From<ProveFailure> for Status {
+    fn from(failure: ProveFailure) -> Self {
+        Status::Failed {
+            error: failure.error,
+            header_mismatch: failure.header_mismatch,
+        }
+    }
 }
 
-// TODO: load pool and notify internal channel
 impl Backend {
     /// Run the backend in background.
     ///
@@ -46,37 +143,67 @@ impl Backend {
     pub async fn serve_in_background(
         pool: Pool,
         chain_specs: SupportedChainSpecs,
-        pause_rx: Receiver<()>,
+        pause_rx: Receiver<PauseAction>,
         action_rx: Receiver<(Action, oneshot::Sender<Result<StatusWithContext, String>>)>,
-        max_proving_concurrency: usize,
+        scheduler_config: SchedulerConfig,
+        retry_policy: RetryPolicy,
+        deadline_policy: DeadlinePolicy,
+        in_flight: InFlight,
     ) {
         let channel_size = std::env::var("INTERNAL_CHANNEL_SIZE")
             .unwrap_or("1024".to_string())
             .parse::<usize>()
             .unwrap_or(1024);
+        let sweep_interval = std::env::var("INTERNAL_SWEEP_INTERVAL_SECS")
+            .unwrap_or("60".to_string())
+            .parse::<u64>()
+            .unwrap_or(60);
+        let leases = Leases::from_env().expect("Failed to configure the request leases");
         let (internal_tx, internal_rx) = mpsc::channel::<RequestKey>(channel_size);
         tokio::spawn(async move {
-            Backend {
+            let mut backend = Backend {
                 pool,
                 chain_specs,
                 internal_tx,
-                proving_semaphore: Arc::new(Semaphore::new(max_proving_concurrency)),
-            }
-            .serve(action_rx, internal_rx, pause_rx)
-            .await;
+                scheduler: Scheduler::new(scheduler_config),
+                retry_policy: Arc::new(retry_policy),
+                deadline_policy: Arc::new(deadline_policy),
+                leases,
+                in_flight,
+                paused: false,
+                parked: Vec::new(),
+                watching: HashSet::new(),
+                dependents: HashMap::new(),
+            };
+            backend.recover().await;
+            backend
+                .serve(
+                    action_rx,
+                    internal_rx,
+                    pause_rx,
+                    Duration::from_secs(sweep_interval),
+                )
+                .await;
         });
     }
 
     // There are three incoming channels:
     // 1. action_rx: actions from the external Actor
-    // 2. internal_rx: internal signals from the backend itself
-    // 3. pause_rx: pause signal from the external Actor
+    // 2. internal_rx: internal signals from the backend itself, including the completion
+    //    signals of the proving works
+    // 3. pause_rx: pause and resume signals from the external Actor
+    //
+    // The work-in-progress requests are also swept every `sweep_interval`, as a safety net for
+    // lost completion signals.
     async fn serve(
         mut self,
         mut action_rx: Receiver<(Action, oneshot::Sender<Result<StatusWithContext, String>>)>,
         mut internal_rx: Receiver<RequestKey>,
-        mut pause_rx: Receiver<()>,
+        mut pause_rx: Receiver<PauseAction>,
+        sweep_interval: Duration,
     ) {
+        let mut sweep =
+            tokio::time::interval_at(tokio::time::Instant::now() + sweep_interval, sweep_interval);
         loop {
             tokio::select! {
                 Some((action, resp_tx)) = action_rx.recv() => {
@@ -96,10 +223,23 @@ impl Backend {
                 Some(request_key) = internal_rx.recv() => {
                     self.handle_internal_signal(request_key.clone()).await;
                 }
-                Some(()) = pause_rx.recv() => {
-                    tracing::info!("Actor Backend received pause-signal, halting");
-                    if let Err(err) = self.halt().await {
-                        tracing::error!("Actor Backend failed to halt: {err:?}");
+                Some(pause_action) = pause_rx.recv() => match pause_action {
+                    PauseAction::Pause => {
+                        tracing::info!("Actor Backend received pause-signal, halting");
+                        if let Err(err) = self.halt().await {
+                            tracing::error!("Actor Backend failed to halt: {err:?}");
+                        }
+                    }
+                    PauseAction::Resume => {
+                        tracing::info!("Actor Backend received resume-signal, resuming");
+                        self.resume().await;
+                    }
+                },
+                _ = sweep.tick() => {
+                    let watching = std::mem::take(&mut self.watching);
+                    tracing::debug!("Actor Backend sweeps {} work-in-progress requests", watching.len());
+                    for request_key in watching {
+                        self.ensure_internal_signal(request_key).await;
                     }
                 }
                 else => {
@@ -116,13 +256,18 @@ impl Backend {
         action: Action,
     ) -> Result<StatusWithContext, String> {
         match action {
+            Action::Prove { request_key, .. } if self.paused => {
+                tracing::warn!("Actor Backend received prove-action {request_key}, but it is paused, rejecting");
+                Err("System is paused".to_string())
+            }
             Action::Prove {
                 request_key,
                 request_entity,
             } => match self.pool.get_status(&request_key) {
                 Ok(None) => {
                     tracing::debug!("Actor Backend received prove-action {request_key}, and it is not in pool, registering");
-                    self.register(request_key.clone(), request_entity).await
+                    self.register_if_absent(request_key.clone(), request_entity)
+                        .await
                 }
                 Ok(Some(status)) => match status.status() {
                     Status::Registered | Status::WorkInProgress | Status::Success { .. } => {
@@ -171,55 +316,144 @@ impl Backend {
         }
     }
 
+    // Pick up the requests left unfinished by a previous run, e.g. after a crash or a deploy.
+    //
+    // No proving work survives a restart, so orphaned `WorkInProgress` requests are reset to
+    // `Registered`, and every `Registered` request is signalled to be started again. The remote
+    // proof ids stored in the pool are kept, so provers can still look them up. The requests
+    // leased by another host sharing the pool are only watched, to be taken over once their
+    // lease expires.
+    async fn recover(&mut self) {
+        let statuses = match self.pool.list() {
+            Ok(statuses) => statuses,
+            Err(err) => {
+                tracing::error!("Actor Backend failed to list the pool for recovery: {err:?}");
+                return;
+            }
+        };
+
+        let mut recovered = 0;
+        for (request_key, status) in statuses {
+            match status.status() {
+                Status::Registered => {}
+                Status::WorkInProgress if self.is_leased_elsewhere(&request_key) => {
+                    tracing::info!("Actor Backend found work-in-progress request {request_key} leased by another host, watching it");
+                    self.watching.insert(request_key);
+                    continue;
+                }
+                Status::WorkInProgress => {
+                    tracing::warn!("Actor Backend found orphaned work-in-progress request {request_key}, re-registering");
+                    if let Err(err) = self
+                        .pool
+                        .update_status(request_key.clone(), status.transition(Status::Registered))
+                    {
+                        tracing::error!(
+                            "Actor Backend failed to re-register {request_key}: {err:?}"
+                        );
+                        continue;
+                    }
+                }
+                Status::Success { .. } | Status::Cancelled { .. } | Status::Failed { .. } => {
+                    continue
+                }
+            }
+            self.ensure_internal_signal(request_key).await;
+            recovered += 1;
+        }
+        tracing::info!("Actor Backend recovered {recovered} unfinished requests from the pool");
+    }
+
     // Check the request status and then move on to the next step accordingly.
     async fn handle_internal_signal(&mut self, request_key: RequestKey) {
         match self.pool.get(&request_key) {
             Ok(Some((request_entity, status))) => match status.status() {
-                Status::Registered => match request_entity {
-                    RequestEntity::SingleProof(entity) => {
-                        tracing::debug!("Actor Backend received internal signal {request_key}, status: {status}, proving single proof");
-                        self.prove_single(request_key.clone(), entity).await;
-                        self.ensure_internal_signal(request_key).await;
-                    }
-                    RequestEntity::Aggregation(entity) => {
-                        tracing::debug!("Actor Backend received internal signal {request_key}, status: {status}, proving aggregation proof");
-                        self.prove_aggregation(request_key.clone(), entity).await;
-                        self.ensure_internal_signal(request_key).await;
-                    }
-                    RequestEntity::BatchProof(entity) => {
-                        tracing::debug!("Actor Backend received internal signal {request_key}, status: {status}, proving batch proof");
-                        self.prove_batch(request_key.clone(), entity).await;
-                        self.ensure_internal_signal(request_key).await;
-                    }
-                    RequestEntity::GuestInput(entity) => {
-                        tracing::debug!("Actor Backend received internal signal {request_key}, status: {status}, proving single proof");
-                        self.generate_guest_input(request_key.clone(), entity).await;
-                        self.ensure_internal_signal(request_key).await;
+                Status::Registered if self.paused => {
+                    tracing::debug!("Actor Backend received internal signal {request_key}, status: {status}, parking until resumed");
+                    if !self.parked.contains(&request_key) {
+                        self.parked.push(request_key);
                     }
-                    RequestEntity::BatchGuestInput(entity) => {
-                        tracing::debug!("Actor Backend received internal signal {request_key}, status: {status}, proving single proof");
-                        self.generate_batch_guest_input(request_key.clone(), entity)
-                            .await;
-                        self.ensure_internal_signal(request_key).await;
+                }
+                Status::Registered if self.is_leased_elsewhere(&request_key) => {
+                    // Started by another host, the sweep checks it again in case that host
+                    // goes away before starting it
+                    tracing::debug!("Actor Backend received internal signal {request_key}, status: {status}, but another host claimed it, watching it");
+                    self.watching.insert(request_key);
+                }
+                Status::Registered => {
+                    let dependency_proofs = match self.check_dependencies(&request_entity) {
+                        Dependencies::Ready(proofs) => proofs,
+                        Dependencies::Waiting(dependencies) => {
+                            // Woken by the completion of the dependencies, the sweep checks
+                            // them again in case a completion is missed
+                            tracing::debug!("Actor Backend received internal signal {request_key}, status: {status}, waiting for {} dependencies", dependencies.len());
+                            for dependency in dependencies {
+                                self.dependents
+                                    .entry(dependency)
+                                    .or_default()
+                                    .insert(request_key.clone());
+                            }
+                            self.watching.insert(request_key);
+                            return;
+                        }
+                        Dependencies::Failed(error) => {
+                            tracing::warn!("Actor Backend received internal signal {request_key}, but {error}, failing");
+                            let failed_status = status
+                                .transition(Status::Failed {
+                                    error,
+                                    header_mismatch: None,
+                                })
+                                .with_last_failure(FailureClass::DependencyFailed);
+                            if let Err(err) =
+                                self.pool.update_status(request_key.clone(), failed_status)
+                            {
+                                tracing::error!("Actor Backend failed to update status of {request_key}: {err:?}");
+                            }
+                            self.ensure_internal_signal(request_key).await;
+                            return;
+                        }
+                    };
+                    self.start(request_key, request_entity, dependency_proofs)
+                        .await;
+                }
+                Status::WorkInProgress
+                    if !self.in_flight.lock().contains_key(&request_key)
+                        && !self.is_leased_elsewhere(&request_key) =>
+                {
+                    // Nobody works on it anymore, e.g. its host crashed and its lease expired
+                    tracing::warn!("Actor Backend found orphaned work-in-progress request {request_key}, taking it over");
+                    if let Err(err) = self
+                        .pool
+                        .update_status(request_key.clone(), status.transition(Status::Registered))
+                    {
+                        tracing::error!(
+                            "Actor Backend failed to re-register {request_key}: {err:?}"
+                        );
+                        self.watching.insert(request_key);
+                        return;
                     }
-                },
+                    self.ensure_internal_signal(request_key).await;
+                }
                 Status::WorkInProgress => {
-                    // Wait for proving completion
+                    // Wait for the completion signal of the proving work, the sweep checks it
+                    // again in case the signal is lost
                     tracing::debug!(
                         "Actor Backend checks a work-in-progress request {request_key}, elapsed: {elapsed:?}",
                         elapsed = chrono::Utc::now() - status.timestamp(),
                     );
-                    self.ensure_internal_signal_after(request_key, Duration::from_secs(3))
-                        .await;
+                    self.watching.insert(request_key);
                 }
                 Status::Success { .. } | Status::Cancelled { .. } | Status::Failed { .. } => {
                     tracing::debug!("Actor Backend received internal signal {request_key}, status: {status}, done");
+                    self.watching.remove(&request_key);
+                    self.wake_dependents(&request_key).await;
                 }
             },
             Ok(None) => {
                 tracing::warn!(
                     "Actor Backend received internal signal {request_key}, but it is not in pool, skipping"
                 );
+                self.watching.remove(&request_key);
+                self.wake_dependents(&request_key).await;
             }
             Err(err) => {
                 // Fault tolerance: re-enqueue the internal signal after 3 seconds
@@ -257,6 +491,134 @@ impl Backend {
         self.ensure_internal_signal(request_key).await
     }
 
+    // Start the proving work of a registered request, whose dependencies all succeeded.
+    async fn start(
+        &mut self,
+        request_key: RequestKey,
+        request_entity: RequestEntity,
+        dependency_proofs: Vec<Proof>,
+    ) {
+        match request_entity {
+            RequestEntity::SingleProof(entity) => {
+                tracing::debug!("Actor Backend starts {request_key}, proving single proof");
+                self.prove_single(request_key.clone(), entity).await;
+            }
+            RequestEntity::Aggregation(entity) => {
+                tracing::debug!("Actor Backend starts {request_key}, proving aggregation proof");
+                // The proofs of the sub-requests are read from the pool by key
+                let entity = if dependency_proofs.is_empty() {
+                    entity
+                } else {
+                    entity.with_proofs(dependency_proofs)
+                };
+                self.prove_aggregation(request_key.clone(), entity).await;
+            }
+            RequestEntity::BatchProof(entity) => {
+                tracing::debug!("Actor Backend starts {request_key}, proving batch proof");
+                // The guest input generated by the input request it depends on, if any
+                let batch_guest_input = dependency_proofs
+                    .into_iter()
+                    .next()
+                    .and_then(|proof| proof.proof);
+                self.prove_batch(request_key.clone(), entity, batch_guest_input)
+                    .await;
+            }
+            RequestEntity::GuestInput(entity) => {
+                tracing::debug!("Actor Backend starts {request_key}, generating guest input");
+                self.generate_guest_input(request_key.clone(), entity).await;
+            }
+            RequestEntity::BatchGuestInput(entity) => {
+                tracing::debug!("Actor Backend starts {request_key}, generating batch guest input");
+                self.generate_batch_guest_input(request_key.clone(), entity)
+                    .await;
+            }
+        }
+        self.ensure_internal_signal(request_key).await;
+    }
+
+    // Check the dependencies of a registered request in the pool.
+    fn check_dependencies(&mut self, request_entity: &RequestEntity) -> Dependencies {
+        let mut proofs = Vec::with_capacity(request_entity.dependencies().len());
+        let mut waiting = Vec::new();
+        for dependency in request_entity.dependencies() {
+            match self.pool.get_status(dependency) {
+                Ok(Some(status)) => match status.into_status() {
+                    Status::Success { proof } => proofs.push(proof),
+                    Status::Registered | Status::WorkInProgress => waiting.push(dependency.clone()),
+                    status @ (Status::Cancelled | Status::Failed { .. }) => {
+                        return Dependencies::Failed(format!("dependency {dependency} is {status}"))
+                    }
+                },
+                Ok(None) => {
+                    return Dependencies::Failed(format!("dependency {dependency} is not in pool"))
+                }
+                Err(err) => {
+                    tracing::warn!("Actor Backend failed to get status of dependency {dependency}: {err:?}, checking it again later");
+                    waiting.push(dependency.clone());
+                }
+            }
+        }
+        if waiting.is_empty() {
+            Dependencies::Ready(proofs)
+        } else {
+            Dependencies::Waiting(waiting)
+        }
+    }
+
+    // Signal the requests waiting for a finished request, to start or fail them.
+    async fn wake_dependents(&mut self, request_key: &RequestKey) {
+        let Some(dependents) = self.dependents.remove(request_key) else {
+            return;
+        };
+        for dependent in dependents {
+            tracing::debug!(
+                "Actor Backend wakes {dependent}, its dependency {request_key} is done"
+            );
+            self.ensure_internal_signal(dependent).await;
+        }
+    }
+
+    // Whether another host holds an unexpired lease on the request. A lease of this host left
+    // by a previous run, or that cannot be read, counts as not held elsewhere, as the claim
+    // before starting the work is the one that matters.
+    fn is_leased_elsewhere(&mut self, request_key: &RequestKey) -> bool {
+        match self.pool.get_lease(request_key) {
+            Ok(Some(lease)) => lease.holder != self.leases.holder(),
+            Ok(None) => false,
+            Err(err) => {
+                tracing::warn!("Actor Backend failed to get the lease of {request_key}: {err:?}");
+                false
+            }
+        }
+    }
+
+    fn release_lease(&mut self, request_key: &RequestKey) {
+        if let Err(err) = self.pool.release_lease(request_key, self.leases.holder()) {
+            // It expires on its own anyway
+            tracing::warn!("Actor Backend failed to release the lease of {request_key}: {err:?}");
+        }
+    }
+
+    // Register a new request to the pool, unless another host sharing the pool registered it
+    // first.
+    async fn register_if_absent(
+        &mut self,
+        request_key: RequestKey,
+        request_entity: RequestEntity,
+    ) -> Result<StatusWithContext, String> {
+        let status = StatusWithContext::new_registered();
+        if self
+            .pool
+            .try_add(request_key.clone(), request_entity, status.clone())?
+        {
+            return Ok(status);
+        }
+        tracing::debug!("Actor Backend registers {request_key}, but it is registered already");
+        self.pool
+            .get_status(&request_key)?
+            .ok_or_else(|| "request is not in pool".to_string())
+    }
+
     // Register a new request to the pool and notify the actor.
     async fn register(
         &mut self,
@@ -295,60 +657,49 @@ impl Backend {
         }
 
         // Case: old_status is work-in-progress:
-        // 1. Cancel the proving work by the cancel token // TODO: cancel token
+        // 1. Cancel the proving work by the cancel token, which releases its permit
         // 2. Remove the proof id from the pool
         // 3. Mark the request as cancelled in the pool
-        match &request_key {
-            RequestKey::GuestInput(..) => {
-                let status = StatusWithContext::new_cancelled();
-                self.pool.update_status(request_key, status.clone())?;
-                Ok(status)
-            }
-            RequestKey::SingleProof(key) => {
-                raiko_core::interfaces::cancel_proof(
-                    key.proof_type().clone(),
-                    (
-                        key.chain_id().clone(),
-                        key.block_number().clone(),
-                        key.block_hash().clone(),
-                        *key.proof_type() as u8,
-                    ),
-                    Box::new(&mut self.pool),
-                )
-                .await
-                .or_else(|e| {
-                    if e.to_string().contains("No data for query") {
-                        tracing::warn!("Actor Backend received cancel-action {request_key}, but it is already cancelled or not yet started, skipping");
-                        Ok(())
-                    } else {
-                        tracing::error!(
-                            "Actor Backend received cancel-action {request_key}, but failed to cancel proof: {e:?}"
-                        );
-                        Err(format!("failed to cancel proof: {e:?}"))
-                    }
-                })?;
+        if let Some(cancel_token) = self.in_flight.lock().remove(&request_key) {
+            cancel_token.cancel();
+        }
+        self.cancel_remote(&request_key).await?;
 
-                // 3. Mark the request as cancelled in the pool
-                let status = StatusWithContext::new_cancelled();
-                self.pool.update_status(request_key, status.clone())?;
-                Ok(status)
-            }
-            RequestKey::Aggregation(..) => {
-                let status = StatusWithContext::new_cancelled();
-                self.pool.update_status(request_key, status.clone())?;
-                Ok(status)
-            }
-            RequestKey::BatchProof(..) => {
-                let status = StatusWithContext::new_cancelled();
-                self.pool.update_status(request_key, status.clone())?;
-                Ok(status)
-            }
-            RequestKey::BatchGuestInput(..) => {
-                let status = StatusWithContext::new_cancelled();
-                self.pool.update_status(request_key, status.clone())?;
-                Ok(status)
-            }
+        // The work of another host notices the cancellation when renewing its lease
+        let status = StatusWithContext::new_cancelled();
+        self.pool
+            .update_status(request_key.clone(), status.clone())?;
+        self.release_lease(&request_key);
+        Ok(status)
+    }
+
+    // Cancel the proof requested to a remote prover, if any, and remove its proof id.
+    async fn cancel_remote(&mut self, request_key: &RequestKey) -> Result<(), String> {
+        if let RequestKey::SingleProof(key) = request_key {
+            raiko_core::interfaces::cancel_proof(
+                key.proof_type().clone(),
+                (
+                    key.chain_id().clone(),
+                    key.block_number().clone(),
+                    key.block_hash().clone(),
+                    *key.proof_type() as u8,
+                ),
+                Box::new(&mut self.pool),
+            )
+            .await
+            .or_else(|e| {
+                if e.to_string().contains("No data for query") {
+                    tracing::warn!("Actor Backend received cancel-action {request_key}, but it is already cancelled or not yet started, skipping");
+                    Ok(())
+                } else {
+                    tracing::error!(
+                        "Actor Backend received cancel-action {request_key}, but failed to cancel proof: {e:?}"
+                    );
+                    Err(format!("failed to cancel proof: {e:?}"))
+                }
+            })?;
         }
+        Ok(())
     }
 
     async fn generate_guest_input(
@@ -356,15 +707,18 @@ impl Backend {
         request_key: RequestKey,
         request_entity: GuestInputRequestEntity,
     ) {
-        self.prove(request_key.clone(), |mut actor, request_key| async move {
-            do_generate_guest_input(
-                &mut actor.pool,
-                &actor.chain_specs,
-                request_key,
-                request_entity,
-            )
-            .await
-        })
+        self.prove(
+            request_key.clone(),
+            |mut actor, request_key, _| async move {
+                do_generate_guest_input(
+                    &mut actor.pool,
+                    &actor.chain_specs,
+                    request_key,
+                    request_entity,
+                )
+                .await
+            },
+        )
         .await;
     }
 
@@ -373,15 +727,18 @@ impl Backend {
         request_key: RequestKey,
         request_entity: BatchGuestInputRequestEntity,
     ) {
-        self.prove(request_key.clone(), |mut actor, request_key| async move {
-            do_generate_batch_guest_input(
-                &mut actor.pool,
-                &actor.chain_specs,
-                request_key,
-                request_entity,
-            )
-            .await
-        })
+        self.prove(
+            request_key.clone(),
+            |mut actor, request_key, _| async move {
+                do_generate_batch_guest_input(
+                    &mut actor.pool,
+                    &actor.chain_specs,
+                    request_key,
+                    request_entity,
+                )
+                .await
+            },
+        )
         .await;
     }
 
@@ -390,15 +747,19 @@ impl Backend {
         request_key: RequestKey,
         request_entity: SingleProofRequestEntity,
     ) {
-        self.prove(request_key.clone(), |mut actor, request_key| async move {
-            do_prove_single(
-                &mut actor.pool,
-                &actor.chain_specs,
-                request_key,
-                request_entity,
-            )
-            .await
-        })
+        self.prove(
+            request_key.clone(),
+            |mut actor, request_key, slot| async move {
+                do_prove_single(
+                    &mut actor.pool,
+                    &actor.chain_specs,
+                    request_key,
+                    request_entity,
+                    &slot,
+                )
+                .await
+            },
+        )
         .await;
     }
 
@@ -407,9 +768,12 @@ impl Backend {
         request_key: RequestKey,
         request_entity: AggregationRequestEntity,
     ) {
-        self.prove(request_key.clone(), |mut actor, request_key| async move {
-            do_prove_aggregation(&mut actor.pool, request_key.clone(), request_entity).await
-        })
+        self.prove(
+            request_key.clone(),
+            |mut actor, request_key, _| async move {
+                do_prove_aggregation(&mut actor.pool, request_key.clone(), request_entity).await
+            },
+        )
         .await;
     }
 
@@ -417,125 +781,340 @@ impl Backend {
         &mut self,
         request_key: RequestKey,
         request_entity: BatchProofRequestEntity,
+        batch_guest_input: Option<String>,
     ) {
-        self.prove(request_key.clone(), |mut actor, request_key| async move {
-            do_prove_batch(
-                &mut actor.pool,
-                &actor.chain_specs,
-                request_key.clone(),
-                request_entity,
-            )
-            .await
-        })
+        self.prove(
+            request_key.clone(),
+            |mut actor, request_key, slot| async move {
+                do_prove_batch(
+                    &mut actor.pool,
+                    &actor.chain_specs,
+                    request_key.clone(),
+                    request_entity,
+                    batch_guest_input,
+                    &slot,
+                )
+                .await
+            },
+        )
         .await;
     }
 
     /// Generic method to handle proving for different types of proofs
     async fn prove<F, Fut>(&mut self, request_key: RequestKey, prove_fn: F)
     where
-        F: FnOnce(Backend, RequestKey) -> Fut + Send + 'static,
-        Fut: std::future::Future<Output = Result<Proof, String>> + Send + 'static,
+        F: FnOnce(Backend, RequestKey, WorkSlot) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = Result<Proof, ProveFailure>> + Send + 'static,
     {
         let request_key_ = request_key.clone();
 
-        let pool_status = self
-            .pool
-            .get_status(&request_key)
-            .unwrap()
-            .unwrap()
-            .into_status();
-        if matches!(pool_status, Status::Success { .. } | Status::WorkInProgress) {
+        let (request_entity, pool_status) = self.pool.get(&request_key).unwrap().unwrap();
+        if matches!(
+            pool_status.status(),
+            Status::Success { .. } | Status::WorkInProgress
+        ) {
             tracing::warn!("Actor Backend received prove-action {request_key}, but it is not registered, skipping");
             return;
         }
 
-        // 1. Update the request status in pool to WorkInProgress
+        // Overdue requests are failed without being started
+        let deadline = self.deadline_policy.deadline(&request_entity, Utc::now());
+        if let Some(deadline) = deadline.filter(|deadline| *deadline <= Utc::now()) {
+            tracing::warn!("Actor Backend received prove-action {request_key}, but its deadline {deadline} is over, failing");
+            let status = pool_status
+                .transition(ProveFailure::deadline_exceeded(deadline).into())
+                .with_last_failure(FailureClass::DeadlineExceeded);
+            if let Err(err) = self.pool.update_status(request_key.clone(), status) {
+                tracing::error!(
+                    "Actor Backend failed to update status of overdue {request_key}: {err:?}"
+                );
+            }
+            return;
+        }
+
+        // 1. Claim the request, so that no other host sharing the pool works on it, and update
+        // the request status in pool to WorkInProgress, counting the attempt
+        match self
+            .pool
+            .try_claim(&request_key, self.leases.holder(), self.leases.ttl())
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::debug!("Actor Backend received prove-action {request_key}, but another host claimed it, watching it");
+                self.watching.insert(request_key);
+                return;
+            }
+            Err(err) => {
+                tracing::error!("Actor Backend failed to claim {request_key}: {err:?}");
+                return;
+            }
+        }
+        // Another host may have proven or cancelled the request before the claim
+        let pool_status = match self.pool.get_status(&request_key) {
+            Ok(Some(status)) if matches!(status.status(), Status::Registered) => status,
+            Ok(status) => {
+                tracing::warn!(
+                    "Actor Backend claimed {request_key}, but it is no longer registered, skipping. status: {status:?}"
+                );
+                self.release_lease(&request_key);
+                return;
+            }
+            Err(err) => {
+                tracing::error!("Actor Backend failed to get status of {request_key}: {err:?}");
+                self.release_lease(&request_key);
+                return;
+            }
+        };
+        let started_status = pool_status
+            .transition(Status::WorkInProgress)
+            .with_attempts(pool_status.attempts() + 1);
         if let Err(err) = self
             .pool
-            .update_status(request_key.clone(), Status::WorkInProgress.into())
+            .update_status(request_key.clone(), started_status.clone())
         {
             tracing::error!(
                 "Actor Backend failed to update status of prove-action {request_key}: {err:?}, status: {status}",
                 status = Status::WorkInProgress,
             );
+            self.release_lease(&request_key);
             return;
         }
 
-        // 2. Start the proving work in a separate thread
+        // 2. Start the proving work in a separate thread once its scheduling class has a free
+        // slot, cancellable through the in-flight tokens
         let mut actor = self.clone();
-        let proving_semaphore = self.proving_semaphore.clone();
-        let (semaphore_acquired_tx, semaphore_acquired_rx) = oneshot::channel();
+        let scheduler = self.scheduler.clone();
+        let class = SchedulingClass::of(&request_entity);
+        let priority = Priority::of(&request_entity, Utc::now());
+        let cancel_token = CancellationToken::new();
+        self.in_flight
+            .lock()
+            .insert(request_key.clone(), cancel_token.clone());
+        let cancel_token_ = cancel_token.clone();
+        let started_status_ = started_status.clone();
 
         let handle = tokio::spawn(async move {
-            // Acquire a permit from the semaphore before starting the proving work
-            let _permit = proving_semaphore
-                .acquire()
-                .await
-                .expect("semaphore should not be closed");
-            semaphore_acquired_tx.send(()).unwrap();
-
-            // 2.1. Start the proving work
-            let proven_status = prove_fn(actor.clone(), request_key.clone())
-                .await
-                .map(|proof| Status::Success { proof })
-                .unwrap_or_else(|error| Status::Failed { error });
+            // 2.1. Wait for a slot and start the proving work, dropping it as soon as the request
+            // is cancelled, overdue or no longer leased to this host. What the work runs off the
+            // async runtime is stopped along with it, and keeps the slot until it exits.
+            let leases = actor.leases.clone();
+            let work_stopped = CancellationToken::new();
+            let stop_work = work_stopped.clone().drop_guard();
+            let result = tokio::select! {
+                result = async {
+                    let permit = scheduler.acquire(class, priority).await;
+                    tracing::debug!("Actor Backend starts proving {request_key} as {class}, priority: {priority:?}");
+                    let slot = WorkSlot::new(work_stopped.clone(), permit);
+                    prove_fn(actor.clone(), request_key.clone(), slot.clone()).await
+                } => result,
+                _ = cancel_token.cancelled() => {
+                    tracing::info!("Actor Backend cancelled the proving work of {request_key}");
+                    return;
+                }
+                _ = leases.hold(actor.pool.clone(), request_key.clone()) => {
+                    tracing::warn!("Actor Backend drops the proving work of {request_key}, it is no longer leased to this host");
+                    actor.drop_work(&request_key, &cancel_token);
+                    return;
+                }
+                _ = wait_until(deadline) => {
+                    tracing::warn!("Actor Backend cancels the proving work of {request_key}, its deadline is over");
+                    if let Err(err) = actor.cancel_remote(&request_key).await {
+                        tracing::warn!("Actor Backend failed to cancel the remote proof of {request_key}: {err}");
+                    }
+                    Err(ProveFailure::deadline_exceeded(deadline.expect("only waited with a deadline")))
+                }
+            };
+            drop(stop_work);
 
-            match &proven_status {
-                Status::Success { proof } => {
+            // A retried request stays in progress until it is registered again
+            let (proven_status, retry_after) = match result {
+                Ok(proof) => {
                     tracing::info!(
                         "Actor Backend successfully proved {request_key}. Proof: {proof}"
                     );
+                    (started_status.transition(Status::Success { proof }), None)
                 }
-                Status::Failed { error } => {
-                    tracing::error!("Actor Backend failed to prove {request_key}: {error}");
-                }
-                _ => {}
-            }
+                Err(failure) => actor.failed_status(&request_key, &started_status, failure),
+            };
 
-            // 2.2. Update the request status in pool to the resulted status
-            if let Err(err) = actor
+            // 2.2. Update the request status in pool to the resulted status, unless the request
+            // was cancelled or taken over by another host in the meantime
+            if let Ok(false) = actor
                 .pool
-                .update_status(request_key.clone(), proven_status.clone().into())
+                .renew_lease(&request_key, leases.holder(), leases.ttl())
             {
-                tracing::error!(
-                    "Actor Backend failed to update status of prove-action {request_key}: {err:?}, status: {proven_status}"
-                );
+                tracing::warn!("Actor Backend discards the result of {request_key}, it is no longer leased to this host");
+                actor.drop_work(&request_key, &cancel_token);
                 return;
             }
-            // The permit is automatically dropped here, releasing the semaphore
+            // Upload a large proof before taking the lock, which only guards the status write. A
+            // proof which cannot be stored fails the work like any proving failure.
+            let (proven_status, retry_after) = match actor.pool.offload(proven_status) {
+                Ok(proven_status) => (proven_status, retry_after),
+                Err(err) => actor.failed_status(
+                    &request_key,
+                    &started_status,
+                    ProveFailure {
+                        error: format!("failed to store the proof: {err}"),
+                        class: FailureClass::Other,
+                        header_mismatch: None,
+                    },
+                ),
+            };
+            {
+                let mut in_flight = actor.in_flight.lock();
+                if cancel_token.is_cancelled() {
+                    tracing::info!("Actor Backend discards the result of cancelled {request_key}");
+                    return;
+                }
+                if retry_after.is_none() {
+                    in_flight.remove(&request_key);
+                }
+                if let Err(err) = actor
+                    .pool
+                    .update_status(request_key.clone(), proven_status.clone())
+                {
+                    tracing::error!(
+                        "Actor Backend failed to update status of prove-action {request_key}: {err:?}, status: {proven_status}"
+                    );
+                    return;
+                }
+            }
+
+            // 2.3. Notify the backend of the completion, or register the request again after
+            // the backoff, unless it is cancelled or taken over first
+            let Some(retry_after) = retry_after else {
+                actor.release_lease(&request_key);
+                actor.ensure_internal_signal(request_key).await;
+                return;
+            };
+            tracing::info!("Actor Backend retries {request_key} in {retry_after:?}");
+            tokio::select! {
+                _ = tokio::time::sleep(retry_after) => {}
+                _ = cancel_token.cancelled() => return,
+                _ = leases.hold(actor.pool.clone(), request_key.clone()) => {
+                    actor.drop_work(&request_key, &cancel_token);
+                    return;
+                }
+            }
+            {
+                let mut in_flight = actor.in_flight.lock();
+                if cancel_token.is_cancelled() {
+                    return;
+                }
+                in_flight.remove(&request_key);
+                if let Err(err) = actor.pool.update_status(
+                    request_key.clone(),
+                    proven_status.transition(Status::Registered),
+                ) {
+                    tracing::error!(
+                        "Actor Backend failed to register {request_key} again: {err:?}"
+                    );
+                    return;
+                }
+            }
+            actor.release_lease(&request_key);
+            actor.ensure_internal_signal(request_key).await;
         });
 
         // Only set up panic handler if we have a backup request key (for single proofs)
-        let mut pool_ = self.pool.clone();
+        let mut actor_ = self.clone();
         tokio::spawn(async move {
             if let Err(e) = handle.await {
                 if e.is_panic() {
                     tracing::error!("Actor Backend panicked while proving: {e:?}");
-                    let status = Status::Failed {
-                        error: e.to_string(),
-                    };
-                    if let Err(err) =
-                        pool_.update_status(request_key_.clone(), status.clone().into())
                     {
-                        tracing::error!(
+                        let mut tokens = actor_.in_flight.lock();
+                        if cancel_token_.is_cancelled() {
+                            return;
+                        }
+                        tokens.remove(&request_key_);
+                        let status = started_status_
+                            .transition(Status::Failed {
+                                error: e.to_string(),
+                                header_mismatch: None,
+                            })
+                            .with_last_failure(FailureClass::Other);
+                        if let Err(err) = actor_
+                            .pool
+                            .update_status(request_key_.clone(), status.clone())
+                        {
+                            tracing::error!(
                                 "Actor Backend failed to update status of prove-action {request_key_}: {err:?}, status: {status}",
                                 status = status,
                             );
+                            return;
+                        }
                     }
+                    actor_.release_lease(&request_key_);
+                    actor_.ensure_internal_signal(request_key_).await;
                 } else {
                     tracing::error!("Actor Backend failed to prove: {e:?}");
                 }
             }
         });
+    }
 
-        // Wait for the semaphore to be acquired
-        semaphore_acquired_rx.await.unwrap();
+    // Forget the work of a request no longer leased to this host, leaving its status to the
+    // host which took it over.
+    fn drop_work(&mut self, request_key: &RequestKey, cancel_token: &CancellationToken) {
+        let mut in_flight = self.in_flight.lock();
+        if !cancel_token.is_cancelled() {
+            in_flight.remove(request_key);
+            cancel_token.cancel();
+        }
+        drop(in_flight);
+        self.release_lease(request_key);
     }
 
+    // The status of a failed proving work, still in progress when it is retried after the
+    // returned backoff.
+    fn failed_status(
+        &self,
+        request_key: &RequestKey,
+        started_status: &StatusWithContext,
+        failure: ProveFailure,
+    ) -> (StatusWithContext, Option<Duration>) {
+        let attempts = *started_status.attempts();
+        let class = failure.class;
+        tracing::error!(
+            "Actor Backend failed to prove {request_key} at attempt {attempts}, {class:?}: {error}",
+            error = failure.error,
+        );
+        let retry_after = self.retry_policy.retry_after(class, attempts);
+        let status = match retry_after {
+            Some(_) => Status::WorkInProgress,
+            None => failure.into(),
+        };
+        (
+            started_status.transition(status).with_last_failure(class),
+            retry_after,
+        )
+    }
+
+    // Stop starting registered requests. The in-flight works are left to finish, so that
+    // pausing never throws away proving progress.
     async fn halt(&mut self) -> Result<(), String> {
-        // TODO: implement halt for pause
+        self.paused = true;
+        tracing::info!(
+            "Actor Backend paused, draining {} in-flight works",
+            self.in_flight.lock().len()
+        );
         Ok(())
     }
+
+    // Start the registered requests parked while paused.
+    async fn resume(&mut self) {
+        self.paused = false;
+        let parked = std::mem::take(&mut self.parked);
+        tracing::info!(
+            "Actor Backend resumed, starting {} parked works",
+            parked.len()
+        );
+        for request_key in parked {
+            self.ensure_internal_signal(request_key).await;
+        }
+    }
 }
 
 pub async fn do_generate_guest_input(
@@ -543,7 +1122,7 @@ pub async fn do_generate_guest_input(
     chain_specs: &SupportedChainSpecs,
     request_key: RequestKey,
     request_entity: GuestInputRequestEntity,
-) -> Result<Proof, String> {
+) -> Result<Proof, ProveFailure> {
     tracing::info!("Generating proof for {request_key}");
 
     let l1_chain_spec = chain_specs
@@ -581,12 +1160,12 @@ pub async fn do_generate_guest_input(
         request_entity.block_number() - 1,
     )
     .await
-    .map_err(|err| format!("failed to create rpc block data provider: {err:?}"))?;
+    .map_err(|err| ProveFailure::caused_by("failed to create rpc block data provider", err))?;
 
     let input = raiko
         .generate_input(provider)
         .await
-        .map_err(|e| format!("failed to generate input: {e:?}"))?;
+        .map_err(|e| ProveFailure::caused_by("failed to generate input", e))?;
 
     let input_proof = serde_json::to_string(&input).expect("input serialize ok");
     Ok(Proof {
@@ -604,7 +1183,8 @@ pub async fn do_prove_single(
     chain_specs: &SupportedChainSpecs,
     request_key: RequestKey,
     request_entity: SingleProofRequestEntity,
-) -> Result<Proof, String> {
+    slot: &WorkSlot,
+) -> Result<Proof, ProveFailure> {
     tracing::info!("Generating proof for {request_key}");
 
     let l1_chain_spec = chain_specs
@@ -642,7 +1222,7 @@ pub async fn do_prove_single(
         request_entity.block_number() - 1,
     )
     .await
-    .map_err(|err| format!("failed to create rpc block data provider: {err:?}"))?;
+    .map_err(|err| ProveFailure::caused_by("failed to create rpc block data provider", err))?;
 
     // double check if we already have the guest_input
     let input: GuestInput =
@@ -667,28 +1247,93 @@ pub async fn do_prove_single(
             raiko
                 .generate_input(provider)
                 .await
-                .map_err(|e| format!("failed to generate input: {e:?}"))?
+                .map_err(|e| ProveFailure::caused_by("failed to generate input", e))?
         };
 
     // 2. Generate the proof output
-    let output = raiko
-        .get_output(&input)
-        .map_err(|e| format!("failed to get output: {e:?}"))?;
+    // Execute off the async runtime, so that cancelling the work does not wait for the execution
+    let (raiko, input, output) = slot
+        .run_blocking(move || {
+            let output = raiko.get_output(&input);
+            (raiko, input, output)
+        })
+        .await
+        .map_err(|e| format!("failed to get output: {e}"))?;
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            return Err(output_failure(
+                err,
+                &taiko_chain_spec.rpc,
+                std::slice::from_ref(&input),
+                "failed to get output",
+            )
+            .await)
+        }
+    };
 
-    // 3. Generate the proof
-    let proof = raiko
-        .prove(input, &output, Some(pool))
+    // 3. Generate the proof, in a killable process for the local zk provers
+    let proof = if is_local_zk(raiko.request.proof_type, &raiko.request.prover_args) {
+        slot.prove_in_process(
+            &input,
+            false,
+            raiko.request.proof_type,
+            &raiko.request.prover_args,
+        )
         .await
-        .map_err(|err| format!("failed to generate single proof: {err:?}"))?;
+        .map_err(|err| format!("failed to generate single proof: {err}"))?
+    } else {
+        raiko
+            .prove(input, &output, Some(pool))
+            .await
+            .map_err(|err| ProveFailure::caused_by("failed to generate single proof", err))?
+    };
 
     Ok(proof)
 }
 
+/// Turn a failed output generation into a [`ProveFailure`], locating the first diverging
+/// transaction of a header mismatch with the receipts of the node.
+async fn output_failure(
+    err: RaikoError,
+    rpc: &str,
+    inputs: &[GuestInput],
+    context: &str,
+) -> ProveFailure {
+    let RaikoError::HeaderMismatch(mut mismatch) = err else {
+        return ProveFailure::caused_by(context, err);
+    };
+    let input = inputs
+        .iter()
+        .find(|input| input.block.number == mismatch.block_number)
+        .filter(|_| mismatch.receipts_diverge());
+    if let Some(input) = input {
+        let located: Result<_, RaikoError> = async {
+            let provider =
+                RpcBlockDataProvider::new(rpc, input.block.number.saturating_sub(1)).await?;
+            locate_diverging_transaction(&provider, input).await
+        }
+        .await;
+        match located {
+            Ok(tx_index) => mismatch.tx_index = tx_index,
+            Err(err) => tracing::warn!(
+                "failed to locate the diverging transaction of block {}: {err:?}",
+                mismatch.block_number
+            ),
+        }
+    }
+    ProveFailure {
+        error: format!("{context}: {mismatch}"),
+        class: FailureClass::ExecutionMismatch,
+        header_mismatch: Some(mismatch),
+    }
+}
+
 async fn do_prove_aggregation(
     pool: &mut dyn IdWrite,
     request_key: RequestKey,
     request_entity: AggregationRequestEntity,
-) -> Result<Proof, String> {
+) -> Result<Proof, ProveFailure> {
     let proof_type = request_key.proof_type().clone();
     let proofs = request_entity.proofs().clone();
 
@@ -699,7 +1344,7 @@ async fn do_prove_aggregation(
 
     let proof = aggregate_proofs(proof_type, input, &output, &config, Some(pool))
         .await
-        .map_err(|err| format!("failed to generate aggregation proof: {err:?}"))?;
+        .map_err(|err| ProveFailure::caused_by("failed to generate aggregation proof", err))?;
 
     Ok(proof)
 }
@@ -707,7 +1352,7 @@ async fn do_prove_aggregation(
 async fn new_raiko_for_batch_request(
     chain_specs: &SupportedChainSpecs,
     request_entity: BatchProofRequestEntity,
-) -> Result<Raiko, String> {
+) -> Result<Raiko, ProveFailure> {
     let l1_chain_spec = chain_specs
         .get_chain_spec(&request_entity.guest_input_entity().l1_network())
         .expect("unsupported l1 network");
@@ -726,7 +1371,7 @@ async fn new_raiko_for_batch_request(
         *batch_id,
     )
     .await
-    .map_err(|err| format!("Could not parse L1 batch proposal tx: {err:?}"))?;
+    .map_err(|err| ProveFailure::caused_by("Could not parse L1 batch proposal tx", err))?;
 
     let proof_request = ProofRequest {
         block_number: 0,
@@ -750,18 +1395,18 @@ async fn new_raiko_for_batch_request(
     Ok(Raiko::new(l1_chain_spec, taiko_chain_spec, proof_request))
 }
 
-async fn generate_input_for_batch(raiko: &Raiko) -> Result<GuestBatchInput, String> {
+async fn generate_input_for_batch(raiko: &Raiko) -> Result<GuestBatchInput, ProveFailure> {
     let provider_target_blocks = (raiko.request.l2_block_numbers[0] - 1
         ..=*raiko.request.l2_block_numbers.last().unwrap())
         .collect();
     let provider =
         RpcBlockDataProvider::new_batch(&raiko.taiko_chain_spec.rpc, provider_target_blocks)
             .await
-            .expect("Could not create RpcBlockDataProvider");
+            .map_err(|e| ProveFailure::caused_by("Could not create RpcBlockDataProvider", e))?;
     let input = raiko
         .generate_batch_input(provider)
         .await
-        .map_err(|e| format!("failed to generate batch input: {e:?}"))?;
+        .map_err(|e| ProveFailure::caused_by("failed to generate batch input", e))?;
     Ok(input)
 }
 
@@ -770,7 +1415,7 @@ pub async fn do_generate_batch_guest_input(
     chain_specs: &SupportedChainSpecs,
     request_key: RequestKey,
     request_entity: BatchGuestInputRequestEntity,
-) -> Result<Proof, String> {
+) -> Result<Proof, ProveFailure> {
     trace!("batch guest input for: {request_key:?}");
     let batch_proof_request_entity = BatchProofRequestEntity::new_with_guest_input_entity(
         request_entity.clone(),
@@ -778,12 +1423,8 @@ pub async fn do_generate_batch_guest_input(
         Default::default(),
         Default::default(),
     );
-    let raiko = new_raiko_for_batch_request(chain_specs, batch_proof_request_entity)
-        .await
-        .map_err(|err| format!("failed to create raiko: {err:?}"))?;
-    let input = generate_input_for_batch(&raiko)
-        .await
-        .map_err(|err| format!("failed to generate batch guest input: {err:?}"))?;
+    let raiko = new_raiko_for_batch_request(chain_specs, batch_proof_request_entity).await?;
+    let input = generate_input_for_batch(&raiko).await?;
     let input_proof = bincode::serialize(&input)
         .map_err(|err| format!("failed to serialize input to bincode: {err:?}"))?;
     let compressed_bytes = zlib_compress_data(&input_proof).unwrap();
@@ -804,20 +1445,28 @@ async fn do_prove_batch(
     chain_specs: &SupportedChainSpecs,
     request_key: RequestKey,
     request_entity: BatchProofRequestEntity,
-) -> Result<Proof, String> {
+    batch_guest_input: Option<String>,
+    slot: &WorkSlot,
+) -> Result<Proof, ProveFailure> {
     tracing::info!("Generating proof for {request_key}");
 
     let raiko = new_raiko_for_batch_request(chain_specs, request_entity).await?;
-    let input = if let Some(batch_guest_input) = raiko.request.prover_args.get("batch_guest_input")
-    {
-        // Tricky: originally the input was created (and pass around) by prove() infra,
-        // so it's a base64 string(in Proof).
-        // after we get it from db somewhere before, we need to pass it down here, but there is no known
-        // string carrier in key / entity, so we call deser twice, value -> string -> struct.
-        let b64_encoded_string: String = serde_json::from_value(batch_guest_input.clone())
+    // The input is created by the batch guest input request this one depends on, as a base64
+    // string in its proof. Requests registered before the dependencies still carry it in
+    // their prover args.
+    let batch_guest_input = match batch_guest_input {
+        Some(batch_guest_input) => Some(batch_guest_input),
+        None => raiko
+            .request
+            .prover_args
+            .get("batch_guest_input")
+            .map(|value| serde_json::from_value::<String>(value.clone()))
+            .transpose()
             .map_err(|err| {
                 format!("failed to deserialize batch_guest_input from value: {err:?}")
-            })?;
+            })?,
+    };
+    let input = if let Some(b64_encoded_string) = batch_guest_input {
         let compressed_bytes = general_purpose::STANDARD
             .decode(&b64_encoded_string)
             .unwrap();
@@ -828,18 +1477,628 @@ async fn do_prove_batch(
         guest_input
     } else {
         tracing::warn!("rebuild batch guest input for request: {request_key:?}");
-        generate_input_for_batch(&raiko)
-            .await
-            .map_err(|err| format!("failed to generate batch guest input: {err:?}"))?
+        generate_input_for_batch(&raiko).await?
     };
 
-    let output = raiko
-        .get_batch_output(&input)
-        .map_err(|e| format!("failed to get guest batch output: {e:?}"))?;
+    // Execute off the async runtime, so that cancelling the work does not wait for the execution
+    let (raiko, input, output) = slot
+        .run_blocking(move || {
+            let output = raiko.get_batch_output(&input);
+            (raiko, input, output)
+        })
+        .await
+        .map_err(|e| format!("failed to get guest batch output: {e}"))?;
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            return Err(output_failure(
+                err,
+                &raiko.taiko_chain_spec.rpc,
+                &input.inputs,
+                "failed to get guest batch output",
+            )
+            .await)
+        }
+    };
     debug!("batch guest output: {output:?}");
-    let proof = raiko
-        .batch_prove(input, &output, Some(pool))
+    let proof = if is_local_zk(raiko.request.proof_type, &raiko.request.prover_args) {
+        slot.prove_in_process(
+            &input,
+            true,
+            raiko.request.proof_type,
+            &raiko.request.prover_args,
+        )
         .await
-        .map_err(|e| format!("failed to generate batch proof: {e:?}"))?;
+        .map_err(|e| format!("failed to generate batch proof: {e}"))?
+    } else {
+        raiko
+            .batch_prove(input, &output, Some(pool))
+            .await
+            .map_err(|e| ProveFailure::caused_by("failed to generate batch proof", e))?
+    };
     Ok(proof)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raiko_core::interfaces::ProverSpecificOpts;
+    use raiko_lib::proof_type::ProofType;
+    use raiko_reqpool::{
+        memory_pool, AggregationRequestKey, ArtifactBackend, ArtifactId, ArtifactStore,
+    };
+
+    fn test_backend(name: &str) -> Backend {
+        test_backend_with_signals(name).0
+    }
+
+    fn test_backend_with_signals(name: &str) -> (Backend, Receiver<RequestKey>) {
+        let (internal_tx, internal_rx) = mpsc::channel(16);
+        let backend = Backend {
+            pool: memory_pool(name),
+            chain_specs: SupportedChainSpecs::default(),
+            internal_tx,
+            scheduler: Scheduler::new(SchedulerConfig::new(1)),
+            retry_policy: Arc::new(RetryPolicy::disabled()),
+            deadline_policy: Arc::new(DeadlinePolicy::default()),
+            leases: Leases::new(name.to_string(), Duration::from_secs(60)),
+            in_flight: InFlight::default(),
+            paused: false,
+            parked: Vec::new(),
+            watching: HashSet::new(),
+            dependents: HashMap::new(),
+        };
+        (backend, internal_rx)
+    }
+
+    async fn register_aggregation(backend: &mut Backend) -> RequestKey {
+        let request_key: RequestKey =
+            AggregationRequestKey::new(ProofType::Native, vec![1, 2]).into();
+        let request_entity = RequestEntity::Aggregation(AggregationRequestEntity::new(
+            vec![1, 2],
+            Vec::new(),
+            ProofType::Native,
+            ProverSpecificOpts::default(),
+        ));
+        backend
+            .register(request_key.clone(), request_entity)
+            .await
+            .unwrap();
+        request_key
+    }
+
+    #[tokio::test]
+    async fn test_cancel_releases_permit_and_keeps_cancelled_status() {
+        let mut backend = test_backend("test_cancel_releases_permit_and_keeps_cancelled_status");
+        let request_key = register_aggregation(&mut backend).await;
+
+        let (finish_tx, finish_rx) = oneshot::channel::<()>();
+        backend
+            .prove(request_key.clone(), |_, _, _| async move {
+                let _ = finish_rx.await;
+                Ok(Proof::default())
+            })
+            .await;
+        let class = SchedulingClass::Local(ProofType::Native);
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while backend.scheduler.load(class) != (1, 0) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("proving work should be started");
+
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::WorkInProgress);
+        backend.cancel(request_key.clone(), status).await.unwrap();
+
+        // The proving work is dropped right away, releasing its permit
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while backend.scheduler.load(class) != (0, 0) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("permit should be released");
+        assert!(backend.in_flight.lock().is_empty());
+
+        // A late result never overwrites the cancelled status
+        let _ = finish_tx.send(());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_pause_parks_registered_until_resumed() {
+        let (mut backend, mut internal_rx) =
+            test_backend_with_signals("test_pause_parks_registered_until_resumed");
+        let request_key = register_aggregation(&mut backend).await;
+
+        backend.halt().await.unwrap();
+        backend.handle_internal_signal(request_key.clone()).await;
+        assert_eq!(backend.parked, vec![request_key.clone()]);
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::Registered);
+
+        // New proving requests are rejected while paused
+        let response = backend
+            .handle_external_action(Action::Cancel {
+                request_key: request_key.clone(),
+            })
+            .await;
+        assert!(response.is_ok(), "cancel is still allowed while paused");
+        let request_entity = backend.pool.get(&request_key).unwrap().unwrap().0;
+        let response = backend
+            .handle_external_action(Action::Prove {
+                request_key: request_key.clone(),
+                request_entity,
+            })
+            .await;
+        assert_eq!(response, Err("System is paused".to_string()));
+
+        backend.resume().await;
+        assert!(backend.parked.is_empty());
+        let signal = tokio::time::timeout(Duration::from_secs(1), internal_rx.recv())
+            .await
+            .expect("parked request should be signalled");
+        assert_eq!(signal, Some(request_key));
+    }
+
+    #[tokio::test]
+    async fn test_request_claimed_by_another_host_is_watched() {
+        let (mut backend, _internal_rx) =
+            test_backend_with_signals("test_request_claimed_by_another_host_is_watched");
+        let request_key = register_aggregation(&mut backend).await;
+        assert!(backend
+            .pool
+            .try_claim(&request_key, "another-host", Duration::from_secs(60))
+            .unwrap());
+
+        backend.handle_internal_signal(request_key.clone()).await;
+        assert!(backend.watching.contains(&request_key));
+        assert!(backend.in_flight.lock().is_empty());
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::Registered);
+    }
+
+    #[tokio::test]
+    async fn test_claimed_request_no_longer_registered_is_skipped() {
+        let mut backend = test_backend("test_claimed_request_no_longer_registered_is_skipped");
+        let request_key = register_aggregation(&mut backend).await;
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        backend
+            .pool
+            .update_status(request_key.clone(), status.transition(Status::Cancelled))
+            .unwrap();
+
+        backend
+            .prove(request_key.clone(), |_, _, _| async {
+                Ok(Proof::default())
+            })
+            .await;
+        assert!(backend.in_flight.lock().is_empty());
+        assert!(backend.pool.get_lease(&request_key).unwrap().is_none());
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_work_in_progress_is_taken_over() {
+        let (mut backend, mut internal_rx) =
+            test_backend_with_signals("test_orphaned_work_in_progress_is_taken_over");
+        let request_key = register_aggregation(&mut backend).await;
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        backend
+            .pool
+            .update_status(
+                request_key.clone(),
+                status.transition(Status::WorkInProgress),
+            )
+            .unwrap();
+
+        // Left to the host working on it while its lease holds
+        assert!(backend
+            .pool
+            .try_claim(&request_key, "another-host", Duration::from_secs(60))
+            .unwrap());
+        backend.handle_internal_signal(request_key.clone()).await;
+        assert!(backend.watching.contains(&request_key));
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::WorkInProgress);
+
+        // Taken over once the lease is gone, e.g. the host crashed
+        backend
+            .pool
+            .release_lease(&request_key, "another-host")
+            .unwrap();
+        backend.handle_internal_signal(request_key.clone()).await;
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::Registered);
+        let signal = tokio::time::timeout(Duration::from_secs(1), internal_rx.recv())
+            .await
+            .expect("taken over request should be signalled");
+        assert_eq!(signal, Some(request_key));
+    }
+
+    #[tokio::test]
+    async fn test_lost_lease_drops_work() {
+        let mut backend = test_backend("test_lost_lease_drops_work");
+        backend.leases = Leases::new("host-a".to_string(), Duration::from_millis(150));
+        let request_key = register_aggregation(&mut backend).await;
+
+        let (finish_tx, finish_rx) = oneshot::channel::<()>();
+        backend
+            .prove(request_key.clone(), |_, _, _| async move {
+                let _ = finish_rx.await;
+                Ok(Proof::default())
+            })
+            .await;
+        assert_eq!(
+            backend
+                .pool
+                .get_lease(&request_key)
+                .unwrap()
+                .unwrap()
+                .holder,
+            "host-a"
+        );
+
+        // Another host takes the request over
+        backend.pool.release_lease(&request_key, "host-a").unwrap();
+        assert!(backend
+            .pool
+            .try_claim(&request_key, "host-b", Duration::from_secs(60))
+            .unwrap());
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !backend.in_flight.lock().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("proving work should be dropped");
+
+        // A late result never overwrites the status of the new holder
+        let _ = finish_tx.send(());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::WorkInProgress);
+        assert_eq!(
+            backend
+                .pool
+                .get_lease(&request_key)
+                .unwrap()
+                .unwrap()
+                .holder,
+            "host-b"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recover_re_registers_unfinished_requests() {
+        let (mut backend, mut internal_rx) =
+            test_backend_with_signals("test_recover_re_registers_unfinished_requests");
+        let entity = |block_numbers: Vec<u64>| {
+            RequestEntity::Aggregation(AggregationRequestEntity::new(
+                block_numbers,
+                Vec::new(),
+                ProofType::Native,
+                ProverSpecificOpts::default(),
+            ))
+        };
+        let key = |block_numbers: Vec<u64>| -> RequestKey {
+            AggregationRequestKey::new(ProofType::Native, block_numbers).into()
+        };
+        let statuses = [
+            (vec![1], StatusWithContext::new_registered()),
+            (
+                vec![2],
+                StatusWithContext::new(Status::WorkInProgress, chrono::Utc::now()),
+            ),
+            (vec![3], StatusWithContext::new_cancelled()),
+        ];
+        for (block_numbers, status) in statuses {
+            backend
+                .pool
+                .add(key(block_numbers.clone()), entity(block_numbers), status)
+                .unwrap();
+        }
+
+        backend.recover().await;
+
+        let status = backend.pool.get_status(&key(vec![2])).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::Registered);
+        let status = backend.pool.get_status(&key(vec![3])).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::Cancelled);
+
+        let mut signals = Vec::new();
+        for _ in 0..2 {
+            let signal = tokio::time::timeout(Duration::from_secs(1), internal_rx.recv())
+                .await
+                .expect("unfinished request should be signalled");
+            signals.extend(signal);
+        }
+        signals.sort_by_key(|request_key| request_key.to_string());
+        let mut expected = vec![key(vec![1]), key(vec![2])];
+        expected.sort_by_key(|request_key| request_key.to_string());
+        assert_eq!(signals, expected);
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_failure() {
+        let (mut backend, mut internal_rx) =
+            test_backend_with_signals("test_retry_transient_failure");
+        backend.retry_policy = Arc::new(RetryPolicy {
+            max_attempts: 2,
+            backoff_secs: 0,
+            ..Default::default()
+        });
+        let request_key = register_aggregation(&mut backend).await;
+        let fail = |_, _, _| async {
+            Err(ProveFailure::from(
+                "failed to generate input: RPC(\"connection refused\")".to_string(),
+            ))
+        };
+
+        // The first transient failure registers the request again
+        backend.prove(request_key.clone(), fail).await;
+        let signal = tokio::time::timeout(Duration::from_secs(1), internal_rx.recv())
+            .await
+            .expect("retried request should be signalled");
+        assert_eq!(signal, Some(request_key.clone()));
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::Registered);
+        assert_eq!(*status.attempts(), 1);
+        assert_eq!(*status.last_failure(), Some(FailureClass::Rpc));
+        assert!(backend.in_flight.lock().is_empty());
+
+        // The last attempt fails for good
+        backend.prove(request_key.clone(), fail).await;
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !matches!(
+                backend
+                    .pool
+                    .get_status(&request_key)
+                    .unwrap()
+                    .unwrap()
+                    .status(),
+                Status::Failed { .. }
+            ) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("request should fail");
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(*status.attempts(), 2);
+        assert_eq!(*status.last_failure(), Some(FailureClass::Rpc));
+    }
+
+    #[derive(Debug)]
+    struct UnavailableArtifacts;
+
+    impl ArtifactBackend for UnavailableArtifacts {
+        fn put(&self, _id: &ArtifactId, _data: &[u8]) -> Result<(), String> {
+            Err("the store is unavailable".to_string())
+        }
+
+        fn get(&self, _id: &ArtifactId) -> Result<Option<Vec<u8>>, String> {
+            Err("the store is unavailable".to_string())
+        }
+
+        fn delete(&self, _id: &ArtifactId) -> Result<(), String> {
+            Err("the store is unavailable".to_string())
+        }
+
+        fn list(&self) -> Result<Vec<(ArtifactId, DateTime<Utc>)>, String> {
+            Err("the store is unavailable".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unstored_proof_fails_request() {
+        let (mut backend, mut internal_rx) =
+            test_backend_with_signals("test_unstored_proof_fails_request");
+        backend.pool = backend.pool.clone().with_artifact_store(
+            ArtifactStore::new(Arc::new(UnavailableArtifacts)).with_inline_limit(0),
+        );
+        let request_key = register_aggregation(&mut backend).await;
+
+        backend
+            .prove(request_key.clone(), |_, _, _| async {
+                Ok(Proof {
+                    proof: Some("0xproof".to_string()),
+                    ..Default::default()
+                })
+            })
+            .await;
+        let signal = tokio::time::timeout(Duration::from_secs(1), internal_rx.recv())
+            .await
+            .expect("failed work should be signalled");
+        assert_eq!(signal, Some(request_key.clone()));
+
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert!(
+            matches!(status.status(), Status::Failed { error, .. } if error.contains("failed to store the proof")),
+            "unexpected status: {status}"
+        );
+        assert_eq!(*status.last_failure(), Some(FailureClass::Other));
+        assert!(backend.in_flight.lock().is_empty());
+        assert!(backend.pool.get_lease(&request_key).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_completion_signalled_by_proving_work() {
+        let (mut backend, mut internal_rx) =
+            test_backend_with_signals("test_completion_signalled_by_proving_work");
+        let request_key = register_aggregation(&mut backend).await;
+
+        backend
+            .prove(request_key.clone(), |_, _, _| async {
+                Ok(Proof::default())
+            })
+            .await;
+        backend.handle_internal_signal(request_key.clone()).await;
+
+        // The finished work signals the backend right away, without waiting for the sweep
+        let signal = tokio::time::timeout(Duration::from_secs(1), internal_rx.recv())
+            .await
+            .expect("finished work should be signalled");
+        assert_eq!(signal, Some(request_key.clone()));
+        backend.handle_internal_signal(request_key.clone()).await;
+
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert!(status.status().is_success());
+        assert!(backend.watching.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dependents_wait_for_dependencies() {
+        let (mut backend, mut internal_rx) =
+            test_backend_with_signals("test_dependents_wait_for_dependencies");
+        let dependency_key = register_aggregation(&mut backend).await;
+        let request_key: RequestKey =
+            AggregationRequestKey::new(ProofType::Native, vec![3, 4]).into();
+        let request_entity = RequestEntity::Aggregation(
+            AggregationRequestEntity::new(
+                vec![3, 4],
+                Vec::new(),
+                ProofType::Native,
+                ProverSpecificOpts::default(),
+            )
+            .with_dependencies(vec![dependency_key.clone()]),
+        );
+        backend
+            .register(request_key.clone(), request_entity.clone())
+            .await
+            .unwrap();
+
+        // The request is not started until its dependency is done
+        backend.handle_internal_signal(request_key.clone()).await;
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &Status::Registered);
+        assert!(backend.dependents[&dependency_key].contains(&request_key));
+
+        // A successful dependency hands its proof over
+        let proof = Proof {
+            proof: Some("proof".to_string()),
+            ..Default::default()
+        };
+        let success = StatusWithContext::new_registered().transition(Status::Success {
+            proof: proof.clone(),
+        });
+        backend
+            .pool
+            .update_status(dependency_key.clone(), success)
+            .unwrap();
+        assert!(matches!(
+            backend.check_dependencies(&request_entity),
+            Dependencies::Ready(proofs) if proofs == vec![proof]
+        ));
+
+        // A failed dependency wakes and fails the request
+        backend
+            .pool
+            .update_status(dependency_key.clone(), StatusWithContext::new_registered())
+            .unwrap();
+        backend
+            .prove(dependency_key.clone(), |_, _, _| async {
+                Err(ProveFailure::from("failed".to_string()))
+            })
+            .await;
+        let signal = tokio::time::timeout(Duration::from_secs(1), internal_rx.recv())
+            .await
+            .expect("finished dependency should be signalled");
+        assert_eq!(signal, Some(dependency_key.clone()));
+        backend.handle_internal_signal(dependency_key.clone()).await;
+        let signal = tokio::time::timeout(Duration::from_secs(1), internal_rx.recv())
+            .await
+            .expect("dependent should be woken");
+        assert_eq!(signal, Some(request_key.clone()));
+        assert!(backend.dependents.is_empty());
+
+        backend.handle_internal_signal(request_key.clone()).await;
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert!(matches!(status.status(), Status::Failed { .. }));
+        assert_eq!(*status.last_failure(), Some(FailureClass::DependencyFailed));
+    }
+
+    async fn register_aggregation_with_deadline(
+        backend: &mut Backend,
+        deadline: DateTime<Utc>,
+    ) -> RequestKey {
+        let request_key: RequestKey =
+            AggregationRequestKey::new(ProofType::Native, vec![3, 4]).into();
+        let request_entity = RequestEntity::Aggregation(
+            AggregationRequestEntity::new(
+                vec![3, 4],
+                Vec::new(),
+                ProofType::Native,
+                ProverSpecificOpts::default(),
+            )
+            .with_deadline(Some(deadline)),
+        );
+        backend
+            .register(request_key.clone(), request_entity)
+            .await
+            .unwrap();
+        request_key
+    }
+
+    #[tokio::test]
+    async fn test_deadline_cancels_overdue_work() {
+        let mut backend = test_backend("test_deadline_cancels_overdue_work");
+        let deadline = Utc::now() + chrono::Duration::milliseconds(100);
+        let request_key = register_aggregation_with_deadline(&mut backend, deadline).await;
+
+        backend
+            .prove(request_key.clone(), |_, _, _| async {
+                std::future::pending::<()>().await;
+                Ok(Proof::default())
+            })
+            .await;
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !matches!(
+                backend
+                    .pool
+                    .get_status(&request_key)
+                    .unwrap()
+                    .unwrap()
+                    .status(),
+                Status::Failed { .. }
+            ) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("overdue request should fail");
+
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(*status.last_failure(), Some(FailureClass::DeadlineExceeded));
+        assert!(backend.in_flight.lock().is_empty());
+        assert_eq!(
+            backend
+                .scheduler
+                .load(SchedulingClass::Local(ProofType::Native)),
+            (0, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deadline_fails_overdue_request_without_starting() {
+        let mut backend = test_backend("test_deadline_fails_overdue_request_without_starting");
+        let deadline = Utc::now() - chrono::Duration::seconds(1);
+        let request_key = register_aggregation_with_deadline(&mut backend, deadline).await;
+
+        backend
+            .prove(request_key.clone(), |_, _, _| async {
+                Err(ProveFailure::from("should not be started".to_string()))
+            })
+            .await;
+        let status = backend.pool.get_status(&request_key).unwrap().unwrap();
+        assert!(matches!(status.status(), Status::Failed { .. }));
+        assert_eq!(*status.attempts(), 0);
+        assert_eq!(*status.last_failure(), Some(FailureClass::DeadlineExceeded));
+        assert!(backend.in_flight.lock().is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use raiko_lib::proof_type::ProofType;
+use raiko_reqpool::RequestEntity;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Deadlines of the requests registered without one of their own.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DeadlinePolicy {
+    /// Time allowed to each attempt of a proof type, in seconds
+    pub timeout_secs: BTreeMap<ProofType, u64>,
+}
+
+impl DeadlinePolicy {
+    pub fn with_timeout(mut self, proof_type: ProofType, timeout_secs: u64) -> Self {
+        self.timeout_secs.insert(proof_type, timeout_secs);
+        self
+    }
+
+    /// The deadline of an attempt started at `started_at`, the deadline of the request itself
+    /// when it has one.
+    pub fn deadline(
+        &self,
+        request_entity: &RequestEntity,
+        started_at: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        request_entity.deadline().or_else(|| {
+            let timeout_secs = self.timeout_secs.get(&request_entity.proof_type())?;
+            Some(started_at + chrono::Duration::seconds(*timeout_secs as i64))
+        })
+    }
+}
+
+/// Wait until `deadline`, forever when there is none.
+pub(crate) async fn wait_until(deadline: Option<DateTime<Utc>>) {
+    match deadline {
+        Some(deadline) => {
+            let timeout = (deadline - Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(timeout).await
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raiko_core::interfaces::ProverSpecificOpts;
+    use raiko_reqpool::AggregationRequestEntity;
+
+    #[test]
+    fn test_deadline_policy() {
+        let policy = DeadlinePolicy::default().with_timeout(ProofType::Sp1, 60);
+        let entity = |proof_type| {
+            AggregationRequestEntity::new(
+                vec![1],
+                Vec::new(),
+                proof_type,
+                ProverSpecificOpts::default(),
+            )
+        };
+        let started_at = Utc::now();
+
+        let sp1 = RequestEntity::Aggregation(entity(ProofType::Sp1));
+        assert_eq!(
+            policy.deadline(&sp1, started_at),
+            Some(started_at + chrono::Duration::seconds(60))
+        );
+        let sgx = RequestEntity::Aggregation(entity(ProofType::Sgx));
+        assert_eq!(policy.deadline(&sgx, started_at), None);
+
+        // The deadline of the request wins over the default timeout
+        let deadline = started_at + chrono::Duration::seconds(5);
+        let sp1 = RequestEntity::Aggregation(entity(ProofType::Sp1).with_deadline(Some(deadline)));
+        assert_eq!(policy.deadline(&sp1, started_at), Some(deadline));
+
+        let policy: DeadlinePolicy = serde_json::from_str(r#"{"Sp1": 3000}"#).unwrap();
+        assert_eq!(policy.timeout_secs.get(&ProofType::Sp1), Some(&3000));
+    }
+}
@@ -0,0 +1,115 @@
+use raiko_reqpool::{Pool, RequestKey, Status};
+use std::{sync::Arc, time::Duration};
+
+/// The shortest TTL of the leases, which are renewed every third of it.
+const MIN_TTL_SECS: u64 = 3;
+
+/// The identity of this host in a pool shared by a fleet of hosts, and how long its claims on
+/// the requests last without being renewed.
+#[derive(Debug, Clone)]
+pub(crate) struct Leases {
+    holder: Arc<str>,
+    ttl: Duration,
+}
+
+impl Leases {
+    pub(crate) fn new(holder: String, ttl: Duration) -> Self {
+        Self {
+            holder: holder.into(),
+            ttl,
+        }
+    }
+
+    /// Read the identity from `RAIKO_HOST_ID` and the TTL from `LEASE_TTL_SECS`. Without an
+    /// identity, a new one is made up for every run of the host.
+    pub(crate) fn from_env() -> Result<Self, String> {
+        let holder = std::env::var("RAIKO_HOST_ID").unwrap_or_else(|_| {
+            format!(
+                "{}-{}-{}",
+                std::env::var("HOSTNAME").unwrap_or("raiko".to_string()),
+                std::process::id(),
+                chrono::Utc::now().timestamp_millis()
+            )
+        });
+        let ttl = match std::env::var("LEASE_TTL_SECS") {
+            Ok(ttl_secs) => parse_ttl(&ttl_secs)?,
+            Err(_) => Duration::from_secs(60),
+        };
+        tracing::info!("Actor Backend leases requests as {holder} for {ttl:?}");
+        Ok(Self::new(holder, ttl))
+    }
+
+    pub(crate) fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    pub(crate) fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Keep renewing the lease on a request while its work runs. Returns once the lease is
+    /// lost, or the request is no longer in progress, e.g. cancelled by another host.
+    pub(crate) async fn hold(&self, mut pool: Pool, request_key: RequestKey) {
+        let mut ticker = tokio::time::interval(self.ttl / 3);
+        ticker.tick().await; // first tick is immediate
+        loop {
+            ticker.tick().await;
+            match pool.renew_lease(&request_key, self.holder(), self.ttl) {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::warn!("Actor Backend lost the lease of {request_key}");
+                    return;
+                }
+                Err(err) => {
+                    // The lease is found lost by the next renewal if it expires meanwhile
+                    tracing::warn!(
+                        "Actor Backend failed to renew the lease of {request_key}: {err:?}"
+                    );
+                    continue;
+                }
+            }
+            match pool.get_status(&request_key) {
+                Ok(Some(status)) if status.status() == &Status::WorkInProgress => {}
+                Ok(status) => {
+                    tracing::info!(
+                        "Actor Backend found {request_key} no longer in progress: {status:?}"
+                    );
+                    return;
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Actor Backend failed to get status of leased {request_key}: {err:?}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Parse the TTL of the leases in seconds, rejecting the ones too short to be renewed in time.
+fn parse_ttl(ttl_secs: &str) -> Result<Duration, String> {
+    let ttl_secs = ttl_secs
+        .parse::<u64>()
+        .map_err(|e| format!("Invalid LEASE_TTL_SECS {ttl_secs:?}: {e}"))?;
+    if ttl_secs < MIN_TTL_SECS {
+        return Err(format!(
+            "Invalid LEASE_TTL_SECS {ttl_secs}, must be at least {MIN_TTL_SECS}"
+        ));
+    }
+    Ok(Duration::from_secs(ttl_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ttl() {
+        assert_eq!(parse_ttl("60"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_ttl("3"), Ok(Duration::from_secs(3)));
+        assert!(parse_ttl("0").is_err());
+        assert!(parse_ttl("2").is_err());
+        assert!(parse_ttl("-1").is_err());
+        assert!(parse_ttl("1m").is_err());
+    }
+}
@@ -1,21 +1,29 @@
 mod action;
 mod actor;
 mod backend;
+mod deadline;
+mod lease;
+mod retry;
+mod scheduler;
+mod work;
 
 use raiko_ballot::Ballot;
 use raiko_core::interfaces::ProofRequestOpt;
 use raiko_lib::consts::SupportedChainSpecs;
 use tokio::sync::{mpsc, oneshot};
 
-pub(crate) use backend::Backend;
+pub(crate) use backend::{Backend, InFlight};
 
 // re-export
-pub use action::Action;
-pub use actor::Actor;
+pub use action::{Action, PauseAction};
+pub use actor::{Actor, DrainStatus};
+pub use deadline::DeadlinePolicy;
 pub use raiko_reqpool::{
     AggregationRequestEntity, AggregationRequestKey, Pool, RequestEntity, RequestKey,
     SingleProofRequestEntity, SingleProofRequestKey, StatusWithContext,
 };
+pub use retry::{classify_error, classify_failure, RetryPolicy};
+pub use scheduler::{Priority, SchedulerConfig, SchedulingClass};
 
 /// Run the actor backend in background, and return the actor.
 pub async fn start_actor(
@@ -23,19 +31,25 @@ pub async fn start_actor(
     ballot: Ballot,
     chain_specs: SupportedChainSpecs,
     default_request_config: ProofRequestOpt,
-    max_proving_concurrency: usize,
+    scheduler_config: SchedulerConfig,
+    retry_policy: RetryPolicy,
+    deadline_policy: DeadlinePolicy,
 ) -> Actor {
     let channel_size = 1024;
     let (action_tx, action_rx) =
         mpsc::channel::<(Action, oneshot::Sender<Result<StatusWithContext, String>>)>(channel_size);
-    let (pause_tx, pause_rx) = mpsc::channel::<()>(1);
+    let (pause_tx, pause_rx) = mpsc::channel::<PauseAction>(1);
+    let in_flight = InFlight::default();
 
     Backend::serve_in_background(
         pool.clone(),
         chain_specs.clone(),
         pause_rx,
         action_rx,
-        max_proving_concurrency,
+        scheduler_config,
+        retry_policy,
+        deadline_policy,
+        in_flight.clone(),
     )
     .await;
 
@@ -47,4 +61,5 @@ pub async fn start_actor(
         action_tx,
         pause_tx,
     )
+    .with_in_flight(in_flight)
 }
@@ -0,0 +1,240 @@
+use raiko_core::interfaces::RaikoError;
+use raiko_lib::prover::ProverError;
+use raiko_reqpool::FailureClass;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, time::Duration};
+
+/// When a failed request is started again by the actor, without waiting for the client to
+/// resend it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on each following one
+    pub backoff_secs: u64,
+    pub max_backoff_secs: u64,
+    /// The failures worth retrying, the others are permanent
+    pub retryable: BTreeSet<FailureClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff_secs: 30,
+            max_backoff_secs: 600,
+            retryable: BTreeSet::from([
+                FailureClass::Rpc,
+                FailureClass::BlobUnavailable,
+                FailureClass::ProverTimeout,
+            ]),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry, every failure is permanent.
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The delay before retrying a request after its `attempts`-th attempt failed with `class`,
+    /// or `None` when the failure is permanent.
+    pub fn retry_after(&self, class: FailureClass, attempts: u32) -> Option<Duration> {
+        if attempts >= self.max_attempts || !self.retryable.contains(&class) {
+            return None;
+        }
+        let backoff = self
+            .backoff_secs
+            .saturating_mul(1 << attempts.saturating_sub(1).min(32));
+        Some(Duration::from_secs(backoff.min(self.max_backoff_secs)))
+    }
+}
+
+/// Classify a failure from the error it comes from, falling back to [`classify_failure`] for
+/// the errors which only carry a message.
+pub fn classify_error(error: &RaikoError) -> FailureClass {
+    match error {
+        RaikoError::HeaderMismatch(_) => FailureClass::ExecutionMismatch,
+        RaikoError::RPC(_) | RaikoError::Io(_) => FailureClass::Rpc,
+        RaikoError::Preflight(message) | RaikoError::Guest(ProverError::GuestError(message)) => {
+            classify_failure(message)
+        }
+        RaikoError::Anyhow(error) => {
+            if let Some(error) = error
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<RaikoError>())
+            {
+                classify_error(error)
+            } else if error.chain().any(|cause| cause.is::<std::io::Error>()) {
+                FailureClass::Rpc
+            } else {
+                classify_failure(&format!("{error:#}"))
+            }
+        }
+        RaikoError::InvalidProofType(_)
+        | RaikoError::InvalidBlobOption(_)
+        | RaikoError::InvalidRequestConfig(_)
+        | RaikoError::FeatureNotSupportedError(_)
+        | RaikoError::Conversion(_)
+        | RaikoError::Guest(_)
+        | RaikoError::Db(_)
+        | RaikoError::Serde(_) => FailureClass::Other,
+    }
+}
+
+/// Classify a failure from its error message, for the errors which are not typed, like the
+/// messages of the provers.
+pub fn classify_failure(error: &str) -> FailureClass {
+    let error = error.to_lowercase();
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|pattern| error.contains(pattern));
+    if contains_any(&[
+        "header mismatch",
+        "headermismatch",
+        "does not match the expected",
+    ]) {
+        FailureClass::ExecutionMismatch
+    } else if contains_any(&["out of memory", "memory allocation", "capacity overflow"]) {
+        FailureClass::ProverOutOfMemory
+    } else if contains_any(&["blob"]) {
+        FailureClass::BlobUnavailable
+    } else if contains_any(&[
+        "rpc",
+        "connection",
+        "transport",
+        "error sending request",
+        "too many requests",
+        "service unavailable",
+        "bad gateway",
+    ]) {
+        FailureClass::Rpc
+    } else if contains_any(&["timed out", "timeout", "deadline exceeded"]) {
+        FailureClass::ProverTimeout
+    } else {
+        FailureClass::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_failure() {
+        for (error, class) in [
+            (
+                "failed to generate input: RPC(\"error sending request: connection refused\")",
+                FailureClass::Rpc,
+            ),
+            (
+                "failed to generate input: Preflight(\"No blob data for 0x01ab\")",
+                FailureClass::BlobUnavailable,
+            ),
+            (
+                "failed to get output: Header mismatch for block 12",
+                FailureClass::ExecutionMismatch,
+            ),
+            (
+                "failed to generate single proof: Guest(GuestError(\"memory allocation of 1024 bytes failed\"))",
+                FailureClass::ProverOutOfMemory,
+            ),
+            (
+                "failed to generate batch proof: Guest(GuestError(\"Sp1: network proof failed Timeout\"))",
+                FailureClass::ProverTimeout,
+            ),
+            ("failed to deserialize guest_input", FailureClass::Other),
+        ] {
+            assert_eq!(classify_failure(error), class, "{error}");
+        }
+    }
+
+    #[test]
+    fn test_classify_error() {
+        use raiko_core::provider::failover::NotServed;
+
+        for (error, class) in [
+            (
+                RaikoError::RPC("https://rpc.example.com".to_string()),
+                FailureClass::Rpc,
+            ),
+            (
+                RaikoError::Preflight("No blob data for 0x01ab".to_string()),
+                FailureClass::BlobUnavailable,
+            ),
+            (
+                RaikoError::Guest(ProverError::GuestError(
+                    "memory allocation of 1024 bytes failed".to_string(),
+                )),
+                FailureClass::ProverOutOfMemory,
+            ),
+            // The endpoints are part of the message, but the error is not an RPC one
+            (
+                RaikoError::InvalidRequestConfig(
+                    "Missing network: https://rpc.example.com".to_string(),
+                ),
+                FailureClass::Other,
+            ),
+            (
+                RaikoError::Anyhow(
+                    anyhow::Error::new(RaikoError::RPC("connection reset".to_string()))
+                        .context("failed to fetch the block"),
+                ),
+                FailureClass::Rpc,
+            ),
+            (
+                RaikoError::Anyhow(
+                    std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset").into(),
+                ),
+                FailureClass::Rpc,
+            ),
+            (
+                NotServed::error("blob 0x01ab is not available at http://beacon"),
+                FailureClass::BlobUnavailable,
+            ),
+        ] {
+            assert_eq!(classify_error(&error), class, "{error:?}");
+        }
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            backoff_secs: 10,
+            max_backoff_secs: 30,
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.retry_after(FailureClass::Rpc, 1),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            policy.retry_after(FailureClass::Rpc, 2),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(
+            policy.retry_after(FailureClass::Rpc, 3),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(policy.retry_after(FailureClass::Rpc, 4), None);
+        assert_eq!(policy.retry_after(FailureClass::ExecutionMismatch, 1), None);
+        assert_eq!(
+            RetryPolicy::disabled().retry_after(FailureClass::Rpc, 1),
+            None
+        );
+
+        let policy: RetryPolicy =
+            serde_json::from_str(r#"{"max_attempts": 5, "retryable": ["prover_out_of_memory"]}"#)
+                .unwrap();
+        assert_eq!(policy.backoff_secs, 30);
+        assert_eq!(
+            policy.retry_after(FailureClass::ProverOutOfMemory, 1),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(policy.retry_after(FailureClass::Rpc, 1), None);
+    }
+}
@@ -0,0 +1,437 @@
+use chrono::{DateTime, Utc};
+use raiko_lib::proof_type::ProofType;
+use raiko_reqpool::RequestEntity;
+use serde_json::Value;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::sync::oneshot;
+
+/// The kind of resource a request keeps busy while it is proven, each class is limited by its
+/// own concurrency so that a slow prover never holds back cheaper work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchedulingClass {
+    /// Guest input generation only, bound by the RPC nodes.
+    GuestInput,
+    /// Proving on this machine.
+    Local(ProofType),
+    /// Proving delegated to a remote prover network, i.e. SP1 network or RISC0 Bonsai.
+    Network(ProofType),
+}
+
+impl SchedulingClass {
+    pub fn of(request_entity: &RequestEntity) -> Self {
+        match request_entity {
+            RequestEntity::GuestInput(_) | RequestEntity::BatchGuestInput(_) => Self::GuestInput,
+            RequestEntity::SingleProof(entity) => {
+                Self::proving(*entity.proof_type(), entity.prover_args())
+            }
+            RequestEntity::BatchProof(entity) => {
+                Self::proving(*entity.proof_type(), entity.prover_args())
+            }
+            RequestEntity::Aggregation(entity) => {
+                Self::proving(*entity.proof_type(), &entity.prover_args().clone().into())
+            }
+        }
+    }
+
+    pub(crate) fn proving(proof_type: ProofType, prover_args: &HashMap<String, Value>) -> Self {
+        let prover_arg = |name: &str| prover_args.get(&proof_type.to_string())?.get(name);
+        let network = match proof_type {
+            ProofType::Sp1 => prover_arg("prover").and_then(Value::as_str) == Some("network"),
+            ProofType::Risc0 => prover_arg("bonsai").and_then(Value::as_bool) == Some(true),
+            ProofType::Native | ProofType::Sgx | ProofType::SgxGeth => false,
+        };
+        if network {
+            Self::Network(proof_type)
+        } else {
+            Self::Local(proof_type)
+        }
+    }
+}
+
+impl std::fmt::Display for SchedulingClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GuestInput => f.write_str("guest_input"),
+            Self::Local(proof_type) => write!(f, "{proof_type}"),
+            Self::Network(proof_type) => write!(f, "{proof_type}_network"),
+        }
+    }
+}
+
+impl std::str::FromStr for SchedulingClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "guest_input" => Ok(Self::GuestInput),
+            s => match s.strip_suffix("_network") {
+                Some(proof_type) => Ok(Self::Network(proof_type.parse()?)),
+                None => Ok(Self::Local(s.parse()?)),
+            },
+        }
+    }
+}
+
+/// The order in which queued requests of the same class are started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Work that nothing waits on yet, like prefetching guest inputs.
+    Low,
+    #[default]
+    Normal,
+    /// Work that releases other work, like aggregating proofs.
+    High,
+    /// Work whose deadline is close.
+    Urgent,
+}
+
+/// Requests due within this time from being queued are started before any other work.
+const URGENT_WITHIN_SECS: i64 = 10 * 60;
+
+impl Priority {
+    pub fn of(request_entity: &RequestEntity, now: DateTime<Utc>) -> Self {
+        let urgent_within = chrono::Duration::seconds(URGENT_WITHIN_SECS);
+        if request_entity
+            .deadline()
+            .is_some_and(|deadline| deadline - now <= urgent_within)
+        {
+            return Self::Urgent;
+        }
+        match request_entity {
+            RequestEntity::Aggregation(_) => Self::High,
+            RequestEntity::SingleProof(_) | RequestEntity::BatchProof(_) => Self::Normal,
+            RequestEntity::GuestInput(_) | RequestEntity::BatchGuestInput(_) => Self::Low,
+        }
+    }
+}
+
+/// Concurrency limits of the scheduling classes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchedulerConfig {
+    /// Limit shared by all the classes without a limit of their own.
+    pub default_limit: usize,
+    /// Limit of a class, `None` for unlimited.
+    pub class_limits: HashMap<SchedulingClass, Option<usize>>,
+}
+
+impl SchedulerConfig {
+    pub fn new(default_limit: usize) -> Self {
+        Self {
+            default_limit,
+            class_limits: HashMap::new(),
+        }
+    }
+
+    pub fn with_class_limit(mut self, class: SchedulingClass, limit: Option<usize>) -> Self {
+        self.class_limits.insert(class, limit);
+        self
+    }
+
+    // The classes without a limit of their own share the `None` bucket.
+    fn bucket(&self, class: SchedulingClass) -> (Option<SchedulingClass>, Option<usize>) {
+        match self.class_limits.get(&class) {
+            Some(limit) => (Some(class), *limit),
+            None => (None, Some(self.default_limit)),
+        }
+    }
+}
+
+/// Admits proving works under the concurrency limit of their class, highest priority first and
+/// in arrival order within a priority.
+#[derive(Clone)]
+pub(crate) struct Scheduler {
+    config: Arc<SchedulerConfig>,
+    buckets: Arc<Mutex<HashMap<Option<SchedulingClass>, Bucket>>>,
+}
+
+#[derive(Default)]
+struct Bucket {
+    running: usize,
+    waiting: BinaryHeap<Waiter>,
+    next_seq: u64,
+}
+
+struct Waiter {
+    priority: Priority,
+    seq: u64,
+    permit_tx: oneshot::Sender<Permit>,
+}
+
+impl Waiter {
+    fn key(&self) -> (Priority, Reverse<u64>) {
+        (self.priority, Reverse(self.seq))
+    }
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// A running slot of a scheduling class, handed over to the next waiter when dropped.
+pub(crate) struct Permit {
+    scheduler: Scheduler,
+    bucket: Option<SchedulingClass>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.scheduler.release(self.bucket);
+    }
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            buckets: Default::default(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Option<SchedulingClass>, Bucket>> {
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait for a running slot of `class`. Dropping the returned future gives up the place in
+    /// the queue.
+    pub async fn acquire(&self, class: SchedulingClass, priority: Priority) -> Permit {
+        let (bucket_key, limit) = self.config.bucket(class);
+        let permit_rx = {
+            let mut buckets = self.lock();
+            let bucket = buckets.entry(bucket_key).or_default();
+            if bucket.waiting.is_empty() && !limit.is_some_and(|limit| bucket.running >= limit) {
+                bucket.running += 1;
+                return Permit {
+                    scheduler: self.clone(),
+                    bucket: bucket_key,
+                };
+            }
+            let (permit_tx, permit_rx) = oneshot::channel();
+            bucket.waiting.push(Waiter {
+                priority,
+                seq: bucket.next_seq,
+                permit_tx,
+            });
+            bucket.next_seq += 1;
+            permit_rx
+        };
+        // The sender is only dropped along with the scheduler
+        permit_rx.await.expect("scheduler should not be dropped")
+    }
+
+    /// The number of running and waiting works sharing the limit of `class`.
+    pub fn load(&self, class: SchedulingClass) -> (usize, usize) {
+        let (bucket_key, _) = self.config.bucket(class);
+        self.lock()
+            .get(&bucket_key)
+            .map_or((0, 0), |bucket| (bucket.running, bucket.waiting.len()))
+    }
+
+    fn release(&self, bucket_key: Option<SchedulingClass>) {
+        let waiter = {
+            let mut buckets = self.lock();
+            let bucket = buckets.entry(bucket_key).or_default();
+            match bucket.waiting.pop() {
+                Some(waiter) => waiter,
+                None => {
+                    bucket.running -= 1;
+                    return;
+                }
+            }
+        };
+        // The slot is handed over as is. When the waiter is gone, the refused permit is dropped
+        // here, outside of the lock, and offered to the next one.
+        let _ = waiter.permit_tx.send(Permit {
+            scheduler: self.clone(),
+            bucket: bucket_key,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raiko_core::interfaces::ProverSpecificOpts;
+    use raiko_reqpool::AggregationRequestEntity;
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn test_scheduling_class_of_request() {
+        let aggregation = |prover_args: ProverSpecificOpts| {
+            RequestEntity::Aggregation(AggregationRequestEntity::new(
+                vec![1],
+                Vec::new(),
+                ProofType::Sp1,
+                prover_args,
+            ))
+        };
+        let network = ProverSpecificOpts {
+            sp1: Some(json!({"prover": "network"})),
+            ..Default::default()
+        };
+        assert_eq!(
+            SchedulingClass::of(&aggregation(network)),
+            SchedulingClass::Network(ProofType::Sp1)
+        );
+        assert_eq!(
+            SchedulingClass::of(&aggregation(ProverSpecificOpts::default())),
+            SchedulingClass::Local(ProofType::Sp1)
+        );
+        assert_eq!(
+            Priority::of(&aggregation(ProverSpecificOpts::default()), Utc::now()),
+            Priority::High
+        );
+
+        for class in [
+            SchedulingClass::GuestInput,
+            SchedulingClass::Local(ProofType::Risc0),
+            SchedulingClass::Network(ProofType::Sp1),
+        ] {
+            assert_eq!(class.to_string().parse(), Ok(class));
+        }
+        assert!("sp2".parse::<SchedulingClass>().is_err());
+    }
+
+    #[test]
+    fn test_priority_raised_close_to_deadline() {
+        let now = Utc::now();
+        let aggregation = |deadline: Option<DateTime<Utc>>| {
+            RequestEntity::Aggregation(
+                AggregationRequestEntity::new(
+                    vec![1],
+                    Vec::new(),
+                    ProofType::Sp1,
+                    ProverSpecificOpts::default(),
+                )
+                .with_deadline(deadline),
+            )
+        };
+        assert_eq!(Priority::of(&aggregation(None), now), Priority::High);
+        assert_eq!(
+            Priority::of(&aggregation(Some(now + chrono::Duration::hours(1))), now),
+            Priority::High
+        );
+        assert_eq!(
+            Priority::of(&aggregation(Some(now + chrono::Duration::minutes(5))), now),
+            Priority::Urgent
+        );
+        assert_eq!(
+            Priority::of(&aggregation(Some(now - chrono::Duration::minutes(1))), now),
+            Priority::Urgent
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_limits_per_class() {
+        let scheduler = Scheduler::new(
+            SchedulerConfig::new(1)
+                .with_class_limit(SchedulingClass::GuestInput, Some(2))
+                .with_class_limit(SchedulingClass::Network(ProofType::Sp1), None),
+        );
+        let local_risc0 = SchedulingClass::Local(ProofType::Risc0);
+        let local_sgx = SchedulingClass::Local(ProofType::Sgx);
+
+        // Unlimited classes never wait
+        let mut network = Vec::new();
+        for _ in 0..8 {
+            network.push(
+                scheduler
+                    .acquire(SchedulingClass::Network(ProofType::Sp1), Priority::Normal)
+                    .await,
+            );
+        }
+        let _input_1 = scheduler
+            .acquire(SchedulingClass::GuestInput, Priority::Low)
+            .await;
+        let _input_2 = scheduler
+            .acquire(SchedulingClass::GuestInput, Priority::Low)
+            .await;
+        assert_eq!(scheduler.load(SchedulingClass::GuestInput), (2, 0));
+
+        // Classes without a limit of their own share the default one
+        let risc0 = scheduler.acquire(local_risc0, Priority::Normal).await;
+        let sgx = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(local_sgx, Priority::Normal).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!sgx.is_finished());
+        assert_eq!(scheduler.load(local_sgx), (1, 1));
+
+        drop(risc0);
+        let sgx = tokio::time::timeout(Duration::from_secs(1), sgx)
+            .await
+            .expect("released slot should be handed over")
+            .unwrap();
+        assert_eq!(scheduler.load(local_sgx), (1, 0));
+        drop(sgx);
+        drop(network);
+        assert_eq!(scheduler.load(local_sgx), (0, 0));
+        assert_eq!(
+            scheduler.load(SchedulingClass::Network(ProofType::Sp1)),
+            (0, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_priority_order() {
+        let scheduler = Scheduler::new(SchedulerConfig::new(1));
+        let class = SchedulingClass::Local(ProofType::Native);
+        let running = scheduler.acquire(class, Priority::Normal).await;
+
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut waiters = Vec::new();
+        for (name, priority) in [
+            ("low", Priority::Low),
+            ("normal-1", Priority::Normal),
+            ("high", Priority::High),
+            ("normal-2", Priority::Normal),
+        ] {
+            let scheduler = scheduler.clone();
+            let order_tx = order_tx.clone();
+            waiters.push(tokio::spawn(async move {
+                let _permit = scheduler.acquire(class, priority).await;
+                order_tx.send(name).unwrap();
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // A waiter that gives up keeps no slot
+        let gone = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.acquire(class, Priority::High).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        gone.abort();
+
+        drop(running);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        let mut order = Vec::new();
+        while let Ok(name) = order_rx.try_recv() {
+            order.push(name);
+        }
+        assert_eq!(order, vec!["high", "normal-1", "normal-2", "low"]);
+        assert_eq!(scheduler.load(class), (0, 0));
+    }
+}
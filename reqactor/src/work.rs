@@ -0,0 +1,188 @@
+use raiko_lib::{proof_type::ProofType, prover::Proof};
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::HashMap, path::PathBuf, process::Stdio, sync::Arc};
+use tokio::{process::Command, sync::oneshot};
+use tokio_util::sync::CancellationToken;
+
+use crate::scheduler::{Permit, SchedulingClass};
+
+/// The scheduling slot of a proving work, handed to what it runs outside of its own future.
+///
+/// Dropping the future of a work does not stop a blocking execution or a prover process, so
+/// those hold a clone of the slot, keeping it taken until they exit, and stop as soon as the
+/// work is stopped.
+#[derive(Clone)]
+pub(crate) struct WorkSlot {
+    stopped: CancellationToken,
+    _permit: Arc<Permit>,
+}
+
+impl WorkSlot {
+    pub(crate) fn new(stopped: CancellationToken, permit: Permit) -> Self {
+        Self {
+            stopped,
+            _permit: Arc::new(permit),
+        }
+    }
+
+    /// Run `f` off the async runtime, skipping it when the work stopped before it started.
+    pub(crate) async fn run_blocking<T, F>(&self, f: F) -> Result<T, String>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let slot = self.clone();
+        tokio::task::spawn_blocking(move || {
+            if slot.stopped.is_cancelled() {
+                return Err("the work is stopped".to_string());
+            }
+            let output = f();
+            drop(slot);
+            Ok(output)
+        })
+        .await
+        .map_err(|e| format!("blocking task failed: {e:?}"))?
+    }
+
+    /// Prove `input` with the `prove-input` binary, killing the process when the work stops.
+    ///
+    /// The zk prover SDKs prove in process for minutes to hours, and cannot be interrupted.
+    pub(crate) async fn prove_in_process<T: Serialize>(
+        &self,
+        input: &T,
+        batch: bool,
+        proof_type: ProofType,
+        prover_args: &HashMap<String, Value>,
+    ) -> Result<Proof, String> {
+        let dir = tempfile::tempdir().map_err(|e| format!("failed to create temp dir: {e:?}"))?;
+        let input_path = dir.path().join("input.bin");
+        let config_path = dir.path().join("config.json");
+        let output_path = dir.path().join("proof.json");
+        let input = bincode::serialize(input)
+            .map_err(|e| format!("failed to serialize input to bincode: {e:?}"))?;
+        let config = serde_json::to_vec(prover_args)
+            .map_err(|e| format!("failed to serialize prover args: {e:?}"))?;
+        tokio::fs::write(&input_path, input)
+            .await
+            .map_err(|e| format!("failed to write input: {e:?}"))?;
+        tokio::fs::write(&config_path, config)
+            .await
+            .map_err(|e| format!("failed to write prover args: {e:?}"))?;
+
+        let mut command = Command::new(prove_input_bin()?);
+        command
+            .arg(&input_path)
+            .arg(format!("--proof-type={proof_type}"))
+            .arg(format!("--config-path={}", config_path.display()))
+            .arg(format!("--output={}", output_path.display()))
+            .arg("--prove")
+            .stdout(Stdio::null())
+            .kill_on_drop(true);
+        if batch {
+            command.arg("--batch");
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("failed to start the prover process: {e:?}"))?;
+
+        // The process is owned by its own task, so that it is killed and reaped however the
+        // work stops, with the slot taken until then
+        let slot = self.clone();
+        let (status_tx, status_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            tokio::select! {
+                status = child.wait() => {
+                    let _ = status_tx.send(status);
+                }
+                _ = slot.stopped.cancelled() => {
+                    if let Err(err) = child.kill().await {
+                        tracing::error!("failed to kill the prover process: {err:?}");
+                    }
+                }
+            }
+            drop(slot);
+        });
+        let status = status_rx
+            .await
+            .map_err(|_| "the prover process was killed".to_string())?
+            .map_err(|e| format!("failed to wait for the prover process: {e:?}"))?;
+        if !status.success() {
+            return Err(format!("the prover process failed: {status}"));
+        }
+
+        let proof = tokio::fs::read(&output_path)
+            .await
+            .map_err(|e| format!("failed to read the proof: {e:?}"))?;
+        serde_json::from_slice(&proof)
+            .map_err(|e| format!("failed to deserialize the proof: {e:?}"))
+    }
+}
+
+/// Whether the proof is generated by a zk prover SDK on this host.
+pub(crate) fn is_local_zk(proof_type: ProofType, prover_args: &HashMap<String, Value>) -> bool {
+    matches!(
+        SchedulingClass::proving(proof_type, prover_args),
+        SchedulingClass::Local(ProofType::Sp1 | ProofType::Risc0)
+    )
+}
+
+/// The `prove-input` binary installed next to the host, unless `PROVE_INPUT_BIN` is set.
+fn prove_input_bin() -> Result<PathBuf, String> {
+    if let Ok(path) = std::env::var("PROVE_INPUT_BIN") {
+        return Ok(path.into());
+    }
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("failed to locate the running binary: {e:?}"))?;
+    Ok(exe.with_file_name("prove-input"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::{Priority, Scheduler, SchedulerConfig};
+
+    #[tokio::test]
+    async fn test_blocking_work_holds_slot_until_it_exits() {
+        let scheduler = Scheduler::new(SchedulerConfig::new(1));
+        let class = SchedulingClass::Local(ProofType::Sp1);
+        let stopped = CancellationToken::new();
+        let permit = scheduler.acquire(class, Priority::Normal).await;
+        let slot = WorkSlot::new(stopped.clone(), permit);
+
+        let (started_tx, started_rx) = oneshot::channel();
+        let (finish_tx, finish_rx) = std::sync::mpsc::channel::<()>();
+        let work = tokio::spawn(async move {
+            slot.run_blocking(move || {
+                started_tx.send(()).unwrap();
+                finish_rx.recv().unwrap();
+            })
+            .await
+        });
+        started_rx.await.unwrap();
+
+        // Dropping the work leaves the blocking execution running with the slot
+        work.abort();
+        stopped.cancel();
+        assert_eq!(scheduler.load(class), (1, 0));
+
+        finish_tx.send(()).unwrap();
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            scheduler.acquire(class, Priority::Normal),
+        )
+        .await
+        .expect("slot should be released once the blocking execution exits");
+    }
+
+    #[tokio::test]
+    async fn test_stopped_work_skips_blocking_execution() {
+        let scheduler = Scheduler::new(SchedulerConfig::new(1));
+        let class = SchedulingClass::Local(ProofType::Sp1);
+        let stopped = CancellationToken::new();
+        let permit = scheduler.acquire(class, Priority::Normal).await;
+        let slot = WorkSlot::new(stopped.clone(), permit);
+        stopped.cancel();
+        assert!(slot.run_blocking(|| ()).await.is_err());
+    }
+}
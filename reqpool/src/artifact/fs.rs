@@ -0,0 +1,82 @@
+use super::{ArtifactBackend, ArtifactId};
+use chrono::{DateTime, Utc};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Artifacts stored as files of a local directory, named after their id.
+#[derive(Debug, Clone)]
+pub struct FsArtifactBackend {
+    root: PathBuf,
+}
+
+impl FsArtifactBackend {
+    pub fn open<P: AsRef<Path>>(root: P) -> Result<Self, String> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root)
+            .map_err(|e| format!("failed to create artifact directory {root:?}: {e}"))?;
+        Ok(Self { root })
+    }
+
+    fn path(&self, id: &ArtifactId) -> PathBuf {
+        self.root.join(id.as_str())
+    }
+}
+
+impl ArtifactBackend for FsArtifactBackend {
+    fn put(&self, id: &ArtifactId, data: &[u8]) -> Result<(), String> {
+        // Write to a temporary file first, so that readers never see a partial artifact
+        let tmp_path = self.root.join(format!(
+            "{id}.{}.{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp_path, data).map_err(|e| format!("failed to write artifact {id}: {e}"))?;
+        fs::rename(&tmp_path, self.path(id))
+            .map_err(|e| format!("failed to write artifact {id}: {e}"))
+    }
+
+    fn get(&self, id: &ArtifactId) -> Result<Option<Vec<u8>>, String> {
+        match fs::read(self.path(id)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("failed to read artifact {id}: {e}")),
+        }
+    }
+
+    fn delete(&self, id: &ArtifactId) -> Result<(), String> {
+        match fs::remove_file(self.path(id)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("failed to delete artifact {id}: {e}")),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<(ArtifactId, DateTime<Utc>)>, String> {
+        let entries = fs::read_dir(&self.root)
+            .map_err(|e| format!("failed to list artifact directory {:?}: {e}", self.root))?;
+        let mut artifacts = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| e.to_string())?;
+            // Skip the temporary files and anything else that is not an artifact
+            let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<ArtifactId>().ok())
+            else {
+                continue;
+            };
+            let modified = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map_err(|e| format!("failed to read artifact {id} metadata: {e}"))?;
+            artifacts.push((id, DateTime::<Utc>::from(modified)));
+        }
+        Ok(artifacts)
+    }
+}
@@ -0,0 +1,323 @@
+mod fs;
+mod s3;
+
+pub use fs::FsArtifactBackend;
+pub use s3::{S3ArtifactBackend, S3Config};
+
+use crate::{Status, StatusWithContext};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+/// The content address of an artifact, the hex-encoded sha256 of its bytes.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ArtifactId(String);
+
+impl ArtifactId {
+    pub fn of(data: &[u8]) -> Self {
+        Self(hex::encode(Sha256::digest(data)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for ArtifactId {
+    type Error = String;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        if id.len() == 64 && id.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+            Ok(Self(id))
+        } else {
+            Err(format!("invalid artifact id: {id}"))
+        }
+    }
+}
+
+impl std::str::FromStr for ArtifactId {
+    type Err = String;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Self::try_from(id.to_string())
+    }
+}
+
+impl From<ArtifactId> for String {
+    fn from(id: ArtifactId) -> Self {
+        id.0
+    }
+}
+
+impl std::fmt::Display for ArtifactId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Where the artifacts are stored.
+///
+/// The operations are blocking, like the ones of the pool.
+pub trait ArtifactBackend: std::fmt::Debug + Send + Sync {
+    /// Store the artifact, overwriting it and refreshing its age when it already exists.
+    fn put(&self, id: &ArtifactId, data: &[u8]) -> Result<(), String>;
+
+    fn get(&self, id: &ArtifactId) -> Result<Option<Vec<u8>>, String>;
+
+    fn delete(&self, id: &ArtifactId) -> Result<(), String>;
+
+    /// List the stored artifacts with the time they were stored.
+    fn list(&self) -> Result<Vec<(ArtifactId, DateTime<Utc>)>, String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum ArtifactBackendConfig {
+    /// A directory of the local filesystem
+    Fs { root: PathBuf },
+    /// A bucket of an S3-compatible object storage
+    S3(S3Config),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The configuration of the artifact store
+pub struct ArtifactStoreConfig {
+    #[serde(flatten)]
+    pub backend: ArtifactBackendConfig,
+    /// How long the artifacts are kept, forever if not set
+    #[serde(default)]
+    pub retention_secs: Option<u64>,
+    /// The proofs up to this size are kept inline in the pool
+    #[serde(default = "ArtifactStoreConfig::default_inline_limit")]
+    pub inline_limit: usize,
+}
+
+impl ArtifactStoreConfig {
+    fn default_inline_limit() -> usize {
+        4096
+    }
+}
+
+/// Content-addressed store of the proof artifacts: proofs, guest inputs and batch inputs.
+///
+/// The pool keeps a reference to the artifact of a successful request instead of its content,
+/// so that large artifacts do not live in Redis, and artifacts are pruned on their own
+/// retention instead of the TTL of the pool.
+#[derive(Debug, Clone)]
+pub struct ArtifactStore {
+    backend: Arc<dyn ArtifactBackend>,
+    retention: Option<Duration>,
+    inline_limit: usize,
+}
+
+impl ArtifactStore {
+    pub fn new(backend: Arc<dyn ArtifactBackend>) -> Self {
+        Self {
+            backend,
+            retention: None,
+            inline_limit: ArtifactStoreConfig::default_inline_limit(),
+        }
+    }
+
+    pub fn open(config: ArtifactStoreConfig) -> Result<Self, String> {
+        let backend: Arc<dyn ArtifactBackend> = match config.backend {
+            ArtifactBackendConfig::Fs { root } => Arc::new(FsArtifactBackend::open(root)?),
+            ArtifactBackendConfig::S3(config) => Arc::new(S3ArtifactBackend::new(config)?),
+        };
+        tracing::info!("ArtifactStore.open: {backend:?}");
+        Ok(Self {
+            backend,
+            retention: config.retention_secs.map(Duration::from_secs),
+            inline_limit: config.inline_limit,
+        })
+    }
+
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    pub fn with_inline_limit(mut self, inline_limit: usize) -> Self {
+        self.inline_limit = inline_limit;
+        self
+    }
+
+    pub fn retention(&self) -> Option<Duration> {
+        self.retention
+    }
+
+    pub fn put(&self, data: &[u8]) -> Result<ArtifactId, String> {
+        let id = ArtifactId::of(data);
+        self.backend.put(&id, data)?;
+        Ok(id)
+    }
+
+    pub fn get(&self, id: &ArtifactId) -> Result<Option<Vec<u8>>, String> {
+        self.backend.get(id)
+    }
+
+    /// Delete the artifacts older than the retention, returns how many were deleted.
+    pub fn prune(&self) -> Result<usize, String> {
+        let Some(retention) = self.retention else {
+            return Ok(0);
+        };
+        let retention = chrono::Duration::from_std(retention).map_err(|e| e.to_string())?;
+        let expired_before = Utc::now() - retention;
+        let mut pruned = 0;
+        for (id, stored_at) in self.backend.list()? {
+            if stored_at < expired_before {
+                self.backend.delete(&id)?;
+                pruned += 1;
+            }
+        }
+        tracing::info!("ArtifactStore.prune: {pruned} artifacts");
+        Ok(pruned)
+    }
+
+    /// Move the proof of a successful status into the store, keeping its reference only.
+    pub(crate) fn offload(
+        &self,
+        mut status: StatusWithContext,
+    ) -> Result<StatusWithContext, String> {
+        let Status::Success { proof } = status.status_mut() else {
+            return Ok(status);
+        };
+        if proof
+            .proof
+            .as_ref()
+            .is_none_or(|content| content.len() <= self.inline_limit)
+        {
+            return Ok(status);
+        }
+        let content = proof.proof.take().expect("checked above");
+        let id = self.put(content.as_bytes())?;
+        Ok(status.with_artifact(id))
+    }
+
+    /// Load the proof of a successful status from the store. A status whose artifact is gone
+    /// is failed.
+    pub(crate) fn resolve(
+        &self,
+        mut status: StatusWithContext,
+    ) -> Result<StatusWithContext, String> {
+        let Some(id) = status.artifact().clone() else {
+            return Ok(status);
+        };
+        let Status::Success { proof } = status.status_mut() else {
+            return Ok(status);
+        };
+        let Some(content) = self.get(&id)? else {
+            // Pruned while still referenced, the proof is lost and has to be generated again
+            tracing::warn!("ArtifactStore.resolve: artifact {id} is missing, the proof is lost");
+            return Ok(status.transition(Status::Failed {
+                error: format!("the proof artifact {id} is missing from the store"),
+                header_mismatch: None,
+            }));
+        };
+        proof.proof = Some(String::from_utf8(content).map_err(|e| e.to_string())?);
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raiko_lib::prover::Proof;
+
+    #[test]
+    fn test_artifact_store_offloads_large_proofs() {
+        let root = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::open(ArtifactStoreConfig {
+            backend: ArtifactBackendConfig::Fs {
+                root: root.path().to_path_buf(),
+            },
+            retention_secs: None,
+            inline_limit: 8,
+        })
+        .unwrap();
+        let success = |content: &str| {
+            StatusWithContext::new_registered().transition(Status::Success {
+                proof: Proof {
+                    proof: Some(content.to_string()),
+                    ..Default::default()
+                },
+            })
+        };
+
+        // Small proofs stay inline
+        let small = store.offload(success("small")).unwrap();
+        assert_eq!(small.artifact(), &None);
+        assert!(matches!(
+            small.status(),
+            Status::Success { proof } if proof.proof.as_deref() == Some("small")
+        ));
+
+        // Large proofs are only referenced
+        let large_content = "a large guest input";
+        let large = store.offload(success(large_content)).unwrap();
+        let id = large.artifact().clone().unwrap();
+        assert_eq!(id, ArtifactId::of(large_content.as_bytes()));
+        assert!(matches!(
+            large.status(),
+            Status::Success { proof } if proof.proof.is_none()
+        ));
+        assert_eq!(
+            store.get(&id).unwrap(),
+            Some(large_content.as_bytes().to_vec())
+        );
+
+        let resolved = store.resolve(large).unwrap();
+        assert!(matches!(
+            resolved.status(),
+            Status::Success { proof } if proof.proof.as_deref() == Some(large_content)
+        ));
+    }
+
+    #[test]
+    fn test_artifact_store_fails_lost_proofs() {
+        let root = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::new(Arc::new(FsArtifactBackend::open(root.path()).unwrap()))
+            .with_inline_limit(0)
+            .with_retention(Duration::ZERO);
+        let success = StatusWithContext::new_registered().transition(Status::Success {
+            proof: Proof {
+                proof: Some("proof".to_string()),
+                ..Default::default()
+            },
+        });
+        let offloaded = store.offload(success).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(store.prune().unwrap(), 1);
+
+        let resolved = store.resolve(offloaded).unwrap();
+        assert!(matches!(resolved.status(), Status::Failed { .. }));
+        assert_eq!(resolved.artifact(), &None);
+    }
+
+    #[test]
+    fn test_artifact_store_prunes_expired_artifacts() {
+        let root = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::new(Arc::new(FsArtifactBackend::open(root.path()).unwrap()))
+            .with_retention(Duration::from_secs(3600));
+        let id = store.put(b"artifact").unwrap();
+        assert_eq!(store.prune().unwrap(), 0);
+
+        let store = store.with_retention(Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(store.prune().unwrap(), 1);
+        assert_eq!(store.get(&id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_artifact_id() {
+        let id = ArtifactId::of(b"");
+        assert_eq!(
+            id.as_str(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(id.as_str().parse::<ArtifactId>(), Ok(id));
+        assert!("../etc/passwd".parse::<ArtifactId>().is_err());
+    }
+}
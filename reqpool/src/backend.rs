@@ -1,11 +1,69 @@
-use crate::MemoryBackend;
+use crate::{ballot::BALLOT_DRAW_PREFIX, BallotDraw, Lease, MemoryBackend, SqliteBackend};
+use chrono::{DateTime, Utc};
 use redis::{Commands, FromRedisValue, RedisResult, ToRedisArgs};
 use serde::Serialize;
+use std::time::Duration;
 
-/// A connection wrapper that integrates both Redis and MemoryConnection.
+// Set the lease to the holder unless another holder has it, the expired leases are dropped
+// by redis itself.
+const CLAIM_LEASE_SCRIPT: &str = r#"
+local holder = redis.call('GET', KEYS[1])
+if holder == false or holder == ARGV[1] then
+    redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+const RENEW_LEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+const RELEASE_LEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+// Set the last draw time to now unless the last draw is more recent than the interval.
+const TAKE_BALLOT_TURN_SCRIPT: &str = r#"
+local last = redis.call('GET', KEYS[1])
+if last == false or tonumber(ARGV[1]) - tonumber(last) >= tonumber(ARGV[2]) then
+    redis.call('SET', KEYS[1], ARGV[1])
+    return 1
+end
+return 0
+"#;
+
+// Record the draw unless one is recorded. A drawn proof type limited per day takes its turn
+// first, as in TAKE_BALLOT_TURN_SCRIPT, and the undrawn value is recorded when it is not due.
+const RECORD_BALLOT_DRAW_SCRIPT: &str = r#"
+local recorded = redis.call('GET', KEYS[1])
+if recorded then
+    return recorded
+end
+local value = ARGV[1]
+if KEYS[2] then
+    local last = redis.call('GET', KEYS[2])
+    if last == false or tonumber(ARGV[4]) - tonumber(last) >= tonumber(ARGV[5]) then
+        redis.call('SET', KEYS[2], ARGV[4])
+    else
+        value = ARGV[2]
+    end
+end
+redis.call('SET', KEYS[1], value, 'EX', ARGV[3])
+return value
+"#;
+
+/// A connection wrapper that integrates Redis, MemoryConnection and SQLite.
 pub enum Backend {
     Redis(redis::Connection),
     Memory(MemoryBackend),
+    Sqlite(SqliteBackend),
 }
 
 impl Backend {
@@ -18,6 +76,30 @@ impl Backend {
         match self {
             Backend::Redis(conn) => conn.set_ex(key, val, ttl),
             Backend::Memory(conn) => conn.set_ex(key, val, ttl),
+            Backend::Sqlite(conn) => conn.set_ex(key, val, ttl),
+        }
+    }
+
+    /// Set the value unless the key already exists, returns whether it was set.
+    pub fn set_nx_ex<K: Serialize + ToRedisArgs, V: Serialize + ToRedisArgs>(
+        &mut self,
+        key: K,
+        val: V,
+        ttl: u64,
+    ) -> RedisResult<bool> {
+        match self {
+            Backend::Redis(conn) => {
+                let result: Option<String> = redis::cmd("SET")
+                    .arg(key)
+                    .arg(val)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl)
+                    .query(conn)?;
+                Ok(result.is_some())
+            }
+            Backend::Memory(conn) => conn.set_nx_ex(key, val, ttl),
+            Backend::Sqlite(conn) => conn.set_nx_ex(key, val, ttl),
         }
     }
 
@@ -31,6 +113,7 @@ impl Backend {
         match self {
             Backend::Redis(conn) => conn.get(key),
             Backend::Memory(conn) => conn.get(key),
+            Backend::Sqlite(conn) => conn.get(key),
         }
     }
 
@@ -38,6 +121,7 @@ impl Backend {
         match self {
             Backend::Redis(conn) => conn.del(key),
             Backend::Memory(conn) => conn.del(key),
+            Backend::Sqlite(conn) => conn.del(key),
         }
     }
 
@@ -58,6 +142,171 @@ impl Backend {
                 Ok(keys)
             }
             Backend::Memory(conn) => conn.keys(key),
+            Backend::Sqlite(conn) => conn.keys(key),
+        }
+    }
+
+    /// Set the lease of `key` to `holder` during `ttl`, unless another holder has an unexpired
+    /// lease, returns whether it was set.
+    pub fn claim_lease(&mut self, key: &str, holder: &str, ttl: Duration) -> RedisResult<bool> {
+        match self {
+            Backend::Redis(conn) => redis::Script::new(CLAIM_LEASE_SCRIPT)
+                .key(key)
+                .arg(holder)
+                .arg(ttl.as_millis() as u64)
+                .invoke(conn),
+            Backend::Memory(conn) => conn.claim_lease(key, holder, ttl),
+            Backend::Sqlite(conn) => conn.claim_lease(key, holder, ttl),
         }
     }
+
+    /// Extend the unexpired lease of `holder` on `key`, returns whether it was extended.
+    pub fn renew_lease(&mut self, key: &str, holder: &str, ttl: Duration) -> RedisResult<bool> {
+        match self {
+            Backend::Redis(conn) => redis::Script::new(RENEW_LEASE_SCRIPT)
+                .key(key)
+                .arg(holder)
+                .arg(ttl.as_millis() as u64)
+                .invoke(conn),
+            Backend::Memory(conn) => conn.renew_lease(key, holder, ttl),
+            Backend::Sqlite(conn) => conn.renew_lease(key, holder, ttl),
+        }
+    }
+
+    pub fn release_lease(&mut self, key: &str, holder: &str) -> RedisResult<()> {
+        match self {
+            Backend::Redis(conn) => redis::Script::new(RELEASE_LEASE_SCRIPT)
+                .key(key)
+                .arg(holder)
+                .invoke::<usize>(conn)
+                .map(|_| ()),
+            Backend::Memory(conn) => conn.release_lease(key, holder),
+            Backend::Sqlite(conn) => conn.release_lease(key, holder),
+        }
+    }
+
+    pub fn get_lease(&mut self, key: &str) -> RedisResult<Option<Lease>> {
+        match self {
+            Backend::Redis(conn) => {
+                let (holder, ttl_ms): (Option<String>, i64) = redis::pipe()
+                    .atomic()
+                    .cmd("GET")
+                    .arg(key)
+                    .cmd("PTTL")
+                    .arg(key)
+                    .query(conn)?;
+                // A negative TTL means the key is missing or never expires
+                Ok(holder.filter(|_| ttl_ms > 0).map(|holder| Lease {
+                    holder,
+                    expires_at: Utc::now() + chrono::Duration::milliseconds(ttl_ms),
+                }))
+            }
+            Backend::Memory(conn) => conn.get_lease(key),
+            Backend::Sqlite(conn) => conn.get_lease(key),
+        }
+    }
+
+    /// Record the draw at `key` unless one is already recorded, returns the recorded draw.
+    ///
+    /// With a `turn`, the drawn proof type takes its turn at the key as in
+    /// [`Backend::take_ballot_turn`], and no proof type is recorded when its turn is not due.
+    /// The turn is left untouched when a draw is already recorded.
+    pub fn record_ballot_draw(
+        &mut self,
+        key: &str,
+        draw: &BallotDraw,
+        turn: Option<(&str, Duration)>,
+        ttl: u64,
+    ) -> RedisResult<BallotDraw> {
+        match self {
+            Backend::Redis(conn) => {
+                let value = serde_json::to_string(draw).map_err(json_error)?;
+                let undrawn = serde_json::to_string(&draw.undrawn()).map_err(json_error)?;
+                let script = redis::Script::new(RECORD_BALLOT_DRAW_SCRIPT);
+                let mut invocation = script.key(key);
+                let mut interval_ms = 0;
+                if let Some((turn_key, interval)) = turn {
+                    invocation.key(turn_key);
+                    interval_ms = interval.as_millis() as u64;
+                }
+                let recorded: String = invocation
+                    .arg(value)
+                    .arg(undrawn)
+                    .arg(ttl)
+                    .arg(Utc::now().timestamp_millis())
+                    .arg(interval_ms)
+                    .invoke(conn)?;
+                serde_json::from_str(&recorded).map_err(json_error)
+            }
+            Backend::Memory(conn) => conn.record_ballot_draw(key, draw, turn),
+            Backend::Sqlite(conn) => conn.record_ballot_draw(key, draw, turn, ttl),
+        }
+    }
+
+    pub fn get_ballot_draw(&mut self, key: &str) -> RedisResult<Option<BallotDraw>> {
+        match self {
+            Backend::Redis(conn) => {
+                let value: Option<String> = conn.get(key)?;
+                value
+                    .map(|value| serde_json::from_str(&value).map_err(json_error))
+                    .transpose()
+            }
+            Backend::Memory(conn) => conn.get_ballot_draw(key),
+            Backend::Sqlite(conn) => conn.get_ballot_draw(key),
+        }
+    }
+
+    /// All the recorded draws, in no particular order.
+    pub fn ballot_draws(&mut self) -> RedisResult<Vec<BallotDraw>> {
+        match self {
+            Backend::Redis(conn) => {
+                let keys: Vec<String> = conn.keys(format!("{BALLOT_DRAW_PREFIX}*"))?;
+                if keys.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let values: Vec<Option<String>> = redis::cmd("MGET").arg(keys).query(conn)?;
+                // The draws expired in between are skipped
+                Ok(values
+                    .iter()
+                    .flatten()
+                    .filter_map(|value| serde_json::from_str(value).ok())
+                    .collect())
+            }
+            Backend::Memory(conn) => conn.ballot_draws(),
+            Backend::Sqlite(conn) => conn.ballot_draws(),
+        }
+    }
+
+    /// Set the last draw time at `key` to now, unless the last draw is more recent than
+    /// `interval`, returns whether it was set.
+    pub fn take_ballot_turn(&mut self, key: &str, interval: Duration) -> RedisResult<bool> {
+        match self {
+            Backend::Redis(conn) => redis::Script::new(TAKE_BALLOT_TURN_SCRIPT)
+                .key(key)
+                .arg(Utc::now().timestamp_millis())
+                .arg(interval.as_millis() as u64)
+                .invoke(conn),
+            Backend::Memory(conn) => conn.take_ballot_turn(key, interval),
+            Backend::Sqlite(conn) => conn.take_ballot_turn(key, interval),
+        }
+    }
+
+    pub fn last_ballot_draw_time(&mut self, key: &str) -> RedisResult<Option<DateTime<Utc>>> {
+        match self {
+            Backend::Redis(conn) => {
+                let last_ms: Option<i64> = conn.get(key)?;
+                Ok(last_ms.and_then(DateTime::from_timestamp_millis))
+            }
+            Backend::Memory(conn) => conn.last_ballot_draw_time(key),
+            Backend::Sqlite(conn) => conn.last_ballot_draw_time(key),
+        }
+    }
+}
+
+fn json_error(e: serde_json::Error) -> redis::RedisError {
+    redis::RedisError::from((
+        redis::ErrorKind::TypeError,
+        "deserialization error",
+        e.to_string(),
+    ))
 }
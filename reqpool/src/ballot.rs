@@ -0,0 +1,292 @@
+use crate::Pool;
+use chrono::{DateTime, Utc};
+use raiko_lib::{primitives::BlockHash, proof_type::ProofType};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub(crate) const BALLOT_DRAW_PREFIX: &str = "ballot:draw:";
+
+/// The proof type drawn by the ballot for a request of a block hash, `None` when no proof type
+/// was drawn.
+///
+/// The draws are kept in the pool, so that every host sharing it agrees on the proof type of a
+/// request, across restarts too.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BallotDraw {
+    pub block_hash: BlockHash,
+    /// The block number or batch id of the request, as the batches proposed in the same L1
+    /// block share its hash.
+    #[serde(default)]
+    pub request_id: u64,
+    pub proof_type: Option<ProofType>,
+    pub drawn_at: DateTime<Utc>,
+}
+
+impl BallotDraw {
+    pub fn new(block_hash: BlockHash, request_id: u64, proof_type: Option<ProofType>) -> Self {
+        Self {
+            block_hash,
+            request_id,
+            proof_type,
+            drawn_at: Utc::now(),
+        }
+    }
+
+    /// The same draw with no proof type drawn, recorded when the turn of the drawn one is not
+    /// due.
+    pub(crate) fn undrawn(&self) -> Self {
+        Self {
+            proof_type: None,
+            ..self.clone()
+        }
+    }
+}
+
+fn ballot_draw_key(block_hash: &BlockHash, request_id: u64) -> String {
+    format!("{BALLOT_DRAW_PREFIX}{block_hash}:{request_id}")
+}
+
+fn ballot_turn_key(proof_type: &ProofType) -> String {
+    format!("ballot:last_draw:{proof_type}")
+}
+
+impl Pool {
+    /// The draw recorded for the request of the block hash, if any.
+    pub fn get_ballot_draw(
+        &mut self,
+        block_hash: &BlockHash,
+        request_id: u64,
+    ) -> Result<Option<BallotDraw>, String> {
+        self.conn()
+            .map_err(|e| e.to_string())?
+            .get_ballot_draw(&ballot_draw_key(block_hash, request_id))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Record the draw of a request unless one is already recorded, returns the recorded draw,
+    /// i.e. the one of the host which drew the request first.
+    ///
+    /// A drawn proof type limited to one draw per `interval` takes its turn along with the
+    /// record, as in [`Pool::try_take_ballot_turn`], and is not drawn when its turn is not due.
+    /// Only the host recording the draw takes the turn.
+    pub fn record_ballot_draw(
+        &mut self,
+        draw: BallotDraw,
+        interval: Option<Duration>,
+    ) -> Result<BallotDraw, String> {
+        let turn_key = draw
+            .proof_type
+            .filter(|_| interval.is_some())
+            .map(|proof_type| ballot_turn_key(&proof_type));
+        let turn = turn_key.as_deref().zip(interval);
+        let recorded = self
+            .conn()
+            .map_err(|e| e.to_string())?
+            .record_ballot_draw(
+                &ballot_draw_key(&draw.block_hash, draw.request_id),
+                &draw,
+                turn,
+                self.config.redis_ttl,
+            )
+            .map_err(|e| e.to_string())?;
+        tracing::debug!("RedisPool.record_ballot_draw: {draw:?}, recorded: {recorded:?}");
+        Ok(recorded)
+    }
+
+    /// Take the turn of the proof type to be drawn, unless it was last drawn less than
+    /// `interval` ago, returns whether it was taken.
+    pub fn try_take_ballot_turn(
+        &mut self,
+        proof_type: &ProofType,
+        interval: Duration,
+    ) -> Result<bool, String> {
+        let taken = self
+            .conn()
+            .map_err(|e| e.to_string())?
+            .take_ballot_turn(&ballot_turn_key(proof_type), interval)
+            .map_err(|e| e.to_string())?;
+        tracing::debug!("RedisPool.try_take_ballot_turn: {proof_type}, taken: {taken}");
+        Ok(taken)
+    }
+
+    /// When the proof type was last drawn, if it ever was.
+    pub fn last_ballot_draw_time(
+        &mut self,
+        proof_type: &ProofType,
+    ) -> Result<Option<DateTime<Utc>>, String> {
+        self.conn()
+            .map_err(|e| e.to_string())?
+            .last_ballot_draw_time(&ballot_turn_key(proof_type))
+            .map_err(|e| e.to_string())
+    }
+
+    /// List the recorded draws, the latest first.
+    pub fn list_ballot_draws(&mut self) -> Result<Vec<BallotDraw>, String> {
+        let mut draws = self
+            .conn()
+            .map_err(|e| e.to_string())?
+            .ballot_draws()
+            .map_err(|e| e.to_string())?;
+        draws.sort_by(|a, b| b.drawn_at.cmp(&a.drawn_at));
+        Ok(draws)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{memory_pool, Pool, RedisPoolConfig};
+
+    fn check_ballot_draws(mut pool: Pool) {
+        let block_hash = BlockHash::with_last_byte(1);
+        assert_eq!(pool.get_ballot_draw(&block_hash, 1).unwrap(), None);
+
+        // The first recorded draw of a request wins
+        let draw = BallotDraw::new(block_hash, 1, Some(ProofType::Sp1));
+        assert_eq!(pool.record_ballot_draw(draw.clone(), None).unwrap(), draw);
+        assert_eq!(
+            pool.record_ballot_draw(BallotDraw::new(block_hash, 1, None), None)
+                .unwrap(),
+            draw
+        );
+        assert_eq!(
+            pool.get_ballot_draw(&block_hash, 1).unwrap(),
+            Some(draw.clone())
+        );
+
+        // The other requests of the block hash are drawn apart
+        assert_eq!(pool.get_ballot_draw(&block_hash, 2).unwrap(), None);
+        let sibling = BallotDraw::new(block_hash, 2, None);
+        assert_eq!(
+            pool.record_ballot_draw(sibling.clone(), None).unwrap(),
+            sibling
+        );
+
+        let other = BallotDraw::new(BlockHash::with_last_byte(2), 1, None);
+        pool.record_ballot_draw(other.clone(), None).unwrap();
+        assert_eq!(
+            pool.list_ballot_draws().unwrap(),
+            vec![other, sibling, draw]
+        );
+    }
+
+    fn check_ballot_turns(mut pool: Pool) {
+        let interval = Duration::from_secs(3600);
+        assert_eq!(pool.last_ballot_draw_time(&ProofType::Sp1).unwrap(), None);
+
+        assert!(pool
+            .try_take_ballot_turn(&ProofType::Sp1, interval)
+            .unwrap());
+        assert!(pool
+            .last_ballot_draw_time(&ProofType::Sp1)
+            .unwrap()
+            .is_some());
+        assert!(!pool
+            .try_take_ballot_turn(&ProofType::Sp1, interval)
+            .unwrap());
+        // The turns of the proof types are apart
+        assert!(pool
+            .try_take_ballot_turn(&ProofType::Risc0, interval)
+            .unwrap());
+
+        assert!(pool
+            .try_take_ballot_turn(&ProofType::Native, Duration::from_millis(1))
+            .unwrap());
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(pool
+            .try_take_ballot_turn(&ProofType::Native, Duration::from_millis(1))
+            .unwrap());
+    }
+
+    fn check_ballot_draw_turns(mut pool: Pool) {
+        let interval = Some(Duration::from_secs(3600));
+        let block_hash = BlockHash::with_last_byte(1);
+
+        // The request is recorded by another host first, its turn is left untouched
+        let recorded = BallotDraw::new(block_hash, 1, Some(ProofType::Sgx));
+        pool.record_ballot_draw(recorded.clone(), None).unwrap();
+        assert_eq!(
+            pool.record_ballot_draw(
+                BallotDraw::new(block_hash, 1, Some(ProofType::Sgx)),
+                interval
+            )
+            .unwrap(),
+            recorded
+        );
+        assert_eq!(pool.last_ballot_draw_time(&ProofType::Sgx).unwrap(), None);
+
+        // The host recording the draw takes the turn
+        let draw = BallotDraw::new(block_hash, 2, Some(ProofType::Sgx));
+        assert_eq!(
+            pool.record_ballot_draw(draw.clone(), interval).unwrap(),
+            draw
+        );
+        assert!(pool
+            .last_ballot_draw_time(&ProofType::Sgx)
+            .unwrap()
+            .is_some());
+
+        // The next draw is not due yet, nothing is drawn
+        let draw = BallotDraw::new(block_hash, 3, Some(ProofType::Sgx));
+        assert_eq!(
+            pool.record_ballot_draw(draw.clone(), interval).unwrap(),
+            draw.undrawn()
+        );
+        assert_eq!(
+            pool.get_ballot_draw(&block_hash, 3).unwrap(),
+            Some(draw.undrawn())
+        );
+    }
+
+    #[test]
+    fn test_memory_pool_ballot() {
+        check_ballot_draws(memory_pool("test_memory_pool_ballot_draws"));
+        check_ballot_turns(memory_pool("test_memory_pool_ballot_turns"));
+        check_ballot_draw_turns(memory_pool("test_memory_pool_ballot_draw_turns"));
+    }
+
+    #[test]
+    fn test_sqlite_pool_ballot() {
+        let dir = tempfile::tempdir().unwrap();
+        let open = |name: &str| {
+            Pool::open(RedisPoolConfig {
+                redis_url: "redis://localhost:6379".to_string(),
+                redis_ttl: 3600,
+                enable_redis_pool: false,
+                sqlite_path: Some(dir.path().join(name)),
+            })
+            .unwrap()
+        };
+        check_ballot_draws(open("draws.sqlite"));
+        check_ballot_turns(open("turns.sqlite"));
+        check_ballot_draw_turns(open("draw_turns.sqlite"));
+
+        // The draws and turns are kept across restarts
+        let mut pool = open("draws.sqlite");
+        assert_eq!(
+            pool.get_ballot_draw(&BlockHash::with_last_byte(1), 1)
+                .unwrap()
+                .and_then(|draw| draw.proof_type),
+            Some(ProofType::Sp1)
+        );
+        let mut pool = open("turns.sqlite");
+        assert!(!pool
+            .try_take_ballot_turn(&ProofType::Sp1, Duration::from_secs(3600))
+            .unwrap());
+    }
+
+    #[ignore]
+    #[test]
+    fn test_redis_pool_ballot() {
+        let pool = Pool::open(RedisPoolConfig {
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            redis_ttl: 3600,
+            enable_redis_pool: true,
+            sqlite_path: None,
+        })
+        .unwrap();
+        check_ballot_draws(pool.clone());
+        check_ballot_turns(pool.clone());
+        check_ballot_draw_turns(pool);
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// The configuration for the redis-backend request pool
@@ -10,4 +11,9 @@ pub struct RedisPoolConfig {
 
     /// Whether to use redis-backend, otherwise memory-backend
     pub enable_redis_pool: bool,
+
+    /// The path of an embedded SQLite database, used instead of both redis and memory
+    /// backends when set
+    #[serde(default)]
+    pub sqlite_path: Option<PathBuf>,
 }
@@ -0,0 +1,164 @@
+use crate::{Pool, RequestKey};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The claim of a host on a request, so that a single host of a fleet sharing the pool works
+/// on it at a time.
+///
+/// A lease expires unless its holder renews it, so that the requests of a crashed host are
+/// taken over by another one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    pub holder: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Lease {
+    pub(crate) fn new(holder: &str, ttl: Duration) -> Self {
+        Self {
+            holder: holder.to_string(),
+            expires_at: Utc::now() + chrono::Duration::from_std(ttl).unwrap_or_default(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+fn lease_key(request_key: &RequestKey) -> String {
+    format!(
+        "lease:{}",
+        serde_json::to_string(request_key).expect("request keys are serializable")
+    )
+}
+
+impl Pool {
+    /// Claim the request for `holder` during `ttl`, unless another holder has an unexpired
+    /// lease on it. Claiming a request already held by `holder` renews its lease.
+    pub fn try_claim(
+        &mut self,
+        request_key: &RequestKey,
+        holder: &str,
+        ttl: Duration,
+    ) -> Result<bool, String> {
+        let claimed = self
+            .conn()
+            .map_err(|e| e.to_string())?
+            .claim_lease(&lease_key(request_key), holder, ttl)
+            .map_err(|e| e.to_string())?;
+        tracing::debug!("RedisPool.try_claim: {request_key}, {holder}, claimed: {claimed}");
+        Ok(claimed)
+    }
+
+    /// Extend the lease of `holder` on the request by `ttl`, returns false when the lease was
+    /// lost, expired or taken over by another holder.
+    pub fn renew_lease(
+        &mut self,
+        request_key: &RequestKey,
+        holder: &str,
+        ttl: Duration,
+    ) -> Result<bool, String> {
+        self.conn()
+            .map_err(|e| e.to_string())?
+            .renew_lease(&lease_key(request_key), holder, ttl)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Release the lease of `holder` on the request, a lease of another holder is kept.
+    pub fn release_lease(&mut self, request_key: &RequestKey, holder: &str) -> Result<(), String> {
+        tracing::debug!("RedisPool.release_lease: {request_key}, {holder}");
+        self.conn()
+            .map_err(|e| e.to_string())?
+            .release_lease(&lease_key(request_key), holder)
+            .map_err(|e| e.to_string())
+    }
+
+    /// The unexpired lease on the request, if any.
+    pub fn get_lease(&mut self, request_key: &RequestKey) -> Result<Option<Lease>, String> {
+        self.conn()
+            .map_err(|e| e.to_string())?
+            .get_lease(&lease_key(request_key))
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{memory_pool, GuestInputRequestKey, Pool, RedisPoolConfig};
+    use raiko_lib::primitives::B256;
+
+    fn request_key() -> RequestKey {
+        RequestKey::GuestInput(GuestInputRequestKey::new(1, 1, B256::ZERO))
+    }
+
+    fn check_leases(mut pool: Pool) {
+        let request_key = request_key();
+        let ttl = Duration::from_secs(60);
+        assert_eq!(pool.get_lease(&request_key).unwrap(), None);
+
+        assert!(pool.try_claim(&request_key, "host-a", ttl).unwrap());
+        assert!(!pool.try_claim(&request_key, "host-b", ttl).unwrap());
+        assert!(pool.try_claim(&request_key, "host-a", ttl).unwrap());
+        assert_eq!(
+            pool.get_lease(&request_key)
+                .unwrap()
+                .map(|lease| lease.holder),
+            Some("host-a".to_string())
+        );
+
+        assert!(pool.renew_lease(&request_key, "host-a", ttl).unwrap());
+        assert!(!pool.renew_lease(&request_key, "host-b", ttl).unwrap());
+
+        // Only the holder releases its lease
+        pool.release_lease(&request_key, "host-b").unwrap();
+        assert!(pool.get_lease(&request_key).unwrap().is_some());
+        pool.release_lease(&request_key, "host-a").unwrap();
+        assert_eq!(pool.get_lease(&request_key).unwrap(), None);
+        assert!(!pool.renew_lease(&request_key, "host-a", ttl).unwrap());
+
+        // An expired lease is taken over
+        assert!(pool
+            .try_claim(&request_key, "host-a", Duration::from_millis(1))
+            .unwrap());
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(pool.get_lease(&request_key).unwrap(), None);
+        assert!(!pool.renew_lease(&request_key, "host-a", ttl).unwrap());
+        assert!(pool.try_claim(&request_key, "host-b", ttl).unwrap());
+    }
+
+    #[test]
+    fn test_memory_pool_leases() {
+        check_leases(memory_pool("test_memory_pool_leases"));
+    }
+
+    #[test]
+    fn test_sqlite_pool_leases() {
+        let dir = tempfile::tempdir().unwrap();
+        check_leases(
+            Pool::open(RedisPoolConfig {
+                redis_url: "redis://localhost:6379".to_string(),
+                redis_ttl: 3600,
+                enable_redis_pool: false,
+                sqlite_path: Some(dir.path().join("pool.sqlite")),
+            })
+            .unwrap(),
+        );
+    }
+
+    #[ignore]
+    #[test]
+    fn test_redis_pool_leases() {
+        check_leases(
+            Pool::open(RedisPoolConfig {
+                redis_url: "redis://127.0.0.1:6379".to_string(),
+                redis_ttl: 3600,
+                enable_redis_pool: true,
+                sqlite_path: None,
+            })
+            .unwrap(),
+        );
+    }
+}
@@ -1,19 +1,30 @@
+mod artifact;
 mod backend;
+mod ballot;
 mod config;
+mod lease;
 mod macros;
 mod memory_backend;
 mod pool;
 mod request;
+mod sqlite_backend;
 mod utils;
 
 // Re-export
+pub use artifact::{
+    ArtifactBackend, ArtifactBackendConfig, ArtifactId, ArtifactStore, ArtifactStoreConfig,
+    FsArtifactBackend, S3ArtifactBackend, S3Config,
+};
+pub use ballot::BallotDraw;
 pub use config::RedisPoolConfig;
+pub use lease::Lease;
 pub use memory_backend::{memory_pool, MemoryBackend};
 pub use pool::Pool;
 pub use request::{
     AggregationRequestEntity, AggregationRequestKey, BatchGuestInputRequestEntity,
-    BatchGuestInputRequestKey, BatchProofRequestEntity, BatchProofRequestKey,
-    GuestInputRequestEntity, GuestInputRequestKey, RequestEntity, RequestKey,
+    BatchGuestInputRequestKey, BatchProofRequestEntity, BatchProofRequestKey, FailureClass,
+    GuestInputRequestEntity, GuestInputRequestKey, RequestEntity, RequestFilter, RequestKey,
     SingleProofRequestEntity, SingleProofRequestKey, Status, StatusWithContext,
 };
+pub use sqlite_backend::SqliteBackend;
 pub use utils::proof_key_to_hack_request_key;
@@ -1,4 +1,5 @@
-use crate::{Pool, RedisPoolConfig};
+use crate::{BallotDraw, Lease, Pool, RedisPoolConfig};
+use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use redis::{RedisError, RedisResult};
 use serde::Serialize;
@@ -7,12 +8,19 @@ use std::{
     collections::HashMap,
     num::NonZeroUsize,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use lru::LruCache;
 
 type SingleStorage = Arc<Mutex<LruCache<Value, Value>>>;
 type GlobalStorage = Mutex<HashMap<String, SingleStorage>>;
+type SingleLeases = Arc<Mutex<HashMap<String, Lease>>>;
+type SingleBallots = Arc<Mutex<Ballots>>;
+
+/// The draws are bounded like the former in-memory cache of the ballot, up to 8192 blocks per
+/// day.
+const BALLOT_DRAWS_SIZE: usize = 8192;
 
 lazy_static! {
     // #{redis_url => single_storage}
@@ -20,10 +28,39 @@ lazy_static! {
     // We use redis_url to distinguish different redis database for tests, to prevent
     // data race problem when running multiple tests.
     static ref GLOBAL_STORAGE: GlobalStorage = Mutex::new(HashMap::new());
+
+    // #{redis_url => single_leases}
+    //
+    // Leases are kept apart from the LRU storage, so that they are never evicted.
+    static ref GLOBAL_LEASES: Mutex<HashMap<String, SingleLeases>> = Mutex::new(HashMap::new());
+
+    // #{redis_url => single_ballots}
+    static ref GLOBAL_BALLOTS: Mutex<HashMap<String, SingleBallots>> = Mutex::new(HashMap::new());
+}
+
+struct Ballots {
+    draws: LruCache<String, BallotDraw>,
+    last_draw_times: HashMap<String, DateTime<Utc>>,
+}
+
+impl Ballots {
+    fn take_turn(&mut self, key: &str, interval: Duration) -> bool {
+        let now = Utc::now();
+        let interval = chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::MAX);
+        match self.last_draw_times.get(key) {
+            Some(last) if now.signed_duration_since(last) < interval => false,
+            _ => {
+                self.last_draw_times.insert(key.to_string(), now);
+                true
+            }
+        }
+    }
 }
 
 pub struct MemoryBackend {
     storage: SingleStorage,
+    leases: SingleLeases,
+    ballots: SingleBallots,
 }
 
 impl MemoryBackend {
@@ -34,7 +71,26 @@ impl MemoryBackend {
             .unwrap_or("2048".to_string())
             .parse::<usize>()
             .unwrap_or_else(|_| 2048);
+        let leases = GLOBAL_LEASES
+            .lock()
+            .unwrap()
+            .entry(redis_url.clone())
+            .or_default()
+            .clone();
+        let ballots = GLOBAL_BALLOTS
+            .lock()
+            .unwrap()
+            .entry(redis_url.clone())
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(Ballots {
+                    draws: LruCache::new(NonZeroUsize::new(BALLOT_DRAWS_SIZE).unwrap()),
+                    last_draw_times: HashMap::new(),
+                }))
+            })
+            .clone();
         Self {
+            leases,
+            ballots,
             storage: global
                 .entry(redis_url)
                 .or_insert_with(|| {
@@ -57,6 +113,21 @@ impl MemoryBackend {
         Ok(())
     }
 
+    pub fn set_nx_ex<K: Serialize, V: Serialize>(
+        &mut self,
+        key: K,
+        val: V,
+        _ttl: u64,
+    ) -> RedisResult<bool> {
+        let mut lock = self.storage.lock().unwrap();
+        let key = json!(key);
+        if lock.contains(&key) {
+            return Ok(false);
+        }
+        lock.put(key, json!(val));
+        Ok(true)
+    }
+
     pub fn get<K: Serialize, V: serde::de::DeserializeOwned>(&mut self, key: &K) -> RedisResult<V> {
         let mut lock = self.storage.lock().unwrap();
         match lock.get(&json!(key)) {
@@ -89,6 +160,78 @@ impl MemoryBackend {
             .map(|(k, _)| serde_json::from_value(k.clone()).unwrap())
             .collect())
     }
+
+    pub fn claim_lease(&mut self, key: &str, holder: &str, ttl: Duration) -> RedisResult<bool> {
+        let mut leases = self.leases.lock().unwrap();
+        match leases.get(key) {
+            Some(lease) if lease.holder != holder && !lease.is_expired() => Ok(false),
+            _ => {
+                leases.insert(key.to_string(), Lease::new(holder, ttl));
+                Ok(true)
+            }
+        }
+    }
+
+    pub fn renew_lease(&mut self, key: &str, holder: &str, ttl: Duration) -> RedisResult<bool> {
+        let mut leases = self.leases.lock().unwrap();
+        match leases.get_mut(key) {
+            Some(lease) if lease.holder == holder && !lease.is_expired() => {
+                *lease = Lease::new(holder, ttl);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn release_lease(&mut self, key: &str, holder: &str) -> RedisResult<()> {
+        let mut leases = self.leases.lock().unwrap();
+        if leases.get(key).is_some_and(|lease| lease.holder == holder) {
+            leases.remove(key);
+        }
+        Ok(())
+    }
+
+    pub fn get_lease(&mut self, key: &str) -> RedisResult<Option<Lease>> {
+        let leases = self.leases.lock().unwrap();
+        Ok(leases.get(key).filter(|lease| !lease.is_expired()).cloned())
+    }
+
+    pub fn record_ballot_draw(
+        &mut self,
+        key: &str,
+        draw: &BallotDraw,
+        turn: Option<(&str, Duration)>,
+    ) -> RedisResult<BallotDraw> {
+        let mut ballots = self.ballots.lock().unwrap();
+        if let Some(recorded) = ballots.draws.get(key) {
+            return Ok(recorded.clone());
+        }
+        let draw = match turn {
+            Some((turn_key, interval)) if !ballots.take_turn(turn_key, interval) => draw.undrawn(),
+            _ => draw.clone(),
+        };
+        ballots.draws.put(key.to_string(), draw.clone());
+        Ok(draw)
+    }
+
+    pub fn get_ballot_draw(&mut self, key: &str) -> RedisResult<Option<BallotDraw>> {
+        let mut ballots = self.ballots.lock().unwrap();
+        Ok(ballots.draws.get(key).cloned())
+    }
+
+    pub fn ballot_draws(&mut self) -> RedisResult<Vec<BallotDraw>> {
+        let ballots = self.ballots.lock().unwrap();
+        Ok(ballots.draws.iter().map(|(_, draw)| draw.clone()).collect())
+    }
+
+    pub fn take_ballot_turn(&mut self, key: &str, interval: Duration) -> RedisResult<bool> {
+        Ok(self.ballots.lock().unwrap().take_turn(key, interval))
+    }
+
+    pub fn last_ballot_draw_time(&mut self, key: &str) -> RedisResult<Option<DateTime<Utc>>> {
+        let ballots = self.ballots.lock().unwrap();
+        Ok(ballots.last_draw_times.get(key).cloned())
+    }
 }
 
 /// Return the memory pool with the given id.
@@ -99,6 +242,7 @@ pub fn memory_pool<S: ToString>(id: S) -> Pool {
         redis_ttl: 111,
         redis_url: format!("redis://{}:6379", id.to_string()),
         enable_redis_pool: false,
+        sqlite_path: None,
     };
     Pool::open(config).unwrap()
 }
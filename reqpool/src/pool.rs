@@ -1,6 +1,7 @@
 use crate::{
-    backend::Backend, impl_display_using_json_pretty, proof_key_to_hack_request_key, MemoryBackend,
-    RedisPoolConfig, RequestEntity, RequestKey, StatusWithContext,
+    backend::Backend, impl_display_using_json_pretty, proof_key_to_hack_request_key, ArtifactStore,
+    MemoryBackend, RedisPoolConfig, RequestEntity, RequestFilter, RequestKey, SqliteBackend,
+    StatusWithContext,
 };
 use backoff::{exponential::ExponentialBackoff, SystemClock};
 use raiko_lib::prover::{IdStore, IdWrite, ProofKey, ProverError, ProverResult};
@@ -13,7 +14,11 @@ use std::{collections::HashMap, time::Duration};
 #[derive(Debug, Clone)]
 pub struct Pool {
     client: Client,
-    config: RedisPoolConfig,
+    pub(crate) config: RedisPoolConfig,
+    /// The embedded database, shared by the connections of the pool
+    sqlite: Option<SqliteBackend>,
+    /// Where the large proofs are kept, inline in the pool when not set
+    artifacts: Option<ArtifactStore>,
 }
 
 impl Pool {
@@ -24,6 +29,7 @@ impl Pool {
         status: StatusWithContext,
     ) -> Result<(), String> {
         tracing::info!("RedisPool.add: {request_key}, {status}");
+        let status = self.offload(status)?;
         let request_entity_and_status = RequestEntityAndStatus {
             entity: request_entity,
             status,
@@ -39,6 +45,33 @@ impl Pool {
         Ok(())
     }
 
+    /// Add the request unless it is already in the pool, returns whether it was added.
+    ///
+    /// Hosts sharing the pool may receive the same request, only one of them registers it.
+    pub fn try_add(
+        &mut self,
+        request_key: RequestKey,
+        request_entity: RequestEntity,
+        status: StatusWithContext,
+    ) -> Result<bool, String> {
+        let status = self.offload(status)?;
+        let request_entity_and_status = RequestEntityAndStatus {
+            entity: request_entity,
+            status,
+        };
+        let added = self
+            .conn()
+            .map_err(|e| e.to_string())?
+            .set_nx_ex(
+                request_key.clone(),
+                request_entity_and_status,
+                self.config.redis_ttl,
+            )
+            .map_err(|e| e.to_string())?;
+        tracing::info!("RedisPool.try_add: {request_key}, added: {added}");
+        Ok(added)
+    }
+
     pub fn remove(&mut self, request_key: &RequestKey) -> Result<usize, String> {
         tracing::info!("RedisPool.remove: {request_key}");
         let result: usize = self
@@ -49,9 +82,29 @@ impl Pool {
         Ok(result)
     }
 
+    /// Get the request, with the proof of its status loaded from the artifact store. A request
+    /// whose proof was pruned from the store is failed.
     pub fn get(
         &mut self,
         request_key: &RequestKey,
+    ) -> Result<Option<(RequestEntity, StatusWithContext)>, String> {
+        let Some((entity, stored)) = self.get_stored(request_key)? else {
+            return Ok(None);
+        };
+        let Some(artifacts) = &self.artifacts else {
+            return Ok(Some((entity, stored)));
+        };
+        let status = artifacts.resolve(stored.clone())?;
+        if stored.artifact().is_some() && status.artifact().is_none() {
+            self.add(request_key.clone(), entity.clone(), status.clone())?;
+        }
+        Ok(Some((entity, status)))
+    }
+
+    // Get the request as stored, its status only referencing its artifact.
+    fn get_stored(
+        &mut self,
+        request_key: &RequestKey,
     ) -> Result<Option<(RequestEntity, StatusWithContext)>, String> {
         let result: RedisResult<RequestEntityAndStatus> =
             self.conn().map_err(|e| e.to_string())?.get(request_key);
@@ -71,13 +124,22 @@ impl Pool {
         result
     }
 
+    /// Move the large proof of `status` to the artifact store, as [`Pool::update_status`]
+    /// would, so that callers can upload it before taking their own locks.
+    pub fn offload(&self, status: StatusWithContext) -> Result<StatusWithContext, String> {
+        match &self.artifacts {
+            Some(artifacts) => artifacts.offload(status),
+            None => Ok(status),
+        }
+    }
+
     pub fn update_status(
         &mut self,
         request_key: RequestKey,
         status: StatusWithContext,
     ) -> Result<StatusWithContext, String> {
         tracing::info!("RedisPool.update_status: {request_key}, {status}");
-        match self.get(&request_key)? {
+        match self.get_stored(&request_key)? {
             Some((entity, old_status)) => {
                 self.add(request_key, entity, status)?;
                 Ok(old_status)
@@ -86,19 +148,38 @@ impl Pool {
         }
     }
 
+    /// List the requests, the artifacts of their statuses are not loaded.
     pub fn list(&mut self) -> Result<HashMap<RequestKey, StatusWithContext>, String> {
-        let mut conn = self.conn().map_err(|e| e.to_string())?;
-        let keys: Vec<RequestKey> = conn.keys("*").map_err(|e| e.to_string())?;
+        self.list_by(&RequestFilter::default())
+    }
+
+    /// List the requests matching `filter`, looked up by the indexes of the SQLite backend and
+    /// by scanning the pool otherwise. The artifacts of their statuses are not loaded.
+    pub fn list_by(
+        &mut self,
+        filter: &RequestFilter,
+    ) -> Result<HashMap<RequestKey, StatusWithContext>, String> {
+        let keys: Vec<RequestKey> = match self.conn().map_err(|e| e.to_string())? {
+            Backend::Sqlite(mut conn) => conn.keys_by(filter),
+            mut conn => conn.keys("*"),
+        }
+        .map_err(|e| e.to_string())?;
 
         let mut result = HashMap::new();
         for key in keys {
-            if let Ok(Some((_, status))) = self.get(&key) {
-                result.insert(key, status);
+            if let Ok(Some((_, status))) = self.get_stored(&key) {
+                if filter.matches(&key, &status) {
+                    result.insert(key, status);
+                }
             }
         }
 
         Ok(result)
     }
+
+    pub fn artifact_store(&self) -> Option<&ArtifactStore> {
+        self.artifacts.as_ref()
+    }
 }
 
 #[async_trait::async_trait]
@@ -148,18 +229,40 @@ impl IdWrite for Pool {
 
 impl Pool {
     pub fn open(config: RedisPoolConfig) -> Result<Self, redis::RedisError> {
-        if config.enable_redis_pool {
-            tracing::info!("RedisPool.open using redis: {}", config.redis_url);
-        } else {
-            tracing::info!("RedisPool.open using memory pool");
-        }
+        let sqlite = match &config.sqlite_path {
+            Some(path) => {
+                tracing::info!("RedisPool.open using sqlite: {path:?}");
+                Some(SqliteBackend::open(path)?)
+            }
+            None if config.enable_redis_pool => {
+                tracing::info!("RedisPool.open using redis: {}", config.redis_url);
+                None
+            }
+            None => {
+                tracing::info!("RedisPool.open using memory pool");
+                None
+            }
+        };
 
         let client = Client::open(config.redis_url.clone())?;
-        Ok(Self { client, config })
+        Ok(Self {
+            client,
+            config,
+            sqlite,
+            artifacts: None,
+        })
+    }
+
+    /// Keep the large proofs in `artifacts`, only referencing them from the pool.
+    pub fn with_artifact_store(mut self, artifacts: ArtifactStore) -> Self {
+        self.artifacts = Some(artifacts);
+        self
     }
 
     pub fn conn(&mut self) -> Result<Backend, redis::RedisError> {
-        if self.config.enable_redis_pool {
+        if let Some(sqlite) = &self.sqlite {
+            Ok(Backend::Sqlite(sqlite.clone()))
+        } else if self.config.enable_redis_pool {
             Ok(Backend::Redis(self.redis_conn()?))
         } else {
             Ok(Backend::Memory(MemoryBackend::new(
@@ -241,6 +344,7 @@ mod tests {
             enable_redis_pool: true,
             redis_url: "redis://127.0.0.1:6379".to_string(),
             redis_ttl: 3600,
+            sqlite_path: None,
         };
         let mut pool = Pool::open(config).map_err(|e| e.to_string()).unwrap();
 
@@ -281,4 +385,118 @@ mod tests {
         assert_eq!(result.len(), 1);
         assert!(result.contains_key(&request_key));
     }
+
+    #[test]
+    fn test_pool_try_add() {
+        let mut pool = crate::memory_pool("test_pool_try_add");
+        let request_key = RequestKey::SingleProof(SingleProofRequestKey::new(
+            1,
+            1234,
+            B256::ZERO,
+            ProofType::Native,
+            "0x1234567890123456789012345678901234567890".to_string(),
+        ));
+        let request_entity = RequestEntity::SingleProof(SingleProofRequestEntity::new(
+            1234,
+            5678,
+            "sepolia".to_string(),
+            "sepolia".to_string(),
+            B256::ZERO,
+            Address::ZERO,
+            ProofType::Native,
+            BlobProofType::ProofOfEquivalence,
+            HashMap::new(),
+        ));
+
+        assert!(pool
+            .try_add(
+                request_key.clone(),
+                request_entity.clone(),
+                StatusWithContext::new_registered(),
+            )
+            .unwrap());
+        // The first registration wins
+        assert!(!pool
+            .try_add(
+                request_key.clone(),
+                request_entity,
+                StatusWithContext::new_cancelled(),
+            )
+            .unwrap());
+        let status = pool.get_status(&request_key).unwrap().unwrap();
+        assert_eq!(status.status(), &crate::Status::Registered);
+    }
+
+    #[test]
+    fn test_pool_keeps_large_proofs_in_artifact_store() {
+        let root = tempfile::tempdir().unwrap();
+        let artifacts = ArtifactStore::new(std::sync::Arc::new(
+            crate::FsArtifactBackend::open(root.path()).unwrap(),
+        ))
+        .with_inline_limit(8)
+        .with_retention(Duration::ZERO);
+        let mut pool = crate::memory_pool("test_pool_keeps_large_proofs_in_artifact_store")
+            .with_artifact_store(artifacts);
+
+        let request_key = RequestKey::SingleProof(SingleProofRequestKey::new(
+            1,
+            1234,
+            B256::ZERO,
+            ProofType::Native,
+            "0x1234567890123456789012345678901234567890".to_string(),
+        ));
+        let request_entity = RequestEntity::SingleProof(SingleProofRequestEntity::new(
+            1234,
+            5678,
+            "sepolia".to_string(),
+            "sepolia".to_string(),
+            B256::ZERO,
+            Address::ZERO,
+            ProofType::Native,
+            BlobProofType::ProofOfEquivalence,
+            HashMap::new(),
+        ));
+        pool.add(
+            request_key.clone(),
+            request_entity,
+            StatusWithContext::new_registered(),
+        )
+        .unwrap();
+
+        let content = "a proof larger than the inline limit";
+        let proven = StatusWithContext::new_registered().transition(crate::Status::Success {
+            proof: raiko_lib::prover::Proof {
+                proof: Some(content.to_string()),
+                ..Default::default()
+            },
+        });
+        pool.update_status(request_key.clone(), proven).unwrap();
+
+        // The pool only references the proof
+        let listed = pool.list().unwrap().remove(&request_key).unwrap();
+        let id = listed.artifact().clone().unwrap();
+        assert!(matches!(
+            listed.status(),
+            crate::Status::Success { proof } if proof.proof.is_none()
+        ));
+        assert_eq!(
+            pool.artifact_store().unwrap().get(&id).unwrap(),
+            Some(content.as_bytes().to_vec())
+        );
+
+        // Getting the request loads the proof back
+        let status = pool.get_status(&request_key).unwrap().unwrap();
+        assert!(matches!(
+            status.status(),
+            crate::Status::Success { proof } if proof.proof.as_deref() == Some(content)
+        ));
+
+        // A proof pruned while still referenced is lost, failing the request
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(pool.artifact_store().unwrap().prune().unwrap(), 1);
+        let status = pool.get_status(&request_key).unwrap().unwrap();
+        assert!(matches!(status.status(), crate::Status::Failed { .. }));
+        let listed = pool.list().unwrap().remove(&request_key).unwrap();
+        assert!(matches!(listed.status(), crate::Status::Failed { .. }));
+    }
 }
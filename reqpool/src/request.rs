@@ -1,8 +1,8 @@
-use crate::impl_display_using_json_pretty;
+use crate::{artifact::ArtifactId, impl_display_using_json_pretty};
 use alloy_primitives::Address;
 use chrono::{DateTime, Utc};
 use derive_getters::Getters;
-use raiko_core::interfaces::ProverSpecificOpts;
+use raiko_core::{interfaces::ProverSpecificOpts, HeaderMismatch};
 use raiko_lib::{
     input::BlobProofType,
     primitives::{ChainId, B256},
@@ -47,13 +47,66 @@ pub enum Status {
     Failed {
         /// The error message
         error: String,
+        /// The diverging header fields, when the re-executed block does not match
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        header_mismatch: Option<HeaderMismatch>,
     },
 }
 
 impl Status {
+    /// The names of all the statuses, see [`Status::name`]
+    pub const NAMES: [&'static str; 5] = [
+        "registered",
+        "work_in_progress",
+        "success",
+        "cancelled",
+        "failed",
+    ];
+
     pub fn is_success(&self) -> bool {
         matches!(self, Status::Success { .. })
     }
+
+    /// Whether the request reached a final status, no work is left to do on it.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            Status::Success { .. } | Status::Cancelled | Status::Failed { .. }
+        )
+    }
+
+    /// The name of the status, regardless of its content, e.g. "work_in_progress"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Registered => "registered",
+            Status::WorkInProgress => "work_in_progress",
+            Status::Success { .. } => "success",
+            Status::Cancelled => "cancelled",
+            Status::Failed { .. } => "failed",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+/// What a failed attempt of a request failed on
+pub enum FailureClass {
+    /// The RPC nodes were unreachable or returned an error
+    Rpc,
+    /// The blobs of the block could not be fetched
+    BlobUnavailable,
+    /// The re-executed block does not match the expected one
+    ExecutionMismatch,
+    /// The prover ran out of memory
+    ProverOutOfMemory,
+    /// The remote prover did not answer in time
+    ProverTimeout,
+    /// The request was still unfinished at its deadline
+    DeadlineExceeded,
+    /// A request it depends on failed or was cancelled
+    DependencyFailed,
+    /// Any other error
+    Other,
 }
 
 #[derive(
@@ -65,11 +118,57 @@ pub struct StatusWithContext {
     status: Status,
     /// The timestamp of the status
     timestamp: DateTime<Utc>,
+    /// The number of times the request was started
+    #[serde(default)]
+    attempts: u32,
+    /// What the last failed attempt failed on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_failure: Option<FailureClass>,
+    /// The artifact store entry holding the proof of a successful request, when it is too
+    /// large to be kept in the pool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    artifact: Option<ArtifactId>,
 }
 
 impl StatusWithContext {
     pub fn new(status: Status, timestamp: DateTime<Utc>) -> Self {
-        Self { status, timestamp }
+        Self {
+            status,
+            timestamp,
+            attempts: 0,
+            last_failure: None,
+            artifact: None,
+        }
+    }
+
+    /// Move the request to `status`, keeping its attempts history.
+    pub fn transition(&self, status: Status) -> Self {
+        Self {
+            status,
+            timestamp: chrono::Utc::now(),
+            attempts: self.attempts,
+            last_failure: self.last_failure,
+            artifact: None,
+        }
+    }
+
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    pub fn with_last_failure(mut self, last_failure: FailureClass) -> Self {
+        self.last_failure = Some(last_failure);
+        self
+    }
+
+    pub(crate) fn with_artifact(mut self, artifact: ArtifactId) -> Self {
+        self.artifact = Some(artifact);
+        self
+    }
+
+    pub(crate) fn status_mut(&mut self) -> &mut Status {
+        &mut self.status
     }
 
     pub fn new_registered() -> Self {
@@ -112,6 +211,72 @@ impl RequestKey {
             RequestKey::BatchProof(key) => &key.proof_type,
         }
     }
+
+    /// The chain of the request, `None` for the aggregation requests which do not track it.
+    pub fn chain_id(&self) -> Option<ChainId> {
+        match self {
+            RequestKey::GuestInput(key) => Some(key.chain_id),
+            RequestKey::SingleProof(key) => Some(key.chain_id),
+            RequestKey::Aggregation(_) => None,
+            RequestKey::BatchGuestInput(key) => Some(key.chain_id),
+            RequestKey::BatchProof(key) => Some(key.guest_input_key.chain_id),
+        }
+    }
+
+    /// The block numbers of the request, or its batch id for the batch requests.
+    pub fn numbers(&self) -> &[u64] {
+        match self {
+            RequestKey::GuestInput(key) => std::slice::from_ref(&key.block_number),
+            RequestKey::SingleProof(key) => std::slice::from_ref(&key.block_number),
+            RequestKey::Aggregation(key) => &key.block_numbers,
+            RequestKey::BatchGuestInput(key) => std::slice::from_ref(&key.batch_id),
+            RequestKey::BatchProof(key) => std::slice::from_ref(&key.guest_input_key.batch_id),
+        }
+    }
+}
+
+/// Filter of the requests listed from the pool, the unset fields match any request
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestFilter {
+    /// The name of the status, see [`Status::name`]
+    pub status: Option<String>,
+    pub proof_type: Option<ProofType>,
+    pub chain_id: Option<ChainId>,
+    /// The lowest block number or batch id, see [`RequestKey::numbers`]
+    pub from: Option<u64>,
+    /// The highest block number or batch id, inclusive
+    pub to: Option<u64>,
+    /// The earliest timestamp of the status
+    pub since: Option<DateTime<Utc>>,
+    /// The latest timestamp of the status, inclusive
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl RequestFilter {
+    pub fn matches(&self, request_key: &RequestKey, status: &StatusWithContext) -> bool {
+        self.status
+            .as_ref()
+            .is_none_or(|name| name == status.status().name())
+            && self
+                .proof_type
+                .is_none_or(|proof_type| &proof_type == request_key.proof_type())
+            && self
+                .chain_id
+                .is_none_or(|chain_id| Some(chain_id) == request_key.chain_id())
+            && self.matches_range(request_key)
+            && self.since.is_none_or(|since| status.timestamp >= since)
+            && self.until.is_none_or(|until| status.timestamp <= until)
+    }
+
+    // An aggregation matches when any of its block numbers is in the range.
+    fn matches_range(&self, request_key: &RequestKey) -> bool {
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+        request_key.numbers().iter().any(|number| {
+            self.from.is_none_or(|from| *number >= from) && self.to.is_none_or(|to| *number <= to)
+        })
+    }
 }
 
 /// The key to identify a request in the pool
@@ -353,6 +518,9 @@ pub struct SingleProofRequestEntity {
     #[serde(flatten)]
     /// Additional prover params.
     prover_args: HashMap<String, serde_json::Value>,
+    /// When the proof stops being useful, overdue work is cancelled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deadline: Option<DateTime<Utc>>,
 }
 
 impl SingleProofRequestEntity {
@@ -377,8 +545,14 @@ impl SingleProofRequestEntity {
             proof_type,
             blob_proof_type,
             prover_args,
+            deadline: None,
         }
     }
+
+    pub fn with_deadline(mut self, deadline: Option<DateTime<Utc>>) -> Self {
+        self.deadline = deadline;
+        self
+    }
 }
 
 #[derive(PartialEq, Debug, Clone, Deserialize, Serialize, RedisValue, Getters)]
@@ -392,6 +566,12 @@ pub struct AggregationRequestEntity {
     #[serde(flatten)]
     /// Any additional prover params in JSON format.
     prover_args: ProverSpecificOpts,
+    /// When the proof stops being useful, overdue work is cancelled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deadline: Option<DateTime<Utc>>,
+    /// The sub-requests whose proofs are aggregated, when `proofs` are not given upfront.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    depends_on: Vec<RequestKey>,
 }
 
 impl AggregationRequestEntity {
@@ -406,8 +586,25 @@ impl AggregationRequestEntity {
             proofs,
             proof_type,
             prover_args,
+            deadline: None,
+            depends_on: Vec::new(),
         }
     }
+
+    pub fn with_deadline(mut self, deadline: Option<DateTime<Utc>>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn with_dependencies(mut self, depends_on: Vec<RequestKey>) -> Self {
+        self.depends_on = depends_on;
+        self
+    }
+
+    pub fn with_proofs(mut self, proofs: Vec<Proof>) -> Self {
+        self.proofs = proofs;
+        self
+    }
 }
 
 #[serde_as]
@@ -461,6 +658,12 @@ pub struct BatchProofRequestEntity {
     #[serde(flatten)]
     /// Additional prover params.
     prover_args: HashMap<String, serde_json::Value>,
+    /// When the proof stops being useful, overdue work is cancelled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deadline: Option<DateTime<Utc>>,
+    /// The batch guest input request, whose input is proven.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    depends_on: Vec<RequestKey>,
 }
 
 impl BatchProofRequestEntity {
@@ -487,6 +690,8 @@ impl BatchProofRequestEntity {
             prover,
             proof_type,
             prover_args,
+            deadline: None,
+            depends_on: Vec::new(),
         }
     }
 
@@ -501,8 +706,20 @@ impl BatchProofRequestEntity {
             prover,
             proof_type,
             prover_args,
+            deadline: None,
+            depends_on: Vec::new(),
         }
     }
+
+    pub fn with_deadline(mut self, deadline: Option<DateTime<Utc>>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn with_dependencies(mut self, depends_on: Vec<RequestKey>) -> Self {
+        self.depends_on = depends_on;
+        self
+    }
 }
 
 /// The entity of a request
@@ -515,6 +732,39 @@ pub enum RequestEntity {
     BatchProof(BatchProofRequestEntity),
 }
 
+impl RequestEntity {
+    /// The deadline of the proof, guest inputs have none.
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        match self {
+            RequestEntity::SingleProof(entity) => entity.deadline,
+            RequestEntity::Aggregation(entity) => entity.deadline,
+            RequestEntity::BatchProof(entity) => entity.deadline,
+            RequestEntity::GuestInput(_) | RequestEntity::BatchGuestInput(_) => None,
+        }
+    }
+
+    /// The requests that must succeed before this one is started, their proofs are the
+    /// inputs of this one.
+    pub fn dependencies(&self) -> &[RequestKey] {
+        match self {
+            RequestEntity::Aggregation(entity) => &entity.depends_on,
+            RequestEntity::BatchProof(entity) => &entity.depends_on,
+            RequestEntity::GuestInput(_)
+            | RequestEntity::SingleProof(_)
+            | RequestEntity::BatchGuestInput(_) => &[],
+        }
+    }
+
+    pub fn proof_type(&self) -> ProofType {
+        match self {
+            RequestEntity::SingleProof(entity) => entity.proof_type,
+            RequestEntity::Aggregation(entity) => entity.proof_type,
+            RequestEntity::BatchProof(entity) => entity.proof_type,
+            RequestEntity::GuestInput(_) | RequestEntity::BatchGuestInput(_) => ProofType::Native,
+        }
+    }
+}
+
 impl From<GuestInputRequestEntity> for RequestEntity {
     fn from(entity: GuestInputRequestEntity) -> Self {
         RequestEntity::GuestInput(entity)
@@ -565,7 +815,7 @@ impl std::fmt::Display for Status {
             Status::WorkInProgress => write!(f, "WorkInProgress"),
             Status::Success { .. } => write!(f, "Success"),
             Status::Cancelled => write!(f, "Cancelled"),
-            Status::Failed { error } => write!(f, "Failed({})", error),
+            Status::Failed { error, .. } => write!(f, "Failed({})", error),
         }
     }
 }
@@ -0,0 +1,550 @@
+use crate::{BallotDraw, Lease, RequestFilter, RequestKey, StatusWithContext};
+use chrono::{DateTime, Utc};
+use redis::{RedisError, RedisResult};
+use rusqlite::{
+    params, params_from_iter, types::Value as SqlValue, Connection, OptionalExtension,
+    TransactionBehavior,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS entries (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL,
+    expires_at INTEGER NOT NULL,
+    status TEXT,
+    proof_type TEXT,
+    chain_id INTEGER
+);
+CREATE INDEX IF NOT EXISTS entries_expires_at ON entries (expires_at);
+CREATE INDEX IF NOT EXISTS entries_status ON entries (status);
+CREATE INDEX IF NOT EXISTS entries_proof_type ON entries (proof_type);
+CREATE INDEX IF NOT EXISTS entries_chain_id ON entries (chain_id);
+CREATE TABLE IF NOT EXISTS leases (
+    key TEXT PRIMARY KEY NOT NULL,
+    holder TEXT NOT NULL,
+    expires_at_ms INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS ballot_draws (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS ballot_last_draws (
+    key TEXT PRIMARY KEY NOT NULL,
+    drawn_at_ms INTEGER NOT NULL
+);
+";
+
+/// An embedded SQLite database, keeping the requests of a single node across restarts
+/// without running Redis.
+///
+/// Entries expire after their TTL like the Redis ones. The status, proof type and chain of
+/// the requests are kept in indexed columns, see [`SqliteBackend::keys_by`].
+#[derive(Debug, Clone)]
+pub struct SqliteBackend {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteBackend {
+    pub fn open<P: AsRef<Path>>(path: P) -> RedisResult<Self> {
+        Self::init(Connection::open(path).map_err(sqlite_error)?)
+    }
+
+    pub fn open_in_memory() -> RedisResult<Self> {
+        Self::init(Connection::open_in_memory().map_err(sqlite_error)?)
+    }
+
+    fn init(conn: Connection) -> RedisResult<Self> {
+        // Readers do not block the writer with write-ahead logging
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))
+            .map_err(sqlite_error)?;
+        conn.execute_batch(SCHEMA).map_err(sqlite_error)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    pub fn set_ex<K: Serialize, V: Serialize>(
+        &mut self,
+        key: K,
+        val: V,
+        ttl: u64,
+    ) -> RedisResult<()> {
+        let key = json!(key);
+        let val = json!(val);
+        let (status, proof_type, chain_id) = index_columns(&key, &val);
+        let now = Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        // Expired entries are skipped by the reads, drop them on the writes
+        conn.execute("DELETE FROM entries WHERE expires_at <= ?1", params![now])
+            .map_err(sqlite_error)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO entries (key, value, expires_at, status, proof_type, chain_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                key.to_string(),
+                val.to_string(),
+                now.saturating_add(ttl as i64),
+                status,
+                proof_type,
+                chain_id
+            ],
+        )
+        .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    pub fn set_nx_ex<K: Serialize, V: Serialize>(
+        &mut self,
+        key: K,
+        val: V,
+        ttl: u64,
+    ) -> RedisResult<bool> {
+        let key = json!(key);
+        let val = json!(val);
+        let (status, proof_type, chain_id) = index_columns(&key, &val);
+        let now = Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        // An expired entry is replaced as if it were missing
+        let changes = conn
+            .execute(
+                "INSERT INTO entries (key, value, expires_at, status, proof_type, chain_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (key) DO UPDATE SET
+                     value = excluded.value,
+                     expires_at = excluded.expires_at,
+                     status = excluded.status,
+                     proof_type = excluded.proof_type,
+                     chain_id = excluded.chain_id
+                 WHERE entries.expires_at <= ?7",
+                params![
+                    key.to_string(),
+                    val.to_string(),
+                    now.saturating_add(ttl as i64),
+                    status,
+                    proof_type,
+                    chain_id,
+                    now
+                ],
+            )
+            .map_err(sqlite_error)?;
+        Ok(changes == 1)
+    }
+
+    pub fn get<K: Serialize, V: serde::de::DeserializeOwned>(&mut self, key: &K) -> RedisResult<V> {
+        let conn = self.conn.lock().unwrap();
+        let value: Option<String> = conn
+            .query_row(
+                "SELECT value FROM entries WHERE key = ?1 AND expires_at > ?2",
+                params![json!(key).to_string(), Utc::now().timestamp()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        match value {
+            None => Err(RedisError::from((redis::ErrorKind::TypeError, "not found"))),
+            Some(v) => serde_json::from_str(&v).map_err(|e| {
+                RedisError::from((
+                    redis::ErrorKind::TypeError,
+                    "deserialization error",
+                    e.to_string(),
+                ))
+            }),
+        }
+    }
+
+    pub fn del<K: Serialize>(&mut self, key: K) -> RedisResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM entries WHERE key = ?1",
+            params![json!(key).to_string()],
+        )
+        .map_err(sqlite_error)
+    }
+
+    pub fn keys<K: serde::de::DeserializeOwned>(&mut self, key: &str) -> RedisResult<Vec<K>> {
+        assert_eq!(key, "*", "sqlite backend only supports '*'");
+        self.keys_by(&RequestFilter::default())
+    }
+
+    /// The keys of the unexpired requests matching `filter`, looked up by the indexes.
+    ///
+    /// With an empty filter, the keys of the entries which are not requests are returned too.
+    pub fn keys_by<K: serde::de::DeserializeOwned>(
+        &mut self,
+        filter: &RequestFilter,
+    ) -> RedisResult<Vec<K>> {
+        let mut sql = "SELECT key FROM entries WHERE expires_at > ?".to_string();
+        let mut args = vec![SqlValue::Integer(Utc::now().timestamp())];
+        if let Some(status) = &filter.status {
+            sql.push_str(" AND status = ?");
+            args.push(SqlValue::Text(status.clone()));
+        }
+        if let Some(proof_type) = &filter.proof_type {
+            sql.push_str(" AND proof_type = ?");
+            args.push(SqlValue::Text(proof_type.to_string()));
+        }
+        if let Some(chain_id) = filter.chain_id {
+            sql.push_str(" AND chain_id = ?");
+            args.push(SqlValue::Integer(chain_id as i64));
+        }
+
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(&sql).map_err(sqlite_error)?;
+        let rows = statement
+            .query_map(params_from_iter(args), |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        let mut keys = Vec::new();
+        for row in rows {
+            // Skip the keys of another type, like the ones of the stored proof ids
+            if let Ok(key) = serde_json::from_str(&row.map_err(sqlite_error)?) {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    pub fn claim_lease(&mut self, key: &str, holder: &str, ttl: Duration) -> RedisResult<bool> {
+        let now = Utc::now();
+        let conn = self.conn.lock().unwrap();
+        // A single statement, so that hosts sharing the database never both claim a request
+        let changes = conn
+            .execute(
+                "INSERT INTO leases (key, holder, expires_at_ms) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (key) DO UPDATE SET
+                     holder = excluded.holder,
+                     expires_at_ms = excluded.expires_at_ms
+                 WHERE leases.holder = excluded.holder OR leases.expires_at_ms <= ?4",
+                params![key, holder, expires_at_ms(now, ttl), now.timestamp_millis()],
+            )
+            .map_err(sqlite_error)?;
+        Ok(changes == 1)
+    }
+
+    pub fn renew_lease(&mut self, key: &str, holder: &str, ttl: Duration) -> RedisResult<bool> {
+        let now = Utc::now();
+        let conn = self.conn.lock().unwrap();
+        let changes = conn
+            .execute(
+                "UPDATE leases SET expires_at_ms = ?3
+                 WHERE key = ?1 AND holder = ?2 AND expires_at_ms > ?4",
+                params![key, holder, expires_at_ms(now, ttl), now.timestamp_millis()],
+            )
+            .map_err(sqlite_error)?;
+        Ok(changes == 1)
+    }
+
+    pub fn release_lease(&mut self, key: &str, holder: &str) -> RedisResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM leases WHERE key = ?1 AND holder = ?2",
+            params![key, holder],
+        )
+        .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    pub fn get_lease(&mut self, key: &str) -> RedisResult<Option<Lease>> {
+        let conn = self.conn.lock().unwrap();
+        let lease: Option<(String, i64)> = conn
+            .query_row(
+                "SELECT holder, expires_at_ms FROM leases WHERE key = ?1 AND expires_at_ms > ?2",
+                params![key, Utc::now().timestamp_millis()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        Ok(lease.and_then(|(holder, expires_at_ms)| {
+            Some(Lease {
+                holder,
+                expires_at: DateTime::from_timestamp_millis(expires_at_ms)?,
+            })
+        }))
+    }
+
+    pub fn record_ballot_draw(
+        &mut self,
+        key: &str,
+        draw: &BallotDraw,
+        turn: Option<(&str, Duration)>,
+        ttl: u64,
+    ) -> RedisResult<BallotDraw> {
+        let now = Utc::now().timestamp();
+        let mut conn = self.conn.lock().unwrap();
+        // Taken for writing right away, so that hosts sharing the database never both record
+        // a draw or take a turn
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(sqlite_error)?;
+        let recorded: Option<String> = tx
+            .query_row(
+                "SELECT value FROM ballot_draws WHERE key = ?1 AND expires_at > ?2",
+                params![key, now],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        if let Some(recorded) = recorded {
+            return serde_json::from_str(&recorded).map_err(json_error);
+        }
+
+        let draw = match turn {
+            Some((turn_key, interval)) if !take_turn(&tx, turn_key, interval)? => draw.undrawn(),
+            _ => draw.clone(),
+        };
+        let value = serde_json::to_string(&draw).map_err(json_error)?;
+        // An expired draw is replaced as if it were missing
+        tx.execute(
+            "INSERT INTO ballot_draws (key, value, expires_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (key) DO UPDATE SET
+                 value = excluded.value,
+                 expires_at = excluded.expires_at",
+            params![key, value, now.saturating_add(ttl as i64)],
+        )
+        .map_err(sqlite_error)?;
+        tx.commit().map_err(sqlite_error)?;
+        Ok(draw)
+    }
+
+    pub fn get_ballot_draw(&mut self, key: &str) -> RedisResult<Option<BallotDraw>> {
+        let conn = self.conn.lock().unwrap();
+        let value: Option<String> = conn
+            .query_row(
+                "SELECT value FROM ballot_draws WHERE key = ?1 AND expires_at > ?2",
+                params![key, Utc::now().timestamp()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        value
+            .map(|value| serde_json::from_str(&value).map_err(json_error))
+            .transpose()
+    }
+
+    pub fn ballot_draws(&mut self) -> RedisResult<Vec<BallotDraw>> {
+        let now = Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM ballot_draws WHERE expires_at <= ?1",
+            params![now],
+        )
+        .map_err(sqlite_error)?;
+        let mut statement = conn
+            .prepare("SELECT value FROM ballot_draws")
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        let mut draws = Vec::new();
+        for row in rows {
+            draws.push(serde_json::from_str(&row.map_err(sqlite_error)?).map_err(json_error)?);
+        }
+        Ok(draws)
+    }
+
+    pub fn take_ballot_turn(&mut self, key: &str, interval: Duration) -> RedisResult<bool> {
+        take_turn(&self.conn.lock().unwrap(), key, interval)
+    }
+
+    pub fn last_ballot_draw_time(&mut self, key: &str) -> RedisResult<Option<DateTime<Utc>>> {
+        let conn = self.conn.lock().unwrap();
+        let drawn_at_ms: Option<i64> = conn
+            .query_row(
+                "SELECT drawn_at_ms FROM ballot_last_draws WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        Ok(drawn_at_ms.and_then(DateTime::from_timestamp_millis))
+    }
+}
+
+// Set the last draw time at `key` to now, unless the last draw is more recent than `interval`.
+fn take_turn(conn: &Connection, key: &str, interval: Duration) -> RedisResult<bool> {
+    let now_ms = Utc::now().timestamp_millis();
+    let interval_ms: i64 = interval.as_millis().try_into().unwrap_or(i64::MAX);
+    // A single statement, so that hosts sharing the database never both take a turn
+    let changes = conn
+        .execute(
+            "INSERT INTO ballot_last_draws (key, drawn_at_ms) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET drawn_at_ms = excluded.drawn_at_ms
+             WHERE excluded.drawn_at_ms - ballot_last_draws.drawn_at_ms >= ?3",
+            params![key, now_ms, interval_ms],
+        )
+        .map_err(sqlite_error)?;
+    Ok(changes == 1)
+}
+
+fn expires_at_ms(now: DateTime<Utc>, ttl: Duration) -> i64 {
+    now.timestamp_millis()
+        .saturating_add(ttl.as_millis().try_into().unwrap_or(i64::MAX))
+}
+
+// The indexed columns of an entry, only set for the requests.
+fn index_columns(key: &Value, val: &Value) -> (Option<String>, Option<String>, Option<i64>) {
+    let Ok(request_key) = RequestKey::deserialize(key) else {
+        return (None, None, None);
+    };
+    let status = val
+        .get("status")
+        .and_then(|status| StatusWithContext::deserialize(status).ok())
+        .map(|status| status.status().name().to_string());
+    (
+        status,
+        Some(request_key.proof_type().to_string()),
+        request_key.chain_id().map(|chain_id| chain_id as i64),
+    )
+}
+
+fn sqlite_error(e: rusqlite::Error) -> RedisError {
+    RedisError::from((redis::ErrorKind::IoError, "sqlite error", e.to_string()))
+}
+
+fn json_error(e: serde_json::Error) -> RedisError {
+    RedisError::from((
+        redis::ErrorKind::TypeError,
+        "deserialization error",
+        e.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        GuestInputRequestEntity, GuestInputRequestKey, Pool, RedisPoolConfig, RequestEntity, Status,
+    };
+    use raiko_lib::{input::BlobProofType, primitives::B256, proof_type::ProofType};
+    use std::collections::HashMap;
+
+    fn sqlite_pool(path: &Path) -> Pool {
+        Pool::open(RedisPoolConfig {
+            redis_url: "redis://localhost:6379".to_string(),
+            redis_ttl: 3600,
+            enable_redis_pool: false,
+            sqlite_path: Some(path.to_path_buf()),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sqlite_backend() {
+        let mut conn = SqliteBackend::open_in_memory().unwrap();
+
+        let key = "hello".to_string();
+        let val = "world".to_string();
+        conn.set_ex(key.clone(), val.clone(), 111).unwrap();
+        let actual: RedisResult<String> = conn.get(&key);
+        assert_eq!(actual, Ok(val));
+        assert_eq!(conn.keys::<String>("*").unwrap(), vec![key.clone()]);
+
+        assert_eq!(conn.del(&key).unwrap(), 1);
+        let actual: RedisResult<String> = conn.get(&key);
+        assert!(actual.is_err());
+        assert_eq!(conn.del(&key).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sqlite_backend_expires_entries() {
+        let mut conn = SqliteBackend::open_in_memory().unwrap();
+        conn.set_ex("expired".to_string(), "value".to_string(), 0)
+            .unwrap();
+        let actual: RedisResult<String> = conn.get(&"expired".to_string());
+        assert!(actual.is_err());
+        assert!(conn.keys::<String>("*").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sqlite_pool_persists_and_filters_requests() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.sqlite");
+        let request = |chain_id, block_number| {
+            let key = RequestKey::GuestInput(GuestInputRequestKey::new(
+                chain_id,
+                block_number,
+                B256::ZERO,
+            ));
+            let entity = RequestEntity::GuestInput(GuestInputRequestEntity::new(
+                block_number,
+                5678,
+                "taiko_mainnet".to_string(),
+                "ethereum".to_string(),
+                B256::ZERO,
+                BlobProofType::ProofOfEquivalence,
+                HashMap::new(),
+            ));
+            (key, entity)
+        };
+
+        {
+            let mut pool = sqlite_pool(&path);
+            for (chain_id, block_number) in [(1, 1), (1, 2), (2, 1)] {
+                let (key, entity) = request(chain_id, block_number);
+                pool.add(key, entity, StatusWithContext::new_registered())
+                    .unwrap();
+            }
+            let (key, _) = request(1, 2);
+            pool.update_status(
+                key,
+                StatusWithContext::new_registered().transition(Status::WorkInProgress),
+            )
+            .unwrap();
+        }
+
+        // The requests survive reopening the pool
+        let mut pool = sqlite_pool(&path);
+        assert_eq!(pool.list().unwrap().len(), 3);
+
+        let filter = RequestFilter {
+            chain_id: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(pool.list_by(&filter).unwrap().len(), 2);
+
+        let filter = RequestFilter {
+            status: Some("work_in_progress".to_string()),
+            ..Default::default()
+        };
+        let listed = pool.list_by(&filter).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed.contains_key(&request(1, 2).0));
+
+        let filter = RequestFilter {
+            proof_type: Some(ProofType::Sp1),
+            ..Default::default()
+        };
+        assert!(pool.list_by(&filter).unwrap().is_empty());
+
+        let filter = RequestFilter {
+            from: Some(2),
+            to: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            pool.list_by(&filter)
+                .unwrap()
+                .into_keys()
+                .collect::<Vec<_>>(),
+            vec![request(1, 2).0]
+        );
+
+        let filter = RequestFilter {
+            since: Some(Utc::now()),
+            ..Default::default()
+        };
+        assert!(pool.list_by(&filter).unwrap().is_empty());
+        let filter = RequestFilter {
+            until: Some(Utc::now()),
+            ..Default::default()
+        };
+        assert_eq!(pool.list_by(&filter).unwrap().len(), 3);
+    }
+}
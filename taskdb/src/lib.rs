@@ -166,4 +166,5 @@ pub enum TaskDescriptor {
 
 pub type TaskReport = (TaskDescriptor, TaskStatus);
 
-pub type AggregationTaskReport = (AggregationOnlyRequest, TaskStatus);
+/// The pool only keeps the descriptor of the aggregation requests, not the proofs to aggregate.
+pub type AggregationTaskReport = (AggregationTaskDescriptor, TaskStatus);